@@ -0,0 +1,115 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::relative_link;
+
+/// The directory, beside the document, in which images are stored.
+const ASSETS_DIR: &str = "assets";
+
+/// The file extensions of images which may be inserted into a document.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+/// Whether the file at the path is an image.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .map_or(false, |ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Obtains an unused path within the assets directory for a new pasted image, creating the
+/// assets directory if it does not exist.
+pub fn new_image_path(document_dir: &Path, extension: &str) -> io::Result<PathBuf> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    unused_path(document_dir, &format!("pasted-{}", seconds), extension)
+}
+
+/// Copies the image into the assets directory beside the document, and returns the path of
+/// the copy. Images that are already within the document's directory are not copied.
+pub fn import_image(document_dir: &Path, image: &Path) -> io::Result<PathBuf> {
+    if image.starts_with(document_dir) {
+        return Ok(image.to_path_buf());
+    }
+
+    let stem = image.file_stem().map_or("image".into(), |stem| stem.to_string_lossy());
+    let extension = image.extension().map_or("".into(), |ext| ext.to_string_lossy());
+    let destination = unused_path(document_dir, &stem, &extension)?;
+    fs::copy(image, &destination)?;
+    Ok(destination)
+}
+
+/// Creates the markdown for an image, relative to the document's directory.
+pub fn image_markdown(document_dir: &Path, image: &Path) -> String {
+    let alt = image.file_stem().map_or("".into(), |stem| stem.to_string_lossy());
+    format!("![{}]({})", alt, relative_link(document_dir, image))
+}
+
+/// Finds a file name within the assets directory that is not taken, by appending a number to
+/// the name if necessary.
+fn unused_path(document_dir: &Path, name: &str, extension: &str) -> io::Result<PathBuf> {
+    let assets = document_dir.join(ASSETS_DIR);
+    fs::create_dir_all(&assets)?;
+
+    let file_name = |suffix: String| if extension.is_empty() {
+        [name, suffix.as_str()].concat()
+    } else {
+        [name, suffix.as_str(), ".", extension].concat()
+    };
+
+    let mut path = assets.join(file_name(String::new()));
+    let mut number = 1;
+    while path.exists() {
+        path = assets.join(file_name(format!("-{}", number)));
+        number += 1;
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::process;
+
+    #[test]
+    fn images_are_recognized_by_their_extension() {
+        assert!(is_image(Path::new("photo.JPG")));
+        assert!(is_image(Path::new("/a/diagram.svg")));
+        assert!(!is_image(Path::new("notes.md")));
+        assert!(!is_image(Path::new("png")));
+    }
+
+    #[test]
+    fn image_links_are_relative_to_the_document() {
+        let markdown = image_markdown(Path::new("/notes"), Path::new("/notes/assets/my cat.png"));
+        assert_eq!(markdown, "![my cat](assets/my%20cat.png)");
+    }
+
+    #[test]
+    fn images_are_copied_to_unused_paths() {
+        let dir = env::temp_dir().join(format!("assets-test-{}", process::id()));
+        let (document_dir, outside) = (dir.join("notes"), dir.join("cat.png"));
+        fs::create_dir_all(&document_dir).unwrap();
+        File::create(&outside).unwrap();
+        let inside = document_dir.join("dog.png");
+        File::create(&inside).unwrap();
+
+        let first = import_image(&document_dir, &outside).unwrap();
+        let second = import_image(&document_dir, &outside).unwrap();
+        let kept = import_image(&document_dir, &inside).unwrap();
+        let pasted = new_image_path(&document_dir, "png").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, document_dir.join("assets/cat.png"));
+        assert_eq!(second, document_dir.join("assets/cat-1.png"));
+        assert_eq!(kept, inside);
+        assert_eq!(pasted.parent(), Some(document_dir.join("assets").as_path()));
+        assert!(pasted.file_name().unwrap().to_string_lossy().starts_with("pasted-"));
+    }
+}
@@ -0,0 +1,361 @@
+use outline::headings;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::{percent_decode, percent_encode};
+use wiki::note_name;
+
+/// The greatest number of proposals that will be offered at once.
+const MAX_PROPOSALS: usize = 50;
+
+/// Emoji shortcodes which may be completed after typing a colon, and their characters.
+const EMOJI: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("check", "✔️"),
+    ("clap", "👏"),
+    ("construction", "🚧"),
+    ("cry", "😢"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("grin", "😁"),
+    ("heart", "❤️"),
+    ("hourglass", "⌛"),
+    ("joy", "😂"),
+    ("laughing", "😆"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("memo", "📝"),
+    ("no_entry", "⛔"),
+    ("ok_hand", "👌"),
+    ("pencil", "✏️"),
+    ("point_right", "👉"),
+    ("pray", "🙏"),
+    ("question", "❓"),
+    ("rocket", "🚀"),
+    ("sad", "😞"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("sunglasses", "😎"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("x", "❌"),
+    ("zap", "⚡"),
+];
+
+/// The sources of completions, each of which is triggered by the text before the cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Provider {
+    /// The anchors of the document's headings, after `](#`.
+    Anchor,
+    /// Files relative to the document's directory, after `](`.
+    Path,
+    /// The labels of the document's reference definitions, after `][`.
+    Reference,
+    /// Emoji shortcodes, after `:`.
+    Emoji,
+    /// The names of the open folder's documents, after `[[`.
+    Note,
+}
+
+/// Describes the text before the cursor that is being completed.
+pub struct Trigger {
+    pub provider: Provider,
+    /// The byte index within the line where the text that a proposal replaces begins.
+    pub start:    usize,
+    /// The text that proposals are matched against.
+    pub query:    String,
+}
+
+/// A completion that may be offered to the user.
+pub struct Proposal {
+    /// The text which is displayed within the list of proposals.
+    pub label: String,
+    /// The text which replaces the trigger's text when the proposal is chosen.
+    pub text:  String,
+}
+
+/// Determines whether the text before the cursor, on the cursor's line, should be completed,
+/// and by which provider.
+pub fn trigger(before: &str) -> Option<Trigger> {
+    // Note names may contain whitespace, and end with the link's closing brackets.
+    if let Some(start) = before.rfind("[[").map(|index| index + 2) {
+        let query = &before[start..];
+        if !query.contains(|c| c == '[' || c == ']') {
+            return Some(Trigger { provider: Provider::Note, start, query: query.to_owned() });
+        }
+    }
+
+    // Link destinations and reference labels can not contain whitespace or closing brackets.
+    let destination = before.rfind("](").map(|index| index + 2);
+    let label = before.rfind("][").map(|index| index + 2);
+    let latest = match (destination, label) {
+        (Some(d), Some(l)) if l > d => Some((Provider::Reference, l)),
+        (Some(d), _) => Some((Provider::Path, d)),
+        (None, Some(l)) => Some((Provider::Reference, l)),
+        (None, None) => None,
+    };
+
+    if let Some((provider, start)) = latest {
+        let text = &before[start..];
+        let closed = text.contains(|c: char| c.is_whitespace() || c == ')' || c == ']');
+        if !closed {
+            return Some(if provider == Provider::Path && text.starts_with('#') {
+                Trigger { provider: Provider::Anchor, start, query: text[1..].to_owned() }
+            } else {
+                Trigger { provider, start, query: text.to_owned() }
+            });
+        }
+    }
+
+    // Emoji shortcodes begin a word, and are only completed once two characters are typed.
+    let start = match before.rfind(':') {
+        Some(start) => start,
+        None => return None,
+    };
+    let query = &before[start + 1..];
+    let starts_word = before[..start].chars().next_back().map_or(true, |c| c.is_whitespace());
+    let is_shortcode = query.chars().all(|c| c.is_alphanumeric() || "_+-".contains(c));
+    if starts_word && is_shortcode && query.len() >= 2 {
+        Some(Trigger { provider: Provider::Emoji, start, query: query.to_owned() })
+    } else {
+        None
+    }
+}
+
+/// Collects the proposals for the trigger from the document, the files beside it, and the
+/// documents of the open folder.
+pub fn proposals(
+    trigger: &Trigger,
+    markdown: &str,
+    dir: Option<&Path>,
+    notes: &[PathBuf],
+) -> Vec<Proposal> {
+    let query = trigger.query.to_lowercase();
+    let mut proposals = match trigger.provider {
+        Provider::Anchor => headings(markdown)
+            .into_iter()
+            .filter(|heading| {
+                heading.anchor.starts_with(&query) || heading.title.to_lowercase().contains(&query)
+            })
+            .map(|heading| Proposal {
+                label: format!("{} (#{})", heading.title, heading.anchor),
+                text:  ["#", heading.anchor.as_str()].concat(),
+            })
+            .collect(),
+        Provider::Path => match dir {
+            Some(dir) => files(dir, &trigger.query),
+            None => Vec::new(),
+        },
+        Provider::Reference => reference_labels(markdown)
+            .into_iter()
+            .filter(|label| label.to_lowercase().starts_with(&query))
+            .map(|label| Proposal { label: label.clone(), text: label })
+            .collect(),
+        Provider::Emoji => EMOJI
+            .iter()
+            .filter(|&&(name, _)| name.starts_with(&query))
+            .map(|&(name, emoji)| Proposal {
+                label: format!("{} :{}:", emoji, name),
+                text:  emoji.to_owned(),
+            })
+            .collect(),
+        Provider::Note => note_names(notes, &query)
+            .into_iter()
+            .map(|name| Proposal { label: name.clone(), text: [name.as_str(), "]]"].concat() })
+            .collect(),
+    };
+
+    proposals.truncate(MAX_PROPOSALS);
+    proposals
+}
+
+/// Lists the files which match the partially-typed relative path, from the directory that
+/// the path leads to. Directories are suffixed with a slash, so that they may be completed
+/// further.
+fn files(document_dir: &Path, typed: &str) -> Vec<Proposal> {
+    let (parent, name) = match typed.rfind('/') {
+        Some(slash) => (&typed[..slash + 1], &typed[slash + 1..]),
+        None => ("", typed),
+    };
+
+    let entries = match fs::read_dir(document_dir.join(percent_decode(parent))) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let name = percent_decode(name);
+    let mut proposals: Vec<Proposal> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            // Hidden files are only offered when a name beginning with a dot is typed.
+            if !file_name.starts_with(&name) || (file_name.starts_with('.') && name.is_empty()) {
+                return None;
+            }
+
+            let is_dir = entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false);
+            let suffix = if is_dir { "/" } else { "" };
+            Some(Proposal {
+                label: [file_name.as_str(), suffix].concat(),
+                text:  [parent, percent_encode(&file_name).as_str(), suffix].concat(),
+            })
+        })
+        .collect();
+
+    proposals.sort_by(|a, b| a.label.cmp(&b.label));
+    proposals
+}
+
+/// Collects the names of the notes which contain the lowercase query, with the names that
+/// begin with it first.
+fn note_names(notes: &[PathBuf], query: &str) -> Vec<String> {
+    let mut names: Vec<String> = notes
+        .iter()
+        .map(|note| note_name(note))
+        .filter(|name| name.to_lowercase().contains(query))
+        .collect();
+
+    names.sort_by_key(|name| (!name.to_lowercase().starts_with(query), name.to_lowercase()));
+    names.dedup();
+    names
+}
+
+/// Collects the labels of the reference definitions within the document, such as the `docs`
+/// of `[docs]: https://example.com`.
+fn reference_labels(markdown: &str) -> Vec<String> {
+    let mut labels: Vec<String> = markdown
+        .lines()
+        .filter(|line| line.len() - line.trim_left().len() < 4)
+        .filter_map(|line| {
+            let line = line.trim_left();
+            if !line.starts_with('[') || line.starts_with("[^") {
+                return None;
+            }
+            line.find("]:").map(|end| line[1..end].to_owned())
+        })
+        .filter(|label| !label.is_empty() && !label.contains('['))
+        .collect();
+
+    labels.sort();
+    labels.dedup();
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+
+    fn triggered(before: &str) -> Option<(Provider, usize, String)> {
+        trigger(before).map(|trigger| (trigger.provider, trigger.start, trigger.query))
+    }
+
+    fn texts(proposals: Vec<Proposal>) -> Vec<String> {
+        proposals.into_iter().map(|proposal| proposal.text).collect()
+    }
+
+    #[test]
+    fn links_trigger_completion() {
+        assert_eq!(triggered("see [docs](#get"), Some((Provider::Anchor, 11, "get".into())));
+        assert_eq!(triggered("see [docs](images/a"), Some((Provider::Path, 11, "images/a".into())));
+        assert_eq!(triggered("[docs](a) and [b][re"), Some((Provider::Reference, 18, "re".into())));
+        assert_eq!(triggered("[[Some No"), Some((Provider::Note, 2, "Some No".into())));
+    }
+
+    #[test]
+    fn closed_links_do_not_trigger_completion() {
+        assert_eq!(triggered("[docs](a.md) and"), None);
+        assert_eq!(triggered("[docs](a.md "), None);
+        assert_eq!(triggered("[[Note]] and"), None);
+        assert_eq!(triggered("[a][b] c"), None);
+        assert_eq!(triggered("plain text"), None);
+    }
+
+    #[test]
+    fn emoji_begin_words() {
+        assert_eq!(triggered("nice :ta"), Some((Provider::Emoji, 5, "ta".into())));
+        assert_eq!(triggered(":+1"), Some((Provider::Emoji, 0, "+1".into())));
+        assert_eq!(triggered("nice :t"), None);
+        assert_eq!(triggered("12:30"), None);
+        assert_eq!(triggered("nice :ta da"), None);
+    }
+
+    #[test]
+    fn anchors_and_labels_are_proposed() {
+        let markdown = "# Getting Started\n\n## Usage\n\n[docs]: https://a.b\n[Downloads]: d.md\n";
+
+        let anchors = proposals(&trigger("[a](#us").unwrap(), markdown, None, &[]);
+        assert_eq!(texts(anchors), vec!["#usage"]);
+        let anchors = proposals(&trigger("[a](#start").unwrap(), markdown, None, &[]);
+        assert_eq!(texts(anchors), vec!["#getting-started"]);
+
+        let labels = proposals(&trigger("[a][d").unwrap(), markdown, None, &[]);
+        assert_eq!(texts(labels), vec!["Downloads", "docs"]);
+    }
+
+    #[test]
+    fn footnotes_and_indented_code_are_not_labels() {
+        let markdown = "[^1]: A footnote\n    [code]: x\n [ok]: y\n[]: z\n";
+        assert_eq!(reference_labels(markdown), vec!["ok"]);
+    }
+
+    #[test]
+    fn emoji_are_proposed() {
+        let emoji = proposals(&trigger(":smi").unwrap(), "", None, &[]);
+        let labels: Vec<&str> = emoji.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["😄 :smile:", "😃 :smiley:"]);
+        assert_eq!(emoji[0].text, "😄");
+    }
+
+    #[test]
+    fn notes_beginning_with_the_query_come_first() {
+        let notes = [
+            PathBuf::from("b/Recipes.md"),
+            PathBuf::from("Cooking.md"),
+            PathBuf::from("Coffee.md"),
+            PathBuf::from("a/Coffee.md"),
+        ];
+        let names = proposals(&trigger("[[co").unwrap(), "", None, &notes);
+        assert_eq!(texts(names), vec!["Coffee]]", "Cooking]]"]);
+
+        let names = proposals(&trigger("[[ip").unwrap(), "", None, &notes);
+        assert_eq!(texts(names), vec!["Recipes]]"]);
+    }
+
+    #[test]
+    fn files_are_proposed_relative_to_the_document() {
+        let dir = env::temp_dir().join(format!("completion-test-{}", ::std::process::id()));
+        fs::create_dir_all(dir.join("my images")).unwrap();
+        File::create(dir.join("notes.md")).unwrap();
+        File::create(dir.join(".hidden")).unwrap();
+        File::create(dir.join("my images/cat.png")).unwrap();
+
+        let list = |before: &str| {
+            let files = proposals(&trigger(before).unwrap(), "", Some(&dir), &[]);
+            files.into_iter().map(|p| (p.label, p.text)).collect::<Vec<_>>()
+        };
+        let everything = list("[a](");
+        let in_folder = list("[a](my%20images/c");
+        let hidden = list("[a](.h");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            everything,
+            vec![
+                ("my images/".to_owned(), "my%20images/".to_owned()),
+                ("notes.md".to_owned(), "notes.md".to_owned()),
+            ]
+        );
+        assert_eq!(in_folder, vec![("cat.png".to_owned(), "my%20images/cat.png".to_owned())]);
+        assert_eq!(hidden, vec![(".hidden".to_owned(), ".hidden".to_owned())]);
+    }
+}
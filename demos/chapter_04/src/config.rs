@@ -0,0 +1,10 @@
+use std::env;
+use std::path::PathBuf;
+
+/// The directory in which the editor's configuration files are stored.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("cmark-editor"))
+}
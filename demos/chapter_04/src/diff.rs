@@ -0,0 +1,209 @@
+/// A line within the difference between two texts.
+pub enum Line<'a> {
+    /// A line which both texts share.
+    Same(&'a str),
+    /// A line which only the old text has.
+    Removed(&'a str),
+    /// A line which only the new text has.
+    Added(&'a str),
+}
+
+/// The greatest number of lines that may be removed and added in the middle of the texts
+/// before the comparison gives up on finding the fewest, and replaces the middle outright.
+const MAX_EDITS: usize = 1000;
+
+/// Compares the two texts line by line, finding the fewest lines that must be removed from
+/// and added to the old text to produce the new text.
+pub fn diff<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Lines shared at the start and end are set aside, as they are usually the majority of
+    // the document.
+    let prefix = old.iter().zip(new.iter()).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut lines: Vec<Line> = old[..prefix].iter().map(|&line| Line::Same(line)).collect();
+    match shortest_edit(old_middle, new_middle, MAX_EDITS) {
+        Some(middle) => lines.extend(middle),
+        None => {
+            lines.extend(old_middle.iter().map(|&line| Line::Removed(line)));
+            lines.extend(new_middle.iter().map(|&line| Line::Added(line)));
+        }
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|&line| Line::Same(line)));
+    lines
+}
+
+/// Finds the fewest lines to remove and add with Myers' algorithm, whose time grows with the
+/// length of the texts multiplied by the number of edits, rather than with the product of
+/// their lengths. **None** is returned if more than `max` edits are needed.
+fn shortest_edit<'a>(old: &[&'a str], new: &[&'a str], max: usize) -> Option<Vec<Line<'a>>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+
+    // After each number of edits `d`, the furthest that each diagonal `k = x - y` reaches
+    // into the old text, where the diagonal's entry is at index `k + d`.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..max.min(old.len() + new.len()) as isize + 1 {
+        let mut furthest = vec![0isize; 2 * d as usize + 1];
+        let mut reached = false;
+        {
+            let previous = |k: isize| trace.last().map_or(0, |v| v[(k + d - 1) as usize]);
+            let mut k = -d;
+            while k <= d {
+                // Either a line is added, moving down from the diagonal above, or a line is
+                // removed, moving right from the diagonal below.
+                let mut x = if d == 0 {
+                    0
+                } else if k == -d || (k != d && previous(k - 1) < previous(k + 1)) {
+                    previous(k + 1)
+                } else {
+                    previous(k - 1) + 1
+                };
+                let mut y = x - k;
+                while x < n && y < m && old[x as usize] == new[y as usize] {
+                    x += 1;
+                    y += 1;
+                }
+                furthest[(k + d) as usize] = x;
+                reached |= x >= n && y >= m;
+                k += 2;
+            }
+        }
+
+        trace.push(furthest);
+        if reached {
+            return Some(backtrack(old, new, &trace));
+        }
+    }
+
+    None
+}
+
+/// Follows the edits recorded by `shortest_edit` back from the end of both texts.
+fn backtrack<'a>(old: &[&'a str], new: &[&'a str], trace: &[Vec<isize>]) -> Vec<Line<'a>> {
+    let mut lines = Vec::new();
+    let (mut x, mut y) = (old.len() as isize, new.len() as isize);
+    for d in (1..trace.len() as isize).rev() {
+        let previous = |k: isize| trace[d as usize - 1][(k + d - 1) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && previous(k - 1) < previous(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = previous(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            lines.push(Line::Same(old[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+
+        if x == previous_x {
+            lines.push(Line::Added(new[y as usize - 1]));
+        } else {
+            lines.push(Line::Removed(old[x as usize - 1]));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+
+    while x > 0 && y > 0 {
+        lines.push(Line::Same(old[x as usize - 1]));
+        x -= 1;
+        y -= 1;
+    }
+
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the difference as in a unified diff, with a line for each line of the texts.
+    fn unified(old: &str, new: &str) -> Vec<String> {
+        diff(old, new)
+            .into_iter()
+            .map(|line| match line {
+                Line::Same(text) => [" ", text].concat(),
+                Line::Removed(text) => ["-", text].concat(),
+                Line::Added(text) => ["+", text].concat(),
+            })
+            .collect()
+    }
+
+    fn edits(old: &str, new: &str) -> usize {
+        unified(old, new).iter().filter(|line| !line.starts_with(' ')).count()
+    }
+
+    #[test]
+    fn identical_texts() {
+        assert_eq!(unified("a\nb", "a\nb"), vec![" a", " b"]);
+        assert!(unified("", "").is_empty());
+    }
+
+    #[test]
+    fn lines_are_added_and_removed() {
+        assert_eq!(unified("", "a\nb"), vec!["+a", "+b"]);
+        assert_eq!(unified("a\nb", ""), vec!["-a", "-b"]);
+        assert_eq!(unified("a\nc", "a\nb\nc"), vec![" a", "+b", " c"]);
+        assert_eq!(unified("a\nb\nc", "a\nc"), vec![" a", "-b", " c"]);
+        assert_eq!(unified("a\nb\nc", "a\nB\nc"), vec![" a", "-b", "+B", " c"]);
+    }
+
+    #[test]
+    fn the_fewest_edits_are_found() {
+        assert_eq!(edits("a\nb\nc\na\nb\nb\na", "c\nb\na\nb\na\nc"), 5);
+        assert_eq!(
+            unified("x\na\ny\nb\nz", "a\nq\nb"),
+            vec!["-x", " a", "-y", "+q", " b", "-z"]
+        );
+    }
+
+    #[test]
+    fn applying_the_difference_produces_the_new_text() {
+        let old = "one\ntwo\nthree\nfour\nfive\nsix\nseven";
+        let new = "zero\none\nthree\nfour\n4.5\nsix\nseven\neight";
+        let (mut before, mut after) = (Vec::new(), Vec::new());
+        for line in diff(old, new) {
+            match line {
+                Line::Same(text) => {
+                    before.push(text);
+                    after.push(text);
+                }
+                Line::Removed(text) => before.push(text),
+                Line::Added(text) => after.push(text),
+            }
+        }
+        assert_eq!(before.join("\n"), old);
+        assert_eq!(after.join("\n"), new);
+        assert_eq!(edits(old, new), 5);
+    }
+
+    #[test]
+    fn large_differences_replace_the_middle() {
+        let old: Vec<String> = (0..MAX_EDITS).map(|n| format!("old {}", n)).collect();
+        let new: Vec<String> = (0..MAX_EDITS).map(|n| format!("new {}", n)).collect();
+        let old = ["first", &old.join("\n"), "last"].join("\n");
+        let new = ["first", &new.join("\n"), "last"].join("\n");
+
+        let lines = unified(&old, &new);
+        assert_eq!(lines.len(), 2 * MAX_EDITS + 2);
+        assert_eq!(lines[0], " first");
+        let (removed, added) = lines[1..2 * MAX_EDITS + 1].split_at(MAX_EDITS);
+        assert!(removed.iter().all(|line| line.starts_with("-old")));
+        assert!(added.iter().all(|line| line.starts_with("+new")));
+        assert_eq!(lines[2 * MAX_EDITS + 1], " last");
+    }
+}
@@ -0,0 +1,167 @@
+/// The states that the document being edited may be in, which determine what the header bar
+/// and the window's title display.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DocumentState {
+    /// A new document, which has not been edited.
+    UntitledClean,
+    /// A new document, which has been edited but not saved.
+    UntitledDirty,
+    /// A document which is the same as its file.
+    SavedClean,
+    /// A document which has been edited since it was read from or written to its file.
+    SavedDirty,
+    /// The document is being written to its file.
+    Saving { untitled: bool },
+    /// The document could not be written to its file, and so has not been saved.
+    Error { untitled: bool, message: String },
+}
+
+/// What may happen to the document.
+pub enum DocumentEvent {
+    /// The document was modified, or was returned to how it was when it was last saved.
+    Modified(bool),
+    /// A file was read into the editor.
+    Opened,
+    SaveBegan,
+    /// The document was written, and may have been modified since the writing began.
+    Saved { modified: bool },
+    /// The document was not written, as the user decided against it.
+    SaveCanceled { modified: bool },
+    SaveFailed(String),
+}
+
+impl Default for DocumentState {
+    fn default() -> DocumentState { DocumentState::UntitledClean }
+}
+
+impl DocumentState {
+    /// The state that the document is in after the event.
+    pub fn next(&self, event: DocumentEvent) -> DocumentState {
+        let untitled = self.is_untitled();
+        match (self, event) {
+            (_, DocumentEvent::Opened) => DocumentState::SavedClean,
+            (_, DocumentEvent::SaveBegan) => DocumentState::Saving { untitled },
+            (_, DocumentEvent::Saved { modified }) => DocumentState::edited(false, modified),
+            (_, DocumentEvent::SaveCanceled { modified }) => {
+                DocumentState::edited(untitled, modified)
+            }
+            (_, DocumentEvent::SaveFailed(message)) => DocumentState::Error { untitled, message },
+            // The outcome of the save decides the state once it has finished.
+            (&DocumentState::Saving { .. }, DocumentEvent::Modified(_)) => self.clone(),
+            // The error remains until the document is saved, or its changes are undone.
+            (&DocumentState::Error { .. }, DocumentEvent::Modified(true)) => self.clone(),
+            (_, DocumentEvent::Modified(modified)) => DocumentState::edited(untitled, modified),
+        }
+    }
+
+    fn edited(untitled: bool, modified: bool) -> DocumentState {
+        match (untitled, modified) {
+            (true, false) => DocumentState::UntitledClean,
+            (true, true) => DocumentState::UntitledDirty,
+            (false, false) => DocumentState::SavedClean,
+            (false, true) => DocumentState::SavedDirty,
+        }
+    }
+
+    /// Whether the document has not been saved to a file.
+    pub fn is_untitled(&self) -> bool {
+        match *self {
+            DocumentState::UntitledClean | DocumentState::UntitledDirty => true,
+            DocumentState::Saving { untitled } | DocumentState::Error { untitled, .. } => untitled,
+            DocumentState::SavedClean | DocumentState::SavedDirty => false,
+        }
+    }
+
+    /// Whether the document has changes which may be saved.
+    pub fn can_save(&self) -> bool {
+        match *self {
+            DocumentState::UntitledDirty
+            | DocumentState::SavedDirty
+            | DocumentState::Error { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the document may be saved as another file.
+    pub fn can_save_as(&self) -> bool {
+        match *self {
+            DocumentState::Saving { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// Titles the document of the given name. Documents with unsaved changes are marked with
+    /// `*`.
+    pub fn title(&self, name: &str) -> String {
+        match *self {
+            DocumentState::UntitledClean | DocumentState::SavedClean => name.to_owned(),
+            DocumentState::UntitledDirty | DocumentState::SavedDirty => ["*", name].concat(),
+            DocumentState::Saving { .. } => format!("{} (Saving…)", name),
+            DocumentState::Error { .. } => format!("*{} (Not Saved)", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use self::DocumentEvent::*;
+    use self::DocumentState::*;
+
+    fn error(untitled: bool) -> DocumentState {
+        Error { untitled, message: "denied".to_owned() }
+    }
+
+    #[test]
+    fn transitions() {
+        let table = vec![
+            (UntitledClean, Modified(true), UntitledDirty),
+            (UntitledDirty, Modified(false), UntitledClean),
+            (SavedClean, Modified(true), SavedDirty),
+            (SavedDirty, Modified(false), SavedClean),
+            (UntitledDirty, Opened, SavedClean),
+            (UntitledDirty, SaveBegan, Saving { untitled: true }),
+            (SavedDirty, SaveBegan, Saving { untitled: false }),
+            // Edits during a save leave the outcome to decide the state.
+            (Saving { untitled: false }, Modified(true), Saving { untitled: false }),
+            (Saving { untitled: true }, Modified(false), Saving { untitled: true }),
+            (Saving { untitled: false }, Opened, SavedClean),
+            (Saving { untitled: true }, Saved { modified: false }, SavedClean),
+            (Saving { untitled: false }, Saved { modified: true }, SavedDirty),
+            (Saving { untitled: true }, SaveCanceled { modified: true }, UntitledDirty),
+            (UntitledDirty, SaveCanceled { modified: true }, UntitledDirty),
+            (SavedDirty, SaveCanceled { modified: true }, SavedDirty),
+            (Saving { untitled: true }, SaveFailed("denied".to_owned()), error(true)),
+            (Saving { untitled: false }, SaveFailed("denied".to_owned()), error(false)),
+            // Errors remain until the document is saved, or its changes are undone.
+            (error(false), Modified(true), error(false)),
+            (error(false), Modified(false), SavedClean),
+            (error(true), Modified(false), UntitledClean),
+            (error(true), SaveBegan, Saving { untitled: true }),
+            (error(false), Saved { modified: false }, SavedClean),
+        ];
+
+        for (state, event, expected) in table {
+            let next = state.next(event);
+            assert_eq!(next, expected, "from {:?}", state);
+        }
+    }
+
+    #[test]
+    fn titles() {
+        assert_eq!(UntitledClean.title("Untitled"), "Untitled");
+        assert_eq!(SavedDirty.title("notes.md"), "*notes.md");
+        assert_eq!(Saving { untitled: false }.title("notes.md"), "notes.md (Saving…)");
+        assert_eq!(error(false).title("notes.md"), "*notes.md (Not Saved)");
+    }
+
+    #[test]
+    fn saving_is_allowed_only_with_changes() {
+        assert!(!SavedClean.can_save());
+        assert!(UntitledDirty.can_save());
+        assert!(error(true).can_save());
+        assert!(!Saving { untitled: false }.can_save());
+        assert!(!Saving { untitled: false }.can_save_as());
+        assert!(SavedClean.can_save_as());
+    }
+}
@@ -0,0 +1,381 @@
+/// The character encodings that documents may be read and written in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, in which every byte is the code point of the same value.
+    Latin1,
+    /// The Western European encoding of Windows, which extends Latin-1 with punctuation.
+    Windows1252,
+}
+
+/// The characters which end each line of a document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+/// How a document's text is stored within its file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextFormat {
+    pub encoding:    Encoding,
+    /// Whether the file begins with a byte order mark.
+    pub bom:         bool,
+    pub line_ending: LineEnding,
+}
+
+/// The text of a file, with its line endings normalized to `\n`, and how it was stored.
+pub struct Decoded {
+    pub text:   String,
+    pub format: TextFormat,
+}
+
+/// The characters of Windows-1252 which differ from Latin-1, for the bytes `0x80` to `0x9F`.
+/// Bytes which Windows-1252 leaves undefined are decoded as the control characters of
+/// Latin-1.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+impl Encoding {
+    pub const ALL: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Latin1,
+        Encoding::Windows1252,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16 LE",
+            Encoding::Utf16Be => "UTF-16 BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    /// The byte order mark which may begin files of the encoding.
+    pub fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+            Encoding::Latin1 | Encoding::Windows1252 => &[],
+        }
+    }
+
+    /// Decodes the bytes, which must not begin with a byte order mark. Returns **None** if
+    /// the bytes are not valid within the encoding.
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if bytes.len() % 2 != 0 {
+                    return None;
+                }
+
+                let units: Vec<u16> = bytes
+                    .chunks(2)
+                    .map(|pair| if self == Encoding::Utf16Le {
+                        u16::from(pair[0]) | u16::from(pair[1]) << 8
+                    } else {
+                        u16::from(pair[0]) << 8 | u16::from(pair[1])
+                    })
+                    .collect();
+                String::from_utf16(&units).ok()
+            }
+            Encoding::Latin1 => Some(bytes.iter().map(|&byte| char::from(byte)).collect()),
+            Encoding::Windows1252 => Some(
+                bytes
+                    .iter()
+                    .map(|&byte| match byte {
+                        0x80...0x9F => WINDOWS_1252[(byte - 0x80) as usize],
+                        byte => char::from(byte),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Encodes the text. Returns the first character that the encoding can not represent, if
+    /// there is one.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, char> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf16Le => Ok(text.encode_utf16()
+                .flat_map(|unit| vec![unit as u8, (unit >> 8) as u8])
+                .collect()),
+            Encoding::Utf16Be => Ok(text.encode_utf16()
+                .flat_map(|unit| vec![(unit >> 8) as u8, unit as u8])
+                .collect()),
+            Encoding::Latin1 => text.chars()
+                .map(|c| if (c as u32) < 0x100 { Ok(c as u8) } else { Err(c) })
+                .collect(),
+            Encoding::Windows1252 => text.chars()
+                .map(|c| match WINDOWS_1252.iter().position(|&special| special == c) {
+                    Some(index) => Ok(0x80 + index as u8),
+                    None if (c as u32) < 0x80 || (c as u32 >= 0xA0 && (c as u32) < 0x100) => {
+                        Ok(c as u8)
+                    }
+                    None => Err(c),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 3] = [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Cr => "CR",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// The line ending which is used the most within the text. Text without any line endings
+    /// uses `\n`.
+    pub fn detect(text: &str) -> LineEnding {
+        let crlf = text.matches("\r\n").count();
+        let cr = text.matches('\r').count() - crlf;
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf && crlf >= cr {
+            LineEnding::CrLf
+        } else if cr > lf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        }
+    }
+}
+
+impl Default for TextFormat {
+    fn default() -> TextFormat {
+        TextFormat { encoding: Encoding::Utf8, bom: false, line_ending: LineEnding::Lf }
+    }
+}
+
+impl TextFormat {
+    /// Describes the format within the status bar, such as `UTF-8 with BOM`.
+    pub fn encoding_name(&self) -> String {
+        if self.bom {
+            format!("{} with BOM", self.encoding.name())
+        } else {
+            self.encoding.name().to_owned()
+        }
+    }
+
+    /// Converts the text, whose lines end with `\n`, into the bytes of a file of this format.
+    /// Returns the first character that the encoding can not represent, if there is one.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, char> {
+        let text = match self.line_ending {
+            LineEnding::Lf => text.to_owned(),
+            ending => text.replace('\n', ending.as_str()),
+        };
+
+        let mut bytes = if self.bom { self.encoding.bom().to_vec() } else { Vec::new() };
+        bytes.extend(self.encoding.encode(&text)?);
+        Ok(bytes)
+    }
+}
+
+/// Decodes the bytes of a file with the encoding, skipping the encoding's byte order mark if
+/// the file begins with one. Line endings are normalized to `\n`.
+pub fn decode(bytes: &[u8], encoding: Encoding) -> Option<Decoded> {
+    let bom = !encoding.bom().is_empty() && bytes.starts_with(encoding.bom());
+    let content = if bom { &bytes[encoding.bom().len()..] } else { bytes };
+    encoding.decode(content).map(|text| {
+        let line_ending = LineEnding::detect(&text);
+        let text = if text.contains('\r') {
+            text.replace("\r\n", "\n").replace('\r', "\n")
+        } else {
+            text
+        };
+        Decoded { text, format: TextFormat { encoding, bom, line_ending } }
+    })
+}
+
+/// Determines the encoding of a file's bytes. Files which begin with a byte order mark, which
+/// are evidently UTF-16, or which are valid UTF-8, are decoded. Otherwise, the encoding is
+/// ambiguous, and the encodings that the file may be in are returned instead, most likely
+/// first.
+pub fn detect(bytes: &[u8]) -> Result<Decoded, Vec<Encoding>> {
+    for &encoding in &[Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+        if bytes.starts_with(encoding.bom()) {
+            if let Some(decoded) = decode(bytes, encoding) {
+                return Ok(decoded);
+            }
+        }
+    }
+
+    // ASCII text encoded as UTF-16 is also valid UTF-8, as its zero bytes decode as NUL.
+    if let Some(encoding) = utf16_without_bom(bytes) {
+        if let Some(decoded) = decode(bytes, encoding) {
+            return Ok(decoded);
+        }
+    }
+
+    // Text documents do not contain NUL, and so other files are not taken to be UTF-8.
+    if !bytes.contains(&0) {
+        if let Some(decoded) = decode(bytes, Encoding::Utf8) {
+            return Ok(decoded);
+        }
+    }
+
+    Err(vec![Encoding::Windows1252, Encoding::Latin1, Encoding::Utf16Le, Encoding::Utf16Be])
+}
+
+/// Text which is mostly ASCII has a zero in every other byte when it is encoded as UTF-16,
+/// which reveals the byte order.
+fn utf16_without_bom(bytes: &[u8]) -> Option<Encoding> {
+    let sample = &bytes[..bytes.len().min(4096)];
+    if sample.len() < 2 || bytes.len() % 2 != 0 {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let even = sample.chunks(2).filter(|pair| pair[0] == 0).count();
+    let odd = sample.chunks(2).filter(|pair| pair.len() == 2 && pair[1] == 0).count();
+    if odd * 10 >= pairs * 4 && even * 20 < pairs {
+        Some(Encoding::Utf16Le)
+    } else if even * 10 >= pairs * 4 && odd * 20 < pairs {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> { Encoding::Utf16Le.encode(text).unwrap() }
+
+    fn detected(bytes: &[u8]) -> (String, TextFormat) {
+        let decoded = detect(bytes).ok().expect("the encoding should be detected");
+        (decoded.text, decoded.format)
+    }
+
+    fn format(encoding: Encoding, bom: bool, line_ending: LineEnding) -> TextFormat {
+        TextFormat { encoding, bom, line_ending }
+    }
+
+    #[test]
+    fn utf8_is_detected() {
+        let (text, found) = detected("# Café\n".as_bytes());
+        assert_eq!(text, "# Café\n");
+        assert_eq!(found, TextFormat::default());
+
+        let (text, found) = detected(b"\xEF\xBB\xBFBOM\r\nnext\r\n");
+        assert_eq!(text, "BOM\nnext\n");
+        assert_eq!(found, format(Encoding::Utf8, true, LineEnding::CrLf));
+    }
+
+    #[test]
+    fn utf16_is_detected() {
+        let mut bytes = Encoding::Utf16Be.bom().to_vec();
+        bytes.extend(Encoding::Utf16Be.encode("Big end\n").unwrap());
+        let (text, found) = detected(&bytes);
+        assert_eq!(text, "Big end\n");
+        assert_eq!(found, format(Encoding::Utf16Be, true, LineEnding::Lf));
+
+        // ASCII text in UTF-16 is also valid UTF-8, but must not be read as such.
+        let (text, found) = detected(&utf16le("# Notes\r\nSome text.\r\n"));
+        assert_eq!(text, "# Notes\nSome text.\n");
+        assert_eq!(found, format(Encoding::Utf16Le, false, LineEnding::CrLf));
+
+        let (text, found) = detected(&Encoding::Utf16Be.encode("Without a mark").unwrap());
+        assert_eq!(text, "Without a mark");
+        assert_eq!(found.encoding, Encoding::Utf16Be);
+    }
+
+    #[test]
+    fn ambiguous_files_offer_candidates() {
+        let candidates = detect(b"caf\xE9 \x93quoted\x94").err().unwrap();
+        assert_eq!(candidates[0], Encoding::Windows1252);
+        assert!(candidates.contains(&Encoding::Latin1));
+
+        // Files containing NUL are not text, even if they happen to be valid UTF-8.
+        assert!(detect(b"PK\x03\x04\x00\x00binary\x00data, and more").is_err());
+    }
+
+    #[test]
+    fn single_byte_encodings_are_decoded() {
+        let bytes = b"\x93caf\xE9\x94 \x80";
+        assert_eq!(Encoding::Windows1252.decode(bytes).unwrap(), "\u{201C}café\u{201D} €");
+        assert_eq!(Encoding::Latin1.decode(bytes).unwrap(), "\u{93}café\u{94} \u{80}");
+        assert_eq!(Encoding::Utf8.decode(bytes), None);
+        assert_eq!(Encoding::Utf16Le.decode(b"odd"), None);
+    }
+
+    #[test]
+    fn unrepresentable_characters_are_reported() {
+        assert_eq!(Encoding::Latin1.encode("€"), Err('€'));
+        assert_eq!(Encoding::Windows1252.encode("€ ✓"), Err('✓'));
+        assert_eq!(Encoding::Windows1252.encode("\u{81}"), Ok(vec![0x81]));
+        assert_eq!(Encoding::Windows1252.encode("\u{80}"), Err('\u{80}'));
+    }
+
+    #[test]
+    fn line_endings_are_detected() {
+        assert_eq!(LineEnding::detect("no endings"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\rb\rc\n"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let text = "# “Quoted” café\n\n– a list of things\n";
+        for &encoding in &[Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+            for &bom in &[false, true] {
+                for &line_ending in &LineEnding::ALL {
+                    let written = format(encoding, bom, line_ending);
+                    let bytes = written.encode(text).unwrap();
+                    let decoded = decode(&bytes, encoding).unwrap();
+                    assert_eq!(decoded.text, text);
+                    assert_eq!(decoded.format, written);
+                }
+            }
+        }
+
+        for &encoding in &[Encoding::Windows1252, Encoding::Latin1] {
+            let text = if encoding == Encoding::Latin1 { "café\nna\u{ef}ve\n" } else { text };
+            for &line_ending in &LineEnding::ALL {
+                let written = format(encoding, false, line_ending);
+                let bytes = written.encode(text).unwrap();
+                let decoded = decode(&bytes, encoding).unwrap();
+                assert_eq!(decoded.text, text);
+                assert_eq!(decoded.format, written);
+            }
+        }
+    }
+
+    #[test]
+    fn line_endings_are_written() {
+        let crlf = format(Encoding::Utf8, false, LineEnding::CrLf);
+        assert_eq!(crlf.encode("a\nb\n").unwrap(), b"a\r\nb\r\n".to_vec());
+        let cr = format(Encoding::Windows1252, false, LineEnding::Cr);
+        assert_eq!(cr.encode("€\n").unwrap(), b"\x80\r".to_vec());
+        let bom = format(Encoding::Utf16Le, true, LineEnding::Lf);
+        assert_eq!(bom.encode("a").unwrap(), b"\xFF\xFEa\x00".to_vec());
+    }
+}
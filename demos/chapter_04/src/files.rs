@@ -0,0 +1,47 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// The number of bytes which are read at a time.
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Reports how much of a file has been read.
+pub enum Progress {
+    Read { read: u64, total: u64 },
+    Done(io::Result<Vec<u8>>),
+}
+
+/// Reads the file a chunk at a time, reporting the progress after each chunk, and then the
+/// contents of the file. Reading stops early if `report` returns **false**.
+pub fn read_chunked<F: FnMut(Progress) -> bool>(path: &Path, mut report: F) {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(why) => {
+            report(Progress::Done(Err(why)));
+            return;
+        }
+    };
+
+    let total = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let mut contents = Vec::with_capacity(total as usize);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => {
+                contents.extend_from_slice(&chunk[..read]);
+                let read = contents.len() as u64;
+                if !report(Progress::Read { read, total: total.max(read) }) {
+                    return;
+                }
+            }
+            Err(ref why) if why.kind() == io::ErrorKind::Interrupted => continue,
+            Err(why) => {
+                report(Progress::Done(Err(why)));
+                return;
+            }
+        }
+    }
+
+    report(Progress::Done(Ok(contents)));
+}
@@ -0,0 +1,231 @@
+/// Editing commands which add, or remove, markdown formatting.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    Bold,
+    Italic,
+    Code,
+    Link,
+    Heading(usize),
+    BulletList,
+    NumberedList,
+    Quote,
+    Table,
+}
+
+impl Command {
+    /// The marker which surrounds text formatted by an inline command.
+    pub fn marker(self) -> Option<&'static str> {
+        match self {
+            Command::Bold => Some("**"),
+            Command::Italic => Some("_"),
+            Command::Code => Some("`"),
+            _ => None,
+        }
+    }
+
+    /// Whether the command is applied to entire lines, rather than the selected text.
+    pub fn is_line_command(self) -> bool {
+        match self {
+            Command::Heading(_) | Command::BulletList | Command::NumberedList | Command::Quote => {
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Surrounds the text with the marker, or removes the marker if it is already surrounded.
+pub fn toggle_wrap(text: &str, marker: &str) -> String {
+    if text.len() >= marker.len() * 2 && text.starts_with(marker) && text.ends_with(marker) {
+        text[marker.len()..text.len() - marker.len()].to_owned()
+    } else {
+        [marker, text, marker].concat()
+    }
+}
+
+/// Creates a link from the text. If the text is a URL, it becomes the link's destination, and
+/// otherwise it becomes the link's label.
+pub fn link(text: &str) -> String {
+    let is_url = ["http://", "https://", "mailto:", "www."].iter().any(|s| text.starts_with(s));
+    if is_url {
+        format!("[]({})", text)
+    } else {
+        format!("[{}]()", text)
+    }
+}
+
+/// Applies a line command to every line of the text. If every line already has the formatting
+/// of the command, the formatting is removed instead.
+pub fn toggle_lines(text: &str, command: Command) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let formatted = lines.iter().filter(|line| !line.trim().is_empty()).all(|line| {
+        match command {
+            Command::Heading(level) => heading_level(line) == level,
+            Command::BulletList => bullet_content(line).is_some(),
+            Command::NumberedList => number_content(line).is_some(),
+            Command::Quote => line.starts_with('>'),
+            _ => false,
+        }
+    });
+
+    let mut number = 0;
+    let lines: Vec<String> = lines
+        .into_iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                return line.to_owned();
+            }
+
+            match command {
+                Command::Heading(level) => {
+                    let content = match heading_level(line) {
+                        0 => line,
+                        current => line[current..].trim_left(),
+                    };
+                    if formatted {
+                        content.to_owned()
+                    } else {
+                        format!("{} {}", "#".repeat(level), content)
+                    }
+                }
+                Command::BulletList | Command::NumberedList => {
+                    let content = bullet_content(line).or_else(|| number_content(line));
+                    let content = content.unwrap_or(line);
+                    if formatted {
+                        content.to_owned()
+                    } else if command == Command::BulletList {
+                        format!("- {}", content)
+                    } else {
+                        number += 1;
+                        format!("{}. {}", number, content)
+                    }
+                }
+                Command::Quote => if formatted {
+                    let content = &line[1..];
+                    content.trim_left_matches(' ').to_owned()
+                } else {
+                    format!("> {}", line)
+                },
+                _ => line.to_owned(),
+            }
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+/// Creates a table. Selected lines of tab or comma separated values become the rows of the
+/// table, with the first line as the header; otherwise an empty table is created.
+pub fn table(text: &str) -> String {
+    let rows: Vec<Vec<&str>> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let separator = if line.contains('\t') { '\t' } else { ',' };
+            line.split(separator).map(|cell| cell.trim()).collect()
+        })
+        .collect();
+
+    if rows.is_empty() {
+        return "| Column | Column |\n| ------ | ------ |\n|        |        |\n".to_owned();
+    }
+
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(1);
+    let mut output = String::new();
+    for (index, row) in rows.iter().enumerate() {
+        output.push('|');
+        for column in 0..columns {
+            output.push(' ');
+            output.push_str(row.get(column).cloned().unwrap_or(""));
+            output.push_str(" |");
+        }
+        output.push('\n');
+
+        if index == 0 {
+            output.push('|');
+            for _ in 0..columns {
+                output.push_str(" --- |");
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// The level of the ATX heading on the line, or zero if the line is not a heading.
+fn heading_level(line: &str) -> usize {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level <= 6 && line[level..].starts_with(' ') { level } else { 0 }
+}
+
+fn bullet_content(line: &str) -> Option<&str> {
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        Some(&line[2..])
+    } else {
+        None
+    }
+}
+
+fn number_content(line: &str) -> Option<&str> {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") ")) {
+        Some(&line[digits + 2..])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_markers_are_toggled() {
+        assert_eq!(toggle_wrap("word", "**"), "**word**");
+        assert_eq!(toggle_wrap("**word**", "**"), "word");
+        assert_eq!(toggle_wrap("", "`"), "``");
+        assert_eq!(toggle_wrap("``", "`"), "");
+        // A lone marker is not mistaken for a wrapped, empty selection.
+        assert_eq!(toggle_wrap("*", "*"), "***");
+    }
+
+    #[test]
+    fn links_use_urls_as_destinations() {
+        assert_eq!(link("https://example.com"), "[](https://example.com)");
+        assert_eq!(link("example"), "[example]()");
+    }
+
+    #[test]
+    fn headings_are_toggled() {
+        assert_eq!(toggle_lines("Title", Command::Heading(2)), "## Title");
+        assert_eq!(toggle_lines("# Title", Command::Heading(2)), "## Title");
+        assert_eq!(toggle_lines("## Title", Command::Heading(2)), "Title");
+        assert_eq!(toggle_lines("#hashtag", Command::Heading(1)), "# #hashtag");
+    }
+
+    #[test]
+    fn lists_are_toggled_and_converted() {
+        let text = "one\n\ntwo\nthree";
+        assert_eq!(toggle_lines(text, Command::BulletList), "- one\n\n- two\n- three");
+        assert_eq!(toggle_lines("- one\n- two", Command::BulletList), "one\ntwo");
+        assert_eq!(toggle_lines("- one\n* two", Command::NumberedList), "1. one\n2. two");
+        assert_eq!(toggle_lines("1. one\n2) two", Command::NumberedList), "one\ntwo");
+    }
+
+    #[test]
+    fn quotes_are_toggled() {
+        assert_eq!(toggle_lines("a\nb", Command::Quote), "> a\n> b");
+        assert_eq!(toggle_lines("> a\n>b", Command::Quote), "a\nb");
+    }
+
+    #[test]
+    fn tables_are_created_from_separated_values() {
+        assert_eq!(
+            table("name,count\napples, 3\n"),
+            "| name | count |\n| --- | --- |\n| apples | 3 |\n"
+        );
+        assert_eq!(table("a\tb\tc\nd"), "| a | b | c |\n| --- | --- | --- |\n| d |  |  |\n");
+        assert!(table("").starts_with("| Column | Column |\n"));
+    }
+}
@@ -0,0 +1,293 @@
+use diff::{diff, Line};
+use git2::{Error, ErrorCode, ObjectType, Oid, Repository, Tree};
+use std::path::{Path, PathBuf};
+
+/// How a line of the document differs from the document at the repository's HEAD.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Added,
+    Modified,
+    /// Lines were deleted before this line.
+    Deleted,
+}
+
+/// The git repository which contains the open document.
+pub struct Repo {
+    repo:     Repository,
+    /// The path of the document, relative to the repository's working directory.
+    relative: PathBuf,
+    /// The commit which HEAD referred to when the document was last read from it.
+    head_id:  Option<Oid>,
+    /// The document as it was at HEAD, or **None** if the document is not yet committed.
+    head:     Option<String>,
+}
+
+impl Repo {
+    /// Discovers the repository which contains the document at the path, if there is one.
+    pub fn open(path: &Path) -> Option<Repo> {
+        let repo = match path.parent().map(|dir| Repository::discover(dir)) {
+            Some(Ok(repo)) => repo,
+            _ => return None,
+        };
+
+        let relative = match repo.workdir().and_then(|dir| path.strip_prefix(dir).ok()) {
+            Some(relative) => relative.to_path_buf(),
+            None => return None,
+        };
+
+        let head_id = head_id(&repo);
+        let mut repo = Repo { repo, relative, head_id, head: None };
+        repo.head = repo.read_head().unwrap_or(None);
+        Some(repo)
+    }
+
+    /// The path of the document, relative to the repository's working directory.
+    pub fn relative_path(&self) -> &Path { &self.relative }
+
+    /// Whether the repository contains the document at the path.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.repo.workdir().map_or(false, |dir| dir.join(&self.relative) == path)
+    }
+
+    /// Reads the document as it is at HEAD again if HEAD has moved since it was read, such as
+    /// when a commit is made or another branch is checked out, by this or any other program.
+    pub fn reload(&mut self) {
+        let id = head_id(&self.repo);
+        if id != self.head_id {
+            self.head_id = id;
+            self.head = self.read_head().unwrap_or(None);
+        }
+    }
+
+    /// The document as it was at HEAD, or **None** if the document is not yet committed.
+    pub fn head(&self) -> Option<&str> { self.head.as_ref().map(|head| head.as_str()) }
+
+    fn read_head(&self) -> Result<Option<String>, Error> {
+        let tree = match self.repo.head() {
+            Ok(head) => head.peel_to_commit()?.tree()?,
+            // Nothing has been committed to a new repository.
+            Err(ref why) if why.code() == ErrorCode::UnbornBranch => return Ok(None),
+            Err(why) => return Err(why),
+        };
+
+        let entry = match tree.get_path(&self.relative) {
+            Ok(entry) => entry,
+            Err(ref why) if why.code() == ErrorCode::NotFound => return Ok(None),
+            Err(why) => return Err(why),
+        };
+
+        let object = entry.to_object(&self.repo)?;
+        Ok(object.as_blob().map(|blob| String::from_utf8_lossy(blob.content()).into_owned()))
+    }
+
+    /// The name of the branch which is checked out, or the abbreviated commit ID if no branch
+    /// is checked out.
+    pub fn branch(&self) -> Option<String> {
+        let head = match self.repo.head() {
+            Ok(head) => head,
+            // A new repository's HEAD refers to a branch which does not exist yet.
+            Err(_) => {
+                let head = self.repo.find_reference("HEAD").ok();
+                let target = head.as_ref().and_then(|head| head.symbolic_target());
+                return target.map(|target| target.trim_left_matches("refs/heads/").to_owned());
+            }
+        };
+
+        if head.is_branch() {
+            head.shorthand().map(|name| name.to_owned())
+        } else {
+            head.target().map(|id| id.to_string()[..7].to_owned())
+        }
+    }
+
+
+    /// Commits the document, as it is on disk, with the given message, and returns the ID of
+    /// the new commit. Only the document is committed: the commit's tree is HEAD's tree with
+    /// the document replaced, so changes that the user has staged for other files are left
+    /// staged, and only the document's entry within the index is updated.
+    pub fn commit(&mut self, message: &str) -> Result<String, Error> {
+        let path = match self.repo.workdir() {
+            Some(dir) => dir.join(&self.relative),
+            None => return Err(Error::from_str("the repository has no working directory")),
+        };
+        let blob = self.repo.blob_path(&path)?;
+
+        let parent = match self.repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(ref why) if why.code() == ErrorCode::UnbornBranch => None,
+            Err(why) => return Err(why),
+        };
+        let base = match parent {
+            Some(ref parent) => Some(parent.tree()?),
+            None => None,
+        };
+
+        let tree = replace_in_tree(&self.repo, base.as_ref(), &self.relative, blob)?;
+        let tree = self.repo.find_tree(tree)?;
+        let signature = self.repo.signature()?;
+        let parents: Vec<_> = parent.iter().collect();
+        let id =
+            self.repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)?;
+
+        // The document is staged as it was committed, so that it is not shown as having
+        // staged changes which undo the commit.
+        let mut index = self.repo.index()?;
+        index.add_path(&self.relative)?;
+        index.write()?;
+
+        self.reload();
+        Ok(id.to_string())
+    }
+}
+
+/// The commit that HEAD refers to, or **None** if nothing has been committed.
+fn head_id(repo: &Repository) -> Option<Oid> { repo.head().ok().and_then(|head| head.target()) }
+
+/// Finds the lines of the document that have changed since HEAD, given the document as it
+/// was at HEAD. Documents which have never been committed are entirely added. This may be
+/// called from any thread, as large documents take a while to compare.
+pub fn changes(head: Option<&str>, document: &str) -> Vec<(i32, Change)> {
+    match head {
+        Some(head) => line_changes(head, document),
+        None => {
+            let lines = document.lines().count() as i32;
+            (0..lines).map(|line| (line, Change::Added)).collect()
+        }
+    }
+}
+
+/// Builds a tree which is the base tree with the file at the relative path replaced by the
+/// blob, and returns its ID. The directories leading to the file are rebuilt in the same way,
+/// and are created if the base tree lacks them.
+fn replace_in_tree(
+    repo: &Repository,
+    base: Option<&Tree>,
+    path: &Path,
+    blob: Oid,
+) -> Result<Oid, Error> {
+    let mut components = path.components();
+    let name = match components.next() {
+        Some(name) => name.as_os_str(),
+        None => return Err(Error::from_str("the document's path is empty")),
+    };
+    let rest = components.as_path();
+    let existing = base.and_then(|tree| tree.get_path(Path::new(name)).ok());
+
+    let mut builder = repo.treebuilder(base)?;
+    if rest.as_os_str().is_empty() {
+        // Files keep their mode, such as being executable.
+        let mode = existing.map_or(0o100644, |entry| entry.filemode());
+        builder.insert(name, blob, mode)?;
+    } else {
+        let subtree = match existing {
+            Some(ref entry) if entry.kind() == Some(ObjectType::Tree) => {
+                Some(repo.find_tree(entry.id())?)
+            }
+            _ => None,
+        };
+        let subtree = replace_in_tree(repo, subtree.as_ref(), rest, blob)?;
+        builder.insert(name, subtree, 0o040000)?;
+    }
+    builder.write()
+}
+
+/// Compares the old and new documents, and describes how each changed line of the new
+/// document differs. A line which replaces a removed line is modified, rather than added.
+pub fn line_changes(old: &str, new: &str) -> Vec<(i32, Change)> {
+    let mut changes = Vec::new();
+    let mut line = 0;
+    let mut removed = 0;
+
+    for change in diff(old, new) {
+        match change {
+            Line::Same(_) => {
+                if removed != 0 {
+                    changes.push((line, Change::Deleted));
+                }
+                removed = 0;
+                line += 1;
+            }
+            Line::Removed(_) => removed += 1,
+            Line::Added(_) => {
+                if removed != 0 {
+                    removed -= 1;
+                    changes.push((line, Change::Modified));
+                } else {
+                    changes.push((line, Change::Added));
+                }
+                line += 1;
+            }
+        }
+    }
+
+    // Lines deleted from the end of the document are marked on the last line.
+    if removed != 0 {
+        changes.push(((line - 1).max(0), Change::Deleted));
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn unchanged_documents_have_no_changes() {
+        assert_eq!(line_changes("a\nb", "a\nb"), Vec::new());
+    }
+
+    #[test]
+    fn changes_are_added_modified_or_deleted() {
+        assert_eq!(line_changes("a\nb", "a\nb\nc"), vec![(2, Change::Added)]);
+        assert_eq!(line_changes("a\nb\nc", "a\nB\nc"), vec![(1, Change::Modified)]);
+        assert_eq!(line_changes("a\nb\nc", "a\nc"), vec![(1, Change::Deleted)]);
+    }
+
+    #[test]
+    fn deletions_from_the_end_are_marked_on_the_last_line() {
+        assert_eq!(line_changes("a\nb", "a"), vec![(0, Change::Deleted)]);
+    }
+
+    #[test]
+    fn uncommitted_documents_are_entirely_added() {
+        assert_eq!(changes(None, "a\nb"), vec![(0, Change::Added), (1, Change::Added)]);
+    }
+
+    #[test]
+    fn replaces_only_the_document_within_the_tree() {
+        let dir = env::temp_dir().join(format!("git-test-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+
+        let other = repo.blob(b"other").unwrap();
+        let old = repo.blob(b"old").unwrap();
+        let new = repo.blob(b"new").unwrap();
+
+        // The base tree holds `other.md` and `docs/guide.md`, which is executable.
+        let mut docs = repo.treebuilder(None).unwrap();
+        docs.insert("guide.md", old, 0o100755).unwrap();
+        let docs = docs.write().unwrap();
+        let mut root = repo.treebuilder(None).unwrap();
+        root.insert("other.md", other, 0o100644).unwrap();
+        root.insert("docs", docs, 0o040000).unwrap();
+        let base = repo.find_tree(root.write().unwrap()).unwrap();
+
+        let replaced = replace_in_tree(&repo, Some(&base), Path::new("docs/guide.md"), new);
+        let replaced = repo.find_tree(replaced.unwrap()).unwrap();
+        let guide = replaced.get_path(Path::new("docs/guide.md")).unwrap();
+        let other_entry = replaced.get_path(Path::new("other.md")).map(|entry| entry.id());
+
+        // A new file is created along with the directories that lead to it.
+        let created = replace_in_tree(&repo, None, Path::new("notes/new.md"), new);
+        let created = repo.find_tree(created.unwrap()).unwrap();
+        let created = created.get_path(Path::new("notes/new.md")).map(|entry| entry.filemode());
+
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!((guide.id(), guide.filemode()), (new, 0o100755));
+        assert_eq!(other_entry.ok(), Some(other));
+        assert_eq!(created.ok(), Some(0o100644));
+    }
+}
@@ -0,0 +1,172 @@
+use config::config_dir;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tiny_keccak::keccak512;
+
+/// The file within a document's history which lists its snapshots.
+const INDEX: &str = "index";
+
+/// A named copy of a document, as it was at a moment in time.
+pub struct Snapshot {
+    pub name: String,
+    /// When the snapshot was taken, in seconds since the Unix epoch.
+    pub time: u64,
+    /// The file name of the snapshot's contents within the history.
+    file:     String,
+}
+
+/// The snapshots of a document, which are stored beside the editor's configuration, rather
+/// than beside the document.
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    /// Locates the history of the document at the path. Each document's history is kept in a
+    /// directory named after a hash of the document's path.
+    pub fn for_document(path: &Path) -> Option<History> {
+        let hash = keccak512(path.to_string_lossy().as_bytes());
+        let name: String = hash[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+        config_dir().map(|dir| History { dir: dir.join("history").join(name) })
+    }
+
+    /// Lists the snapshots of the document, from the newest to the oldest.
+    pub fn snapshots(&self) -> io::Result<Vec<Snapshot>> {
+        let mut index = String::new();
+        match File::open(self.dir.join(INDEX)) {
+            Ok(mut file) => file.read_to_string(&mut index)?,
+            Err(ref why) if why.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(why) => return Err(why),
+        };
+
+        Ok(parse_index(&index))
+    }
+
+    /// Stores the contents of the document as a new snapshot with the given name.
+    pub fn save(&self, name: &str, contents: &str) -> io::Result<Snapshot> {
+        fs::create_dir_all(&self.dir)?;
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        // Several snapshots may be taken within the same second.
+        let mut file = format!("{}.md", time);
+        let mut number = 1;
+        while self.dir.join(&file).exists() {
+            file = format!("{}-{}.md", time, number);
+            number += 1;
+        }
+
+        File::create(self.dir.join(&file))?.write_all(contents.as_bytes())?;
+
+        // Names are kept to a single line, so that they fit within the index.
+        let name = name.replace(|c: char| c == '\t' || c == '\n' || c == '\r', " ");
+        let mut index = OpenOptions::new().create(true).append(true).open(self.dir.join(INDEX))?;
+        writeln!(index, "{}\t{}\t{}", time, file, name)?;
+
+        Ok(Snapshot { name, time, file })
+    }
+
+    /// Reads the contents of the document as it was when the snapshot was taken.
+    pub fn contents(&self, snapshot: &Snapshot) -> io::Result<String> {
+        let mut contents = String::new();
+        File::open(self.dir.join(&snapshot.file))?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+}
+
+/// Reads the snapshots from the history's index, from the newest to the oldest. Each line of
+/// the index holds the time, file name, and name of a snapshot, and malformed lines are skipped.
+fn parse_index(index: &str) -> Vec<Snapshot> {
+    let mut snapshots: Vec<Snapshot> = index
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let time = fields.next().and_then(|time| time.parse::<u64>().ok());
+            match (time, fields.next(), fields.next()) {
+                (Some(time), Some(file), Some(name)) => {
+                    Some(Snapshot { name: name.to_owned(), time, file: file.to_owned() })
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    snapshots.reverse();
+    snapshots
+}
+
+/// Formats seconds since the Unix epoch as a date and time, such as `2018-01-31 14:05`, in the
+/// time zone which is the given number of seconds ahead of UTC.
+pub fn format_time(time: u64, offset: i64) -> String {
+    let time = time as i64 + offset;
+    let (mut days, mut seconds) = (time / 86_400, time % 86_400);
+    if seconds < 0 {
+        days -= 1;
+        seconds += 86_400;
+    }
+
+    // Converts the number of days since the epoch into a civil date.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_are_formatted_as_dates() {
+        assert_eq!(format_time(0, 0), "1970-01-01 00:00");
+        assert_eq!(format_time(1_517_407_500, 0), "2018-01-31 14:05");
+        // Leap days and the end of a year.
+        assert_eq!(format_time(951_782_400, 0), "2000-02-29 00:00");
+        assert_eq!(format_time(1_546_300_799, 0), "2018-12-31 23:59");
+    }
+
+    #[test]
+    fn times_are_shifted_into_the_time_zone() {
+        assert_eq!(format_time(1_517_407_500, 3600), "2018-01-31 15:05");
+        assert_eq!(format_time(1_517_407_500, -15 * 3600), "2018-01-30 23:05");
+        assert_eq!(format_time(0, -3600), "1969-12-31 23:00");
+    }
+
+    #[test]
+    fn the_index_lists_the_newest_snapshot_first() {
+        let index = "100\t100.md\tFirst draft\n200\t200.md\tSecond\tdraft\n";
+        let snapshots = parse_index(index);
+        let fields: Vec<_> = snapshots
+            .iter()
+            .map(|s| (s.time, s.file.as_str(), s.name.as_str()))
+            .collect();
+        assert_eq!(fields, vec![(200, "200.md", "Second\tdraft"), (100, "100.md", "First draft")]);
+    }
+
+    #[test]
+    fn malformed_index_lines_are_skipped() {
+        let index = "\nsoon\tsoon.md\tLater\n300\t300.md\n400\t400.md\tKept";
+        let names: Vec<_> = parse_index(index).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["Kept"]);
+    }
+}
@@ -0,0 +1,65 @@
+use config::config_dir;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+/// The arrangements of the editor and the preview which may be chosen.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    EditorOnly,
+    PreviewOnly,
+    SideBySide,
+    Stacked,
+    /// The preview is displayed within its own window, such as on a second monitor.
+    Window,
+}
+
+impl Layout {
+    /// The name with which the layout is stored within the configuration directory.
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::EditorOnly => "editor",
+            Layout::PreviewOnly => "preview",
+            Layout::SideBySide => "side-by-side",
+            Layout::Stacked => "stacked",
+            Layout::Window => "window",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Layout> {
+        match name {
+            "editor" => Some(Layout::EditorOnly),
+            "preview" => Some(Layout::PreviewOnly),
+            "side-by-side" => Some(Layout::SideBySide),
+            "stacked" => Some(Layout::Stacked),
+            "window" => Some(Layout::Window),
+            _ => None,
+        }
+    }
+
+    /// The layout that was last chosen, which is side by side if none has been chosen.
+    pub fn load() -> Layout {
+        let mut name = String::new();
+        layout_path()
+            .and_then(|path| File::open(path).ok())
+            .and_then(|mut file| file.read_to_string(&mut name).ok())
+            .and_then(|_| Layout::from_name(name.trim()))
+            .unwrap_or(Layout::SideBySide)
+    }
+
+    /// Remembers the layout, so that it will be used the next time the editor is opened.
+    pub fn save(self) -> io::Result<()> {
+        let path = match layout_path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        File::create(path)?.write_all(self.name().as_bytes())
+    }
+}
+
+fn layout_path() -> Option<PathBuf> { config_dir().map(|dir| dir.join("layout")) }
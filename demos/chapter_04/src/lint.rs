@@ -0,0 +1,280 @@
+use outline::headings;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::HashSet;
+use std::path::Path;
+use url::percent_decode;
+
+/// How severe a problem within the document is.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem that was found within the document, located by its line, and the byte range
+/// within that line.
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line:     i32,
+    pub start:    usize,
+    pub end:      usize,
+    pub message:  String,
+}
+
+/// Checks the markdown text for common problems. Relative links and images are resolved
+/// against `dir`, and are not checked if the document has yet to be saved.
+pub fn lint(markdown: &str, dir: Option<&Path>) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = markdown.split('\n').collect();
+    let mut diagnostics = Vec::new();
+
+    trailing_whitespace(&lines, &mut diagnostics);
+    heading_problems(markdown, &lines, &mut diagnostics);
+    link_problems(markdown, &lines, dir, &mut diagnostics);
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.start));
+    diagnostics
+}
+
+impl Severity {
+    /// The name of the icon that represents this severity.
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Severity::Warning => "dialog-warning",
+            Severity::Error => "dialog-error",
+        }
+    }
+}
+
+/// Whitespace at the end of a line is flagged, unless it is the two spaces of a hard line
+/// break that is followed by more text.
+fn trailing_whitespace(lines: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+    for (number, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_right();
+        if trimmed.len() == line.len() {
+            continue;
+        }
+
+        let trailing = &line[trimmed.len()..];
+        let next_has_text = lines.get(number + 1).map_or(false, |next| !next.trim().is_empty());
+        if trailing == "  " && !trimmed.is_empty() && next_has_text {
+            continue;
+        }
+
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            line:     number as i32,
+            start:    trimmed.len(),
+            end:      line.len(),
+            message:  "Trailing whitespace".into(),
+        });
+    }
+}
+
+/// Headings should only increase by one level at a time, and should not share a title.
+fn heading_problems(markdown: &str, lines: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+    let mut previous_level = 0;
+    let mut titles = HashSet::new();
+
+    for heading in headings(markdown) {
+        let length = lines.get(heading.line as usize).map_or(0, |line| line.trim_right().len());
+
+        if previous_level != 0 && heading.level > previous_level + 1 {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                line:     heading.line,
+                start:    0,
+                end:      length,
+                message:  format!(
+                    "Heading level jumps from {} to {}",
+                    previous_level, heading.level
+                ),
+            });
+        }
+
+        if !titles.insert(heading.title.trim().to_lowercase()) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                line:     heading.line,
+                start:    0,
+                end:      length,
+                message:  format!("Duplicate heading \"{}\"", heading.title.trim()),
+            });
+        }
+
+        previous_level = heading.level;
+    }
+}
+
+/// Relative links should point to files that exist, or to headings within this document, and
+/// images should exist on the disk.
+fn link_problems(
+    markdown: &str,
+    lines: &[&str],
+    dir: Option<&Path>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let anchors: HashSet<String> = headings(markdown).into_iter().map(|h| h.anchor).collect();
+
+    // Each destination is searched for from where its link begins. The destination of a
+    // reference link is found within its definition, later in the document, and so the search
+    // must not continue from there.
+    let mut parser = Parser::new(markdown);
+    loop {
+        let offset = parser.get_offset();
+        let (destination, is_image) = match parser.next() {
+            Some(Event::Start(Tag::Link(ref destination, _))) => (destination.to_string(), false),
+            Some(Event::Start(Tag::Image(ref destination, _))) => (destination.to_string(), true),
+            Some(_) => continue,
+            None => break,
+        };
+
+        let (line, start) = match find_after(lines, position(markdown, offset), &destination) {
+            Some(found) => found,
+            None => continue,
+        };
+
+        if destination.is_empty() || is_external(&destination) {
+            continue;
+        }
+
+        let message = if destination.starts_with('#') {
+            if anchors.contains(&destination[1..]) {
+                continue;
+            }
+            format!("No heading has the anchor \"{}\"", destination)
+        } else {
+            let dir = match dir {
+                Some(dir) => dir,
+                None => continue,
+            };
+
+            let path = destination.split(|c| c == '#' || c == '?').next().unwrap_or("");
+            if dir.join(percent_decode(path)).exists() {
+                continue;
+            }
+
+            if is_image {
+                format!("Image \"{}\" does not exist", path)
+            } else {
+                format!("Link to \"{}\" is broken", path)
+            }
+        };
+
+        let end = start + destination.len();
+        let severity = if is_image { Severity::Error } else { Severity::Warning };
+
+        diagnostics.push(Diagnostic { severity, line: line as i32, start, end, message });
+    }
+}
+
+/// The line, and the byte index within it, of the byte offset within the markdown.
+fn position(markdown: &str, offset: usize) -> (usize, usize) {
+    let before = &markdown[..offset.min(markdown.len())];
+    match before.rfind('\n') {
+        Some(newline) => (before.matches('\n').count(), offset - newline - 1),
+        None => (0, before.len()),
+    }
+}
+
+/// Finds the line and byte index of the needle, searching from the given line and index.
+fn find_after(lines: &[&str], from: (usize, usize), needle: &str) -> Option<(usize, usize)> {
+    let (line, index) = from;
+    lines
+        .iter()
+        .enumerate()
+        .skip(line)
+        .filter_map(|(number, text)| {
+            let skip = if number == line { index.min(text.len()) } else { 0 };
+            text.get(skip..).and_then(|rest| rest.find(needle)).map(|start| (number, skip + start))
+        })
+        .next()
+}
+
+fn is_external(destination: &str) -> bool {
+    destination.contains("://") || destination.starts_with("mailto:")
+        || destination.starts_with("data:") || destination.starts_with("www.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+
+    fn messages(markdown: &str, dir: Option<&Path>) -> Vec<(i32, usize, usize, String)> {
+        lint(markdown, dir).into_iter().map(|d| (d.line, d.start, d.end, d.message)).collect()
+    }
+
+    #[test]
+    fn trailing_whitespace_is_flagged() {
+        assert_eq!(
+            messages("text \nmore", None),
+            vec![(0, 4, 5, "Trailing whitespace".to_owned())]
+        );
+    }
+
+    #[test]
+    fn hard_line_breaks_are_allowed() {
+        assert!(messages("first  \nsecond", None).is_empty());
+        // A break which is not followed by text does nothing.
+        assert_eq!(messages("last  \n", None).len(), 1);
+    }
+
+    #[test]
+    fn heading_levels_may_not_jump() {
+        let found = messages("# One\n\n### Three\n", None);
+        assert_eq!(found, vec![(2, 0, 9, "Heading level jumps from 1 to 3".to_owned())]);
+    }
+
+    #[test]
+    fn duplicate_headings_are_flagged() {
+        let found = messages("# Notes\n\n## notes\n", None);
+        assert_eq!(found, vec![(2, 0, 8, "Duplicate heading \"notes\"".to_owned())]);
+    }
+
+    #[test]
+    fn anchors_must_exist() {
+        let markdown = "# Intro\n\nSee [intro](#intro) and [missing](#missing).\n";
+        let found = messages(markdown, None);
+        assert_eq!(found.len(), 1);
+        assert_eq!(&found[0].3, "No heading has the anchor \"#missing\"");
+        assert_eq!(&markdown.split('\n').nth(2).unwrap()[found[0].1..found[0].2], "#missing");
+    }
+
+    #[test]
+    fn relative_paths_must_exist() {
+        let dir = env::temp_dir().join(format!("lint-test-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        File::create(dir.join("exists.md")).unwrap();
+
+        let markdown = "[a](exists.md) [b](missing.md#top) ![c](my%20image.png) [d](http://x.y)";
+        let found = lint(markdown, Some(&dir));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].message, "Link to \"missing.md\" is broken");
+        assert!(found[0].severity == Severity::Warning);
+        assert_eq!(found[1].message, "Image \"my%20image.png\" does not exist");
+        assert!(found[1].severity == Severity::Error);
+
+        // Paths are not checked until the document has been saved.
+        assert!(lint(markdown, None).is_empty());
+    }
+
+    #[test]
+    fn links_after_reference_links_are_checked() {
+        let markdown = "See [the intro][intro], then [broken](#broken).\n\n\
+                        [intro]: #intro\n\n# Intro\n";
+        let found = messages(markdown, None);
+        assert_eq!(found, vec![(0, 38, 45, "No heading has the anchor \"#broken\"".to_owned())]);
+    }
+
+    #[test]
+    fn offsets_are_positioned_within_lines() {
+        let markdown = "ab\ncd\r\nef";
+        assert_eq!(position(markdown, 0), (0, 0));
+        assert_eq!(position(markdown, 1), (0, 1));
+        assert_eq!(position(markdown, 3), (1, 0));
+        assert_eq!(position(markdown, 8), (2, 1));
+    }
+}
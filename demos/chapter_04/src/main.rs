@@ -1,20 +1,61 @@
 extern crate gdk;
+extern crate gdk_pixbuf;
+extern crate gio;
+extern crate git2;
+extern crate glib;
+extern crate glib_sys;
 extern crate gtk;
+extern crate gtk_source_sys;
+extern crate gtk_sys;
 #[macro_use]
 extern crate horrorshow;
 extern crate pango;
 extern crate pulldown_cmark;
+extern crate regex;
 extern crate sourceview;
 extern crate tiny_keccak;
 extern crate webkit2gtk;
 
+pub mod assets;
+pub mod completion;
+pub mod config;
+pub mod diff;
+pub mod document;
+pub mod encoding;
+pub mod files;
+pub mod format;
+pub mod git;
+pub mod history;
+pub mod layout;
+pub mod lint;
+pub mod outline;
+pub mod pretty;
 pub mod preview;
+pub mod project;
+pub mod search;
+pub mod server;
+pub mod site;
+pub mod spell;
 pub mod state;
+pub mod stats;
+pub mod table;
 pub mod ui;
+pub mod url;
+pub mod wiki;
 
+use site::DEFAULT_OUTPUT;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
 use ui::App;
 
 fn main() {
+    // `cmark-editor build <folder> [output]` builds a static site without opening the editor.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("build") {
+        process::exit(build_site(&args[1..]));
+    }
+
     // Initialize the UI's initial state
     App::new()
         // Connect events to the UI
@@ -22,3 +63,32 @@ fn main() {
         // Display the UI and execute the program
         .then_execute();
 }
+
+/// Builds the folder into a static site, and returns the exit status of the command.
+fn build_site(args: &[String]) -> i32 {
+    let folder = match args.first() {
+        Some(folder) => Path::new(folder),
+        None => {
+            eprintln!("usage: cmark-editor build <folder> [output]");
+            return 2;
+        }
+    };
+
+    let output = args.get(1).map_or(folder.join(DEFAULT_OUTPUT), PathBuf::from);
+    match site::build(folder, &output) {
+        Ok(report) => {
+            println!(
+                "built {} pages, {} tag pages, and {} assets into {}",
+                report.pages,
+                report.tags,
+                report.assets,
+                output.display()
+            );
+            0
+        }
+        Err(why) => {
+            eprintln!("unable to build the site: {}", why);
+            1
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::HashMap;
+
+/// A heading that was discovered within a markdown document.
+pub struct Heading {
+    pub level:  i32,
+    pub title:  String,
+    pub line:   i32,
+    pub anchor: String,
+}
+
+/// Collects every heading from the markdown text, in the order that they appear.
+pub fn headings(markdown: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut anchors = HashMap::new();
+    let mut current: Option<Heading> = None;
+
+    let mut parser = Parser::new(markdown);
+    while let Some(event) = parser.next() {
+        match event {
+            Event::Start(Tag::Header(level)) => {
+                let line = line_at(markdown, parser.get_offset());
+                let (title, anchor) = (String::new(), String::new());
+                current = Some(Heading { level, title, line, anchor });
+            }
+            Event::Text(ref text) => if let Some(ref mut heading) = current {
+                heading.title.push_str(text);
+            },
+            Event::End(Tag::Header(_)) => if let Some(mut heading) = current.take() {
+                heading.anchor = unique_anchor(&mut anchors, &heading.title);
+                headings.push(heading);
+            },
+            _ => (),
+        }
+    }
+
+    headings
+}
+
+/// Converts a heading's title into a GitHub-style anchor, such as `my-heading`.
+pub fn slugify(title: &str) -> String {
+    title
+        .trim()
+        .chars()
+        .filter_map(|c| if c.is_alphanumeric() {
+            Some(c.to_lowercase().next().unwrap_or(c))
+        } else if c == ' ' || c == '-' {
+            Some('-')
+        } else if c == '_' {
+            Some('_')
+        } else {
+            None
+        })
+        .collect()
+}
+
+/// Headings that share a title get a numbered suffix so that every anchor remains unique.
+fn unique_anchor(anchors: &mut HashMap<String, usize>, title: &str) -> String {
+    let slug = slugify(title);
+    let count = anchors.entry(slug.clone()).or_insert(0);
+    let anchor = if *count == 0 { slug } else { format!("{}-{}", slug, count) };
+    *count += 1;
+    anchor
+}
+
+/// Obtains the zero-based line number that the byte offset is located on.
+fn line_at(text: &str, offset: usize) -> i32 {
+    let offset = if offset > text.len() { text.len() } else { offset };
+    text.as_bytes()[..offset].iter().filter(|&&b| b == b'\n').count() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headings_are_collected_in_order() {
+        let markdown = "# Title\n\nSome text.\n\n## First *Section*\n\n```\n# not a heading\n```\n";
+        let headings = headings(markdown);
+        assert_eq!(headings.len(), 2);
+        assert_eq!((headings[0].level, headings[0].line), (1, 0));
+        assert_eq!(headings[0].title, "Title");
+        assert_eq!((headings[1].level, headings[1].line), (2, 4));
+        assert_eq!(headings[1].title, "First Section");
+        assert_eq!(headings[1].anchor, "first-section");
+    }
+
+    #[test]
+    fn duplicate_headings_have_unique_anchors() {
+        let anchors: Vec<String> =
+            headings("# Notes\n\n# Notes\n\n# Notes\n").into_iter().map(|h| h.anchor).collect();
+        assert_eq!(anchors, vec!["notes", "notes-1", "notes-2"]);
+    }
+
+    #[test]
+    fn slugs_keep_alphanumerics() {
+        assert_eq!(slugify("  Hello, World!  "), "hello-world");
+        assert_eq!(slugify("snake_case and-dashes"), "snake_case-and-dashes");
+        assert_eq!(slugify("Ünïcödé 2"), "ünïcödé-2");
+    }
+
+    #[test]
+    fn lines_are_counted_from_offsets() {
+        assert_eq!(line_at("a\nb\nc", 0), 0);
+        assert_eq!(line_at("a\nb\nc", 2), 1);
+        assert_eq!(line_at("a\nb\nc", 100), 2);
+    }
+}
@@ -0,0 +1,651 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use pulldown_cmark::{OPTION_ENABLE_FOOTNOTES, OPTION_ENABLE_TABLES};
+use table::Table;
+
+/// Stands in for spaces which must not be used to wrap a line, such as those in code spans.
+const NO_BREAK: char = '\u{1}';
+
+/// A block that contains other blocks, and the prefix that each of its lines begin with.
+struct Container {
+    first: String,
+    rest:  String,
+    used:  bool,
+}
+
+/// A link reference definition, such as `[label]: destination "title"`.
+struct Definition {
+    label:       String,
+    destination: String,
+    title:       String,
+    /// The definition as it was written.
+    source:      String,
+    /// Whether an earlier definition has the same label, and is used by links instead.
+    duplicate:   bool,
+}
+
+/// Parses the markdown text and writes it back out as normalized markdown: lists use `-`
+/// and sequential numbers, code blocks are fenced, tables are aligned, and paragraphs are
+/// wrapped at the given width. Link reference definitions are kept at the end of the document,
+/// and the links which use them remain references.
+pub fn pretty_print(markdown: &str, width: usize) -> String {
+    let mut printer = Printer {
+        output: String::new(),
+        width,
+        containers: Vec::new(),
+        lists: Vec::new(),
+        inline: String::new(),
+        text: String::new(),
+        links: Vec::new(),
+        definitions: definitions(markdown),
+        code_span: None,
+        code: None,
+        table: None,
+        html: false,
+        blank: false,
+    };
+
+    for event in Parser::new_ext(markdown, OPTION_ENABLE_TABLES | OPTION_ENABLE_FOOTNOTES) {
+        printer.event(event);
+    }
+
+    printer.flush_text();
+    printer.flush_inline();
+
+    // The parser discards the definitions once it has resolved the links with them.
+    let sources: Vec<String> = printer.definitions.iter().map(|d| d.source.clone()).collect();
+    printer.blank = true;
+    for source in sources {
+        printer.line(&source);
+    }
+
+    if !printer.output.ends_with('\n') && !printer.output.is_empty() {
+        printer.output.push('\n');
+    }
+    printer.output
+}
+
+struct Printer {
+    output:     String,
+    width:      usize,
+    containers: Vec<Container>,
+    /// The next number of each list, or **None** if the list is a bulleted list.
+    lists:      Vec<Option<usize>>,
+    /// Inline markdown which has yet to be written as a paragraph, heading, or cell.
+    inline:     String,
+    /// Text which has yet to be escaped and added to the inline markdown. The parser splits
+    /// text at characters such as `&`, and so text is escaped once the whole of it has arrived.
+    text:       String,
+    /// The destinations and titles of links and images which have yet to be closed, and where
+    /// their text begins within the inline markdown.
+    links:      Vec<(String, String, usize)>,
+    /// The link reference definitions of the document.
+    definitions: Vec<Definition>,
+    /// Where the code span that is being collected begins within the inline markdown.
+    code_span:  Option<usize>,
+    /// The info string and text of a code block which has yet to be written.
+    code:       Option<(String, String)>,
+    table:      Option<Table>,
+    /// Whether the previous event was a line of an HTML block.
+    html:       bool,
+    /// Whether a blank line should be written before the next line.
+    blank:      bool,
+}
+
+impl Printer {
+    fn event(&mut self, event: Event) {
+        if let Some((_, ref mut code)) = self.code {
+            if let Event::Text(ref text) = event {
+                code.push_str(text);
+                return;
+            }
+        }
+
+        match event {
+            Event::Text(ref text) if self.code_span.is_none() => {
+                self.text.push_str(text);
+                return;
+            }
+            _ => self.flush_text(),
+        }
+
+        // HTML blocks are emitted line by line, and are ended by a blank line.
+        if self.html {
+            match event {
+                Event::Html(_) => (),
+                _ => {
+                    self.html = false;
+                    self.blank = true;
+                }
+            }
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            // Spaces within code spans are literal, and must not be used to wrap the line.
+            Event::Text(text) => self.inline.push_str(&text.replace(' ', &NO_BREAK.to_string())),
+            Event::Html(html) => {
+                self.flush_inline();
+                for line in html.trim_right_matches('\n').split('\n') {
+                    self.line(line);
+                }
+                self.html = true;
+            }
+            Event::InlineHtml(html) => self.inline.push_str(&html),
+            Event::FootnoteReference(label) => {
+                self.inline.push_str(&format!("[^{}]", label));
+            }
+            Event::SoftBreak => self.inline.push(' '),
+            Event::HardBreak => self.inline.push_str("\\\n"),
+        }
+    }
+
+    /// Escapes the text which has been collected, and adds it to the inline markdown.
+    fn flush_text(&mut self) {
+        if !self.text.is_empty() {
+            self.inline.push_str(&escape(&self.text));
+            self.text.clear();
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Header(_) => self.flush_inline(),
+            Tag::TableHead | Tag::TableRow => if let Some(ref mut table) = self.table {
+                table.rows.push(Vec::new());
+            },
+            Tag::TableCell => self.inline.clear(),
+            Tag::Rule => {
+                self.flush_inline();
+                self.line("---");
+                self.blank = true;
+            }
+            Tag::BlockQuote => {
+                self.flush_inline();
+                self.push_container("> ", "> ");
+            }
+            Tag::CodeBlock(info) => {
+                self.flush_inline();
+                self.code = Some((info.into_owned(), String::new()));
+            }
+            Tag::List(start) => {
+                self.flush_inline();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush_inline();
+                let marker = match self.lists.last_mut() {
+                    Some(&mut Some(ref mut number)) => {
+                        let marker = format!("{}. ", number);
+                        *number += 1;
+                        marker
+                    }
+                    _ => "- ".to_owned(),
+                };
+
+                let indent = " ".repeat(marker.len());
+                self.push_container(&marker, &indent);
+            }
+            Tag::FootnoteDefinition(label) => {
+                self.flush_inline();
+                self.push_container(&format!("[^{}]: ", label), "    ");
+            }
+            Tag::Table(alignments) => {
+                self.flush_inline();
+                self.table = Some(Table { alignments, rows: Vec::new() });
+            }
+            Tag::Emphasis => self.inline.push('*'),
+            Tag::Strong => self.inline.push_str("**"),
+            Tag::Code => self.code_span = Some(self.inline.len()),
+            Tag::Link(destination, title) => {
+                self.inline.push('[');
+                let start = self.inline.len();
+                self.links.push((destination.into_owned(), title.into_owned(), start));
+            }
+            Tag::Image(destination, title) => {
+                self.inline.push_str("![");
+                let start = self.inline.len();
+                self.links.push((destination.into_owned(), title.into_owned(), start));
+            }
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {
+                self.flush_inline();
+                self.blank = true;
+            }
+            Tag::Header(level) => {
+                let title = self.inline.replace(NO_BREAK, " ");
+                self.inline.clear();
+                self.line(&format!("{} {}", "#".repeat(level as usize), title.trim()));
+                self.blank = true;
+            }
+            Tag::BlockQuote | Tag::FootnoteDefinition(_) => {
+                self.flush_inline();
+                self.containers.pop();
+                self.blank = true;
+            }
+            Tag::CodeBlock(_) => if let Some((info, code)) = self.code.take() {
+                let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+                self.line(&format!("{}{}", fence, info));
+                for line in code.trim_right_matches('\n').split('\n') {
+                    self.line(line);
+                }
+                self.line(&fence);
+                self.blank = true;
+            },
+            Tag::List(_) => {
+                self.flush_inline();
+                self.lists.pop();
+                // A blank line after a nested list would make the outer list a loose list.
+                if self.lists.is_empty() {
+                    self.blank = true;
+                }
+            }
+            Tag::Item => {
+                self.flush_inline();
+                self.containers.pop();
+            }
+            Tag::TableCell => {
+                let cell = self.inline.replace(NO_BREAK, " ").replace('|', "\\|");
+                self.inline.clear();
+                if let Some(ref mut table) = self.table {
+                    if let Some(row) = table.rows.last_mut() {
+                        row.push(cell.trim().to_owned());
+                    }
+                }
+            }
+            Tag::TableHead | Tag::TableRow => (),
+            Tag::Table(_) => if let Some(table) = self.table.take() {
+                for line in table.lines() {
+                    self.line(&line);
+                }
+                self.blank = true;
+            },
+            Tag::Emphasis => self.inline.push('*'),
+            Tag::Strong => self.inline.push_str("**"),
+            Tag::Code => if let Some(start) = self.code_span.take() {
+                // The code span must be fenced by more backticks than it contains.
+                let code = self.inline[start..].to_owned();
+                let fence = "`".repeat(longest_run(&code, '`') + 1);
+                let padding = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+                self.inline.truncate(start);
+                let fence = fence.as_str();
+                self.inline.push_str(&[fence, padding, code.as_str(), padding, fence].concat());
+            },
+            Tag::Link(..) | Tag::Image(..) => if let Some(link) = self.links.pop() {
+                let (destination, title, start) = link;
+                let label = self.definitions
+                    .iter()
+                    .find(|d| !d.duplicate && d.destination == destination && d.title == title)
+                    .map(|definition| definition.label.clone());
+
+                // Links which were resolved from a definition remain references to it. Labels
+                // are matched regardless of case, so text which matches the label is omitted.
+                if let Some(label) = label {
+                    let text = self.inline[start..].replace(NO_BREAK, " ").to_lowercase();
+                    if text == label.to_lowercase() {
+                        self.inline.push_str("][]");
+                    } else {
+                        self.inline.push_str(&format!("][{}]", label));
+                    }
+                    return;
+                }
+
+                let destination = destination.replace(' ', "%20");
+                if title.is_empty() {
+                    self.inline.push_str(&format!("]({})", destination));
+                } else {
+                    let title = title.replace('"', "\\\"").replace(' ', &NO_BREAK.to_string());
+                    self.inline.push_str(&format!("]({}{}\"{}\")", destination, NO_BREAK, title));
+                }
+            },
+            Tag::Rule => (),
+        }
+    }
+
+    /// Writes the inline markdown that has been collected as a wrapped paragraph.
+    fn flush_inline(&mut self) {
+        let text = self.inline.trim().to_owned();
+        self.inline.clear();
+        if text.is_empty() {
+            return;
+        }
+
+        let prefix_width = self.containers.iter().map(|c| c.rest.chars().count()).sum::<usize>();
+        let width = if self.width > prefix_width + 20 { self.width - prefix_width } else { 20 };
+
+        // Hard line breaks divide the paragraph into segments that are wrapped separately.
+        let segments: Vec<&str> = text.split("\\\n").collect();
+        for (index, segment) in segments.iter().enumerate() {
+            let mut lines = wrap(segment.trim(), width);
+            if index + 1 != segments.len() {
+                if let Some(last) = lines.last_mut() {
+                    last.push('\\');
+                }
+            }
+
+            for line in lines {
+                self.line(&line.replace(NO_BREAK, " "));
+            }
+        }
+    }
+
+    fn push_container(&mut self, first: &str, rest: &str) {
+        self.containers.push(Container { first: first.into(), rest: rest.into(), used: false });
+    }
+
+    /// Writes a line of text, prefixed by the containers that it belongs to.
+    fn line(&mut self, text: &str) {
+        if self.blank && !self.output.is_empty() {
+            let prefix: String = self.containers.iter().map(|c| c.rest.as_str()).collect();
+            self.output.push_str(prefix.trim_right());
+            self.output.push('\n');
+        }
+        self.blank = false;
+
+        let mut prefix = String::new();
+        for container in &mut self.containers {
+            prefix.push_str(if container.used { &container.rest } else { &container.first });
+            container.used = true;
+        }
+
+        // Empty lines, such as those within code blocks, should not have trailing whitespace.
+        if text.is_empty() {
+            self.output.push_str(prefix.trim_right());
+        } else {
+            self.output.push_str(&prefix);
+            self.output.push_str(text);
+        }
+        self.output.push('\n');
+    }
+}
+
+/// Wraps the text into lines no wider than `width`. Lines are never broken before a word
+/// which would be mistaken for the start of a block, such as `#` or `1.`.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split(' ').filter(|word| !word.is_empty()) {
+        let fits = current.chars().count() + 1 + word.chars().count() <= width;
+        if current.is_empty() {
+            current.push_str(word);
+        } else if fits || starts_block(word) {
+            current.push(' ');
+            current.push_str(word);
+        } else {
+            lines.push(current);
+            current = word.to_owned();
+        }
+    }
+
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    // The first word of the paragraph, or of the line after a hard line break, was escaped in
+    // the source if it resembled a block.
+    if let Some(first) = lines.first_mut() {
+        if starts_block(first.split(' ').next().unwrap_or("")) {
+            let digits = first.chars().take_while(|c| c.is_ascii_digit()).count();
+            first.insert(digits, '\\');
+        }
+    }
+
+    lines
+}
+
+/// Finds the link reference definitions of the document, which may only begin a paragraph.
+/// Each definition is confirmed by resolving a link with it.
+fn definitions(markdown: &str) -> Vec<Definition> {
+    let mut definitions: Vec<Definition> = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut paragraph_start = true;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_left();
+        let indented = line.len() - trimmed.len() >= 4;
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+                paragraph_start = true;
+            }
+            continue;
+        }
+
+        if !indented && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        let can_define = paragraph_start && !indented && !trimmed.starts_with("[^");
+        paragraph_start = trimmed.is_empty() || trimmed.starts_with('#');
+        if !can_define || !trimmed.starts_with('[') {
+            continue;
+        }
+
+        let label = match trimmed.find("]:") {
+            Some(end) if !trimmed[1..end].trim().is_empty() => &trimmed[1..end],
+            _ => continue,
+        };
+
+        // The first definition of a label is the one that links use.
+        let duplicate = definitions.iter().any(|d| d.label.to_lowercase() == label.to_lowercase());
+        let probe = format!("[probe][{}]\n\n{}\n", label, trimmed);
+        let link = Parser::new(&probe).filter_map(|event| match event {
+            Event::Start(Tag::Link(destination, title)) => {
+                Some((destination.into_owned(), title.into_owned()))
+            }
+            _ => None,
+        }).next();
+
+        if let Some((destination, title)) = link {
+            definitions.push(Definition {
+                label: label.to_owned(),
+                destination,
+                title,
+                source: trimmed.trim_right().to_owned(),
+                duplicate,
+            });
+            paragraph_start = true;
+        }
+    }
+
+    definitions
+}
+
+/// Whether the word would begin a block, if it were placed at the start of a line.
+fn starts_block(word: &str) -> bool {
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        return word[digits..].starts_with('.') || word[digits..].starts_with(')');
+    }
+
+    word.starts_with('#') || word.starts_with('>') || word.starts_with('=')
+        || word == "-" || word == "+" || word == "*" || word.starts_with("---")
+        || word.starts_with("```") || word.starts_with("~~~") || word.starts_with('|')
+}
+
+/// Escapes characters that would otherwise be interpreted as markdown.
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut escaped = String::with_capacity(text.len());
+    for (index, &c) in chars.iter().enumerate() {
+        let escape = match c {
+            '\\' | '`' | '*' | '[' | ']' | '<' => true,
+            // Ampersands would otherwise be read as the start of an entity.
+            '&' => is_reference(&chars[index + 1..]),
+            // Underscores within words can not begin or end emphasis.
+            '_' => {
+                let before = index.checked_sub(1).and_then(|i| chars.get(i));
+                let after = chars.get(index + 1);
+                !before.map_or(false, |c| c.is_alphanumeric())
+                    || !after.map_or(false, |c| c.is_alphanumeric())
+            }
+            _ => false,
+        };
+
+        if escape {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Whether the characters which follow an ampersand complete an entity, such as `&copy;`, or
+/// a numeric character reference, such as `&#169;` or `&#xA9;`.
+fn is_reference(chars: &[char]) -> bool {
+    let (is_digit, rest): (fn(&char) -> bool, &[char]) = match (chars.get(0), chars.get(1)) {
+        (Some(&'#'), Some(&'x')) | (Some(&'#'), Some(&'X')) => {
+            (char::is_ascii_hexdigit, &chars[2..])
+        }
+        (Some(&'#'), _) => (char::is_ascii_digit, &chars[1..]),
+        _ => (char::is_ascii_alphanumeric, chars),
+    };
+    let length = rest.iter().take_while(|c| is_digit(c)).count();
+    length > 0 && rest.get(length) == Some(&';')
+}
+
+/// The length of the longest run of the character within the text.
+fn longest_run(text: &str, character: char) -> usize {
+    let (mut longest, mut current) = (0, 0);
+    for c in text.chars() {
+        current = if c == character { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::html;
+
+    const WIDTH: usize = 40;
+
+    /// The HTML that the markdown renders to, with whitespace collapsed, so that documents
+    /// which only differ in how their paragraphs are wrapped compare equal.
+    fn structure(markdown: &str) -> String {
+        let mut output = String::new();
+        let options = OPTION_ENABLE_TABLES | OPTION_ENABLE_FOOTNOTES;
+        html::push_html(&mut output, Parser::new_ext(markdown, options));
+        output.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Formats the markdown, and checks that formatting it again changes nothing, and that
+    /// the formatted document has the same structure as the original.
+    fn format(markdown: &str) -> String {
+        let formatted = pretty_print(markdown, WIDTH);
+        assert_eq!(pretty_print(&formatted, WIDTH), formatted, "formatting is not idempotent");
+        assert_eq!(structure(&formatted), structure(markdown), "the structure changed");
+        formatted
+    }
+
+    #[test]
+    fn blocks_are_normalized() {
+        let markdown =
+            "Title\n=====\n\n* one\n* two\n\n***\n\n3. three\n7. four\n\n***\n\n    code\n";
+        assert_eq!(
+            format(markdown),
+            "# Title\n\n- one\n- two\n\n---\n\n3. three\n4. four\n\n---\n\n```\ncode\n```\n"
+        );
+    }
+
+    #[test]
+    fn paragraphs_are_wrapped() {
+        let markdown = "The quick brown fox jumps over the lazy dog, and then it jumps over the \
+                        dog again.\n";
+        assert_eq!(
+            format(markdown),
+            "The quick brown fox jumps over the lazy\ndog, and then it jumps over the dog\n\
+             again.\n"
+        );
+    }
+
+    #[test]
+    fn nested_containers_are_prefixed() {
+        let markdown = "> quoted\n> - item\n>   - nested\n\n1. first\n\n   second paragraph\n";
+        assert_eq!(
+            format(markdown),
+            "> quoted\n>\n> - item\n>   - nested\n\n1. first\n\n   second paragraph\n"
+        );
+    }
+
+    #[test]
+    fn code_is_kept_verbatim() {
+        let markdown = "Use `` a ` b `` here.\n\n~~~rust\nlet x = \"```\";\n\nlet y = 1;\n~~~\n";
+        assert_eq!(
+            format(markdown),
+            "Use ``a ` b`` here.\n\n````rust\nlet x = \"```\";\n\nlet y = 1;\n````\n"
+        );
+    }
+
+    #[test]
+    fn reference_links_are_kept() {
+        let markdown = "See [the docs][docs], [Docs][] and\n![logo][img].\n\n\
+                        [docs]: https://example.com/docs \"The Docs\"\n\
+                        [img]: logo.png\n";
+        assert_eq!(format(markdown), markdown);
+    }
+
+    #[test]
+    fn definitions_are_moved_to_the_end() {
+        let markdown = "[a]: /a\n\n# Heading\n\nA [link][a] and an [inline](/b) link.\n";
+        assert_eq!(
+            format(markdown),
+            "# Heading\n\nA [link][a] and an [inline](/b) link.\n\n[a]: /a\n"
+        );
+    }
+
+    #[test]
+    fn definitions_within_code_are_not_kept() {
+        let markdown = "```\n[a]: /a\n```\n\nSome text\n[b]: /b\n";
+        assert_eq!(format(markdown), "```\n[a]: /a\n```\n\nSome text \\[b\\]: /b\n");
+    }
+
+    #[test]
+    fn hard_breaks_escape_block_markers() {
+        for marker in &["# not a heading", "- not a list", "> not a quote", "1. not a list"] {
+            let markdown = format!("First line\\\n\\{}\n", marker);
+            let formatted = format(&markdown);
+            assert!(formatted.starts_with("First line\\\n"), "{}", formatted);
+        }
+    }
+
+    #[test]
+    fn words_which_resemble_blocks_are_not_wrapped() {
+        let markdown = "This sentence is long enough that it would wrap right before # and 1. \
+                        and then continue.\n";
+        let formatted = format(markdown);
+        assert!(formatted.lines().all(|line| !starts_block(line.split(' ').next().unwrap())));
+    }
+
+    #[test]
+    fn tables_are_aligned() {
+        let markdown = "| a | b |\n|:-|-:|\n| long cell | 1 |\n";
+        assert_eq!(
+            format(markdown),
+            "| a         |   b |\n| :-------- | --: |\n| long cell |   1 |\n"
+        );
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let markdown = "Not \\*emphasis\\*, \\[brackets\\] or\n\\_underscores\\_.\n";
+        assert_eq!(format(markdown), markdown);
+    }
+
+    #[test]
+    fn entities_are_escaped() {
+        let markdown = "Write \\&copy; for &copy;, \\&#169; or \\&#xA9;, but & and &c stay.\n";
+        assert_eq!(
+            format(markdown),
+            "Write \\&copy; for ©, \\&#169; or \\&#xA9;,\nbut & and &c stay.\n"
+        );
+    }
+}
@@ -1,17 +1,139 @@
 use horrorshow::Raw;
 use horrorshow::helper::doctype;
-use pulldown_cmark::{html, Parser};
+use outline::headings;
+use pulldown_cmark::{html, Event, Parser, Tag};
+use std::borrow::Cow;
+use std::path::Path;
+use url::percent_encode;
+use wiki::{replace_links, Notes};
+
+/// Documents which are larger than this, in bytes, are previewed a portion at a time, as
+/// rendering the whole of them whenever they are edited would make typing sluggish.
+pub const LARGE_DOCUMENT: usize = 512 * 1024;
+
+/// A portion of a document, which begins and ends between blocks.
+pub struct Excerpt<'a> {
+    pub markdown: &'a str,
+    /// The line that the excerpt begins on, counting from zero.
+    pub first:    usize,
+    /// The line after the last line of the excerpt.
+    pub end:      usize,
+}
+
+/// In goes markdown text; out comes HTML text. Wiki links lead to the destination that `href`
+/// creates for the document that they refer to, and `rewrite` may replace the destination of
+/// any other link.
+pub fn mark_to_html(
+    markdown: &str,
+    notes: Option<Notes>,
+    href: &Fn(&Path) -> String,
+    rewrite: &Fn(&str) -> Option<String>,
+) -> String {
+    // Headings are given IDs so that the preview may be scrolled to them.
+    let mut anchors = headings(markdown).into_iter().map(|heading| heading.anchor);
+    // Wiki links are resolved against the open folder's documents.
+    let markdown = replace_links(markdown, notes, href);
+    let parser = Parser::new(&markdown).map(|event| match event {
+        Event::Start(Tag::Header(level)) => {
+            let anchor = anchors.next().unwrap_or_default();
+            Event::Html(Cow::Owned(format!("<h{} id=\"{}\">", level, anchor)))
+        }
+        Event::Start(Tag::Link(destination, title)) => match rewrite(&destination) {
+            Some(destination) => Event::Start(Tag::Link(Cow::Owned(destination), title)),
+            None => Event::Start(Tag::Link(destination, title)),
+        },
+        Event::End(Tag::Header(level)) => Event::Html(Cow::Owned(format!("</h{}>\n", level))),
+        event => event,
+    });
 
-/// In goes markdown text; out comes HTML text.
-fn mark_to_html(markdown: &str) -> String {
-    let parser = Parser::new(&markdown);
     let mut buffer = String::new();
     html::push_html(&mut buffer, parser);
     buffer
 }
 
-/// In goes markdown text; out comes stylish HTML text.
-pub fn render(markdown: &str) -> String {
+/// In goes markdown text; out comes stylish HTML text. Wiki links are resolved against the
+/// notes of the open folder, if there is one.
+pub fn render(markdown: &str, notes: Option<Notes>) -> String { render_at(markdown, None, notes) }
+
+/// Renders the markdown text, scrolling to the heading with the given anchor once loaded.
+pub fn render_at(markdown: &str, anchor: Option<&str>, notes: Option<Notes>) -> String {
+    let scroll = anchor
+        .map(|anchor| {
+            format!(
+                "window.onload = function() {{ var h = document.getElementById('{}'); if (h) \
+                 {{ h.scrollIntoView(); }} }}",
+                anchor
+            )
+        })
+        .unwrap_or_default();
+
+    let body = mark_to_html(markdown, notes, &|note| file_uri(notes, note), &|_| None);
+    page(&body, &scroll)
+}
+
+/// Renders the lines of a large document from `first` to `last`, with a notice that the rest
+/// of the document is not previewed.
+pub fn render_excerpt(markdown: &str, first: usize, last: usize, notes: Option<Notes>) -> String {
+    let excerpt = excerpt(markdown, first, last);
+    let mut body = format!(
+        "<p class=\"excerpt\">This document is large, so only lines {} to {} are previewed.</p>",
+        excerpt.first + 1,
+        excerpt.end
+    );
+    let href = |note: &Path| file_uri(notes, note);
+    body.push_str(&mark_to_html(excerpt.markdown, notes, &href, &|_| None));
+    page(&body, "")
+}
+
+/// Selects the lines from `first` to `last`, widened to the nearest blank lines outside of
+/// code blocks, so that no block is cut in half.
+pub fn excerpt(markdown: &str, first: usize, last: usize) -> Excerpt {
+    let mut fence: Option<&str> = None;
+    let (mut start, mut start_line) = (0, 0);
+    let mut end = None;
+    let mut offset = 0;
+    let mut lines = 0;
+
+    for (number, line) in markdown.split('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len() + 1;
+        lines = number + 1;
+
+        let trimmed = line.trim_left();
+        let marker = if trimmed.starts_with("```") {
+            Some("```")
+        } else if trimmed.starts_with("~~~") {
+            Some("~~~")
+        } else {
+            None
+        };
+
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            _ => (),
+        }
+
+        if fence.is_some() || !trimmed.is_empty() {
+            continue;
+        }
+
+        if number <= first {
+            start = line_start;
+            start_line = number;
+        } else if number >= last {
+            end = Some((line_start, number));
+            break;
+        }
+    }
+
+    let (end, end_line) = end.unwrap_or((markdown.len(), lines));
+    Excerpt { markdown: &markdown[start..end], first: start_line, end: end_line }
+}
+
+/// Wraps the rendered HTML within the preview's stylish page, which runs the script once
+/// loaded.
+pub fn page(body: &str, script: &str) -> String {
     format!(
         "{}",
         html!(
@@ -24,15 +146,60 @@ pub fn render(markdown: &str) -> String {
                     script {
                         : Raw("hljs.initHighlightingOnLoad()")
                     }
+                    script {
+                        : Raw(script)
+                    }
                     style {
                         : "body { width: 80%; margin: 0 auto }";
-                        : "img { max-width: 80% }"
+                        : "img { max-width: 80% }";
+                        : "a.wiki.missing { color: #c01c28; text-decoration: underline dotted }";
+                        : "p.excerpt { color: #777; font-style: italic }"
                     }
                 }
                 body {
-                    : Raw(&mark_to_html(markdown));
+                    : Raw(body);
                 }
             }
         )
     )
 }
+
+/// Links to the note within the preview, so that clicking it opens the note in the editor.
+fn file_uri(notes: Option<Notes>, note: &Path) -> String {
+    let path = notes.map_or(note.to_path_buf(), |notes| notes.folder.join(note));
+    ["file://", percent_encode(&path.to_string_lossy()).as_str()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excerpt_without_blank_lines_is_whole_document() {
+        let markdown = "a\nb\nc";
+        let excerpt = excerpt(markdown, 1, 1);
+        assert_eq!(excerpt.markdown, markdown);
+        assert_eq!((excerpt.first, excerpt.end), (0, 3));
+    }
+
+    #[test]
+    fn excerpt_widens_to_blank_lines() {
+        let excerpt = excerpt("a\n\nb\nc\n\nd", 2, 3);
+        assert_eq!(excerpt.markdown, "\nb\nc\n");
+        assert_eq!((excerpt.first, excerpt.end), (1, 4));
+    }
+
+    #[test]
+    fn excerpt_keeps_code_blocks_whole() {
+        let excerpt = excerpt("a\n\n```\nx\n\ny\n```\n\nb", 5, 5);
+        assert_eq!(excerpt.markdown, "\n```\nx\n\ny\n```\n");
+        assert_eq!((excerpt.first, excerpt.end), (1, 7));
+    }
+
+    #[test]
+    fn excerpt_fences_close_with_their_own_marker() {
+        let excerpt = excerpt("~~~\n```\n\n~~~\n\nz", 2, 2);
+        assert_eq!(excerpt.markdown, "~~~\n```\n\n~~~\n");
+        assert_eq!((excerpt.first, excerpt.end), (0, 4));
+    }
+}
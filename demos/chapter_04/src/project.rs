@@ -0,0 +1,158 @@
+use outline::headings;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file extensions of markdown documents.
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdown", "mkd", "mkdn"];
+
+/// Whether the file at the path is a markdown document.
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .map_or(false, |ext| MARKDOWN_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Patterns which match the file names of markdown documents.
+pub fn markdown_patterns() -> Vec<String> {
+    MARKDOWN_EXTENSIONS.iter().map(|ext| format!("*.{}", ext)).collect()
+}
+
+/// Suggests a file name for a new document, which is the title of its first heading, or else
+/// `Untitled.md`. Characters which may not be used within file names are replaced.
+pub fn suggested_name(markdown: &str) -> String {
+    let title = headings(markdown).into_iter().next().map_or(String::new(), |h| h.title);
+    let name: String = title
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '-' } else { c })
+        .take(64)
+        .collect();
+
+    match name.trim().trim_matches('.') {
+        "" => "Untitled.md".to_owned(),
+        name => format!("{}.md", name),
+    }
+}
+
+/// Appends the `.md` extension to paths which do not have an extension.
+pub fn with_markdown_extension(path: PathBuf) -> PathBuf {
+    if path.extension().is_some() {
+        path
+    } else {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".md");
+        path.with_file_name(name)
+    }
+}
+
+/// Collects the paths of every markdown document beneath the folder, relative to the folder
+/// and sorted by path. Hidden files and directories, such as `.git`, are skipped.
+pub fn markdown_files(folder: &Path) -> Vec<PathBuf> { walk(folder).1 }
+
+/// Collects the directories beneath the folder, including the folder itself as an empty
+/// path, along with the markdown documents within them, as `markdown_files` does.
+pub fn walk(folder: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let (mut directories, mut files) = (vec![PathBuf::new()], Vec::new());
+    collect(folder, Path::new(""), &mut directories, &mut files);
+    files.sort();
+    (directories, files)
+}
+
+fn collect(
+    folder: &Path,
+    relative: &Path,
+    directories: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) {
+    let entries = match fs::read_dir(folder.join(relative)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = relative.join(&name);
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => {
+                directories.push(path.clone());
+                collect(folder, &path, directories, files);
+            }
+            Ok(_) if is_markdown(&path) => files.push(path),
+            _ => (),
+        }
+    }
+}
+
+/// Whether the relative path matches the filter that was typed. Every word of the filter
+/// must appear within the path, ignoring case.
+pub fn matches(relative: &Path, filter: &str) -> bool {
+    let path = relative.to_string_lossy().to_lowercase();
+    filter.to_lowercase().split_whitespace().all(|word| path.contains(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::process;
+
+    #[test]
+    fn every_word_of_the_filter_must_match() {
+        let path = Path::new("Journal/2018/January.md");
+        assert!(matches(path, ""));
+        assert!(matches(path, "january"));
+        assert!(matches(path, "jan  2018"));
+        assert!(matches(path, "JOURNAL jan"));
+        assert!(!matches(path, "journal february"));
+    }
+
+    #[test]
+    fn untitled_documents_are_named_after_their_first_heading() {
+        assert_eq!(suggested_name("Some text\n\n# Garden\n\n## Beds"), "Garden.md");
+        assert_eq!(suggested_name("Some text without a heading"), "Untitled.md");
+        assert_eq!(suggested_name(""), "Untitled.md");
+    }
+
+    #[test]
+    fn names_do_not_contain_path_separators_or_reserved_characters() {
+        assert_eq!(suggested_name("# Plans/Ideas: 2018?"), "Plans-Ideas- 2018-.md");
+        assert_eq!(suggested_name("# C:\\Users\\Me"), "C--Users-Me.md");
+        assert_eq!(suggested_name("# ../secret"), "-secret.md");
+        assert_eq!(suggested_name("# ..."), "Untitled.md");
+        assert_eq!(suggested_name("# What's \"new\" | next"), "What's -new- - next.md");
+    }
+
+    #[test]
+    fn the_markdown_extension_is_only_added_when_missing() {
+        let extended = |path: &str| with_markdown_extension(PathBuf::from(path));
+        assert_eq!(extended("/notes/garden"), PathBuf::from("/notes/garden.md"));
+        assert_eq!(extended("/notes/garden.md"), PathBuf::from("/notes/garden.md"));
+        assert_eq!(extended("/notes/garden.markdown"), PathBuf::from("/notes/garden.markdown"));
+        assert_eq!(extended("garden.txt"), PathBuf::from("garden.txt"));
+    }
+
+    #[test]
+    fn documents_are_found_beneath_the_folder() {
+        let folder = env::temp_dir().join(format!("project-test-{}", process::id()));
+        for dir in &["notes/old", ".git", "empty"] {
+            fs::create_dir_all(folder.join(dir)).unwrap();
+        }
+        for file in &["b.md", "a.markdown", "notes/old/c.MD", "notes/image.png", ".git/d.md"] {
+            File::create(folder.join(file)).unwrap();
+        }
+
+        let files = markdown_files(&folder);
+        let (mut directories, _) = walk(&folder);
+        directories.sort();
+        fs::remove_dir_all(&folder).unwrap();
+
+        let expected = ["a.markdown", "b.md", "notes/old/c.MD"];
+        assert_eq!(files, expected.iter().map(PathBuf::from).collect::<Vec<_>>());
+        let expected = ["", "empty", "notes", "notes/old"];
+        assert_eq!(directories, expected.iter().map(PathBuf::from).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,220 @@
+use regex::{self, Regex, RegexBuilder};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// What to search for within a folder, and which of its files to search.
+#[derive(Clone)]
+pub struct Query {
+    pub pattern:        String,
+    /// Whether the pattern is a regular expression, rather than plain text.
+    pub regex:          bool,
+    pub case_sensitive: bool,
+    /// Globs of the files to search, such as `docs/**`. Every file is searched if empty.
+    pub include:        Vec<String>,
+    /// Globs of the files which must not be searched.
+    pub exclude:        Vec<String>,
+}
+
+/// A line of a file which matched the query.
+pub struct Match {
+    pub line:  i32,
+    pub text:  String,
+    /// The byte range of the first match within the line's text.
+    pub start: usize,
+    pub end:   usize,
+}
+
+/// Every line of a file which matched the query.
+pub struct FileMatches {
+    /// The path of the file, relative to the folder that was searched.
+    pub path:    PathBuf,
+    pub matches: Vec<Match>,
+}
+
+impl Query {
+    /// Compiles the pattern into a regular expression, escaping it if it is plain text.
+    pub fn compile(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex { self.pattern.clone() } else { regex::escape(&self.pattern) };
+        RegexBuilder::new(&pattern).case_insensitive(!self.case_sensitive).build()
+    }
+
+    /// Whether the file, relative to the folder, should be searched.
+    pub fn includes(&self, relative: &Path) -> bool {
+        let path = relative.to_string_lossy();
+        let matches_any = |globs: &[String]| globs.iter().any(|glob| glob_matches(glob, &path));
+        (self.include.is_empty() || matches_any(&self.include)) && !matches_any(&self.exclude)
+    }
+}
+
+/// Splits a comma-separated list of globs, such as `*.md, docs/**`.
+pub fn parse_globs(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|glob| glob.trim())
+        .filter(|glob| !glob.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Searches each line of the file for the regular expression.
+pub fn search_file(regex: &Regex, path: &Path) -> Vec<Match> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter_map(|(number, line)| {
+            let text = match line {
+                Ok(text) => text,
+                Err(_) => return None,
+            };
+
+            let found = regex.find(&text).map(|found| (found.start(), found.end()));
+            found.map(|(start, end)| Match { line: number as i32, text, start, end })
+        })
+        .collect()
+}
+
+/// Whether the path matches the glob. `*` matches within a single directory, `**` matches
+/// across directories, and `?` matches a single character. Globs without a slash are
+/// matched against the file name alone.
+pub fn glob_matches(glob: &str, path: &str) -> bool {
+    let path = if glob.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+
+    let glob: Vec<char> = glob.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches_from(&glob, &path)
+}
+
+fn matches_from(glob: &[char], path: &[char]) -> bool {
+    match glob.first() {
+        None => path.is_empty(),
+        Some(&'*') if glob.get(1) == Some(&'*') && glob.get(2) == Some(&'/') => {
+            // A `**/` matches whole directories, and may also match no directories at all.
+            (0..path.len() + 1)
+                .filter(|&skip| skip == 0 || path[skip - 1] == '/')
+                .any(|skip| matches_from(&glob[3..], &path[skip..]))
+        }
+        Some(&'*') if glob.get(1) == Some(&'*') => {
+            (0..path.len() + 1).any(|skip| matches_from(&glob[2..], &path[skip..]))
+        }
+        Some(&'*') => {
+            let within = path.iter().take_while(|&&c| c != '/').count();
+            (0..within + 1).any(|skip| matches_from(&glob[1..], &path[skip..]))
+        }
+        Some(&'?') => {
+            path.first().map_or(false, |&c| c != '/') && matches_from(&glob[1..], &path[1..])
+        }
+        Some(&c) => path.first() == Some(&c) && matches_from(&glob[1..], &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    fn query(pattern: &str, include: &str, exclude: &str) -> Query {
+        Query {
+            pattern:        pattern.to_owned(),
+            regex:          false,
+            case_sensitive: false,
+            include:        parse_globs(include),
+            exclude:        parse_globs(exclude),
+        }
+    }
+
+    #[test]
+    fn globs_without_a_slash_match_file_names() {
+        assert!(glob_matches("*.md", "notes.md"));
+        assert!(glob_matches("*.md", "docs/deep/notes.md"));
+        assert!(!glob_matches("*.md", "notes.markdown"));
+        assert!(glob_matches("note?.md", "docs/notes.md"));
+        assert!(!glob_matches("note?.md", "docs/note.md"));
+    }
+
+    #[test]
+    fn stars_match_within_a_directory() {
+        assert!(glob_matches("docs/*.md", "docs/a.md"));
+        assert!(!glob_matches("docs/*.md", "docs/deep/a.md"));
+        assert!(!glob_matches("docs/a?b.md", "docs/a/b.md"));
+    }
+
+    #[test]
+    fn double_stars_match_across_directories() {
+        assert!(glob_matches("docs/**", "docs/a.md"));
+        assert!(glob_matches("docs/**", "docs/deep/a.md"));
+        assert!(!glob_matches("docs/**", "other/a.md"));
+        assert!(glob_matches("**/a.md", "a.md"));
+        assert!(glob_matches("**/a.md", "docs/deep/a.md"));
+        assert!(glob_matches("docs/**/a.md", "docs/a.md"));
+        assert!(glob_matches("docs/**/a.md", "docs/deep/a.md"));
+        // Directories are matched whole.
+        assert!(!glob_matches("docs/**/a.md", "docs/xa.md"));
+        assert!(!glob_matches("**/a.md", "docs/ba.md"));
+    }
+
+    #[test]
+    fn globs_are_separated_by_commas() {
+        assert_eq!(parse_globs(" *.md, docs/** ,, "), vec!["*.md", "docs/**"]);
+        assert!(parse_globs("  ").is_empty());
+    }
+
+    #[test]
+    fn files_are_included_and_excluded() {
+        let everything = query("x", "", "");
+        assert!(everything.includes(Path::new("any/file.txt")));
+
+        let docs = query("x", "docs/**, *.markdown", "drafts/**, *.tmp.md");
+        assert!(docs.includes(Path::new("docs/a.md")));
+        assert!(docs.includes(Path::new("notes/a.markdown")));
+        assert!(!docs.includes(Path::new("notes/a.md")));
+        assert!(!docs.includes(Path::new("docs/b.tmp.md")));
+
+        let drafts = query("x", "", "drafts/**");
+        assert!(!drafts.includes(Path::new("drafts/a.md")));
+        assert!(drafts.includes(Path::new("a.md")));
+    }
+
+    #[test]
+    fn plain_text_is_escaped() {
+        let plain = query("a.b (c)", "", "");
+        let regex = plain.compile().unwrap();
+        assert!(regex.is_match("A.B (C)"));
+        assert!(!regex.is_match("axb (c)"));
+
+        let mut sensitive = query("a.b", "", "");
+        sensitive.regex = true;
+        sensitive.case_sensitive = true;
+        let regex = sensitive.compile().unwrap();
+        assert!(regex.is_match("axb"));
+        assert!(!regex.is_match("AXB"));
+
+        sensitive.pattern = "(".to_owned();
+        assert!(sensitive.compile().is_err());
+    }
+
+    #[test]
+    fn lines_of_files_are_searched() {
+        let path = env::temp_dir().join(format!("search-test-{}.md", ::std::process::id()));
+        let mut file = fs::File::create(&path).unwrap();
+        file.write_all(b"first\nthe needle\nthird needle\n").unwrap();
+        let regex = query("NEEDLE", "", "").compile().unwrap();
+        let found = search_file(&regex, &path);
+        let _ = fs::remove_file(&path);
+
+        let found: Vec<_> =
+            found.iter().map(|m| (m.line, m.text.as_str(), m.start, m.end)).collect();
+        assert_eq!(found, vec![(1, "the needle", 4, 10), (2, "third needle", 6, 12)]);
+        assert!(search_file(&regex, Path::new("/missing")).is_empty());
+    }
+}
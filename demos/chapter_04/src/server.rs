@@ -0,0 +1,506 @@
+use assets::is_image;
+use preview::{mark_to_html, page};
+use project::is_markdown;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use url::{percent_decode, percent_encode};
+use wiki::Notes;
+
+/// The port which the server listens on, if it is free. Otherwise, any free port is used.
+const PORT: u16 = 8377;
+
+/// The longest request line, or header, which is read, in bytes.
+const MAX_LINE: usize = 8 * 1024;
+
+/// The most headers that a request may have.
+const MAX_HEADERS: usize = 100;
+
+/// The path which pages poll to learn whether they should be reloaded.
+const VERSION_PATH: &str = "/__version";
+
+/// The extensions of files, other than images and the folder's documents, that documents may
+/// refer to and which are served.
+const ASSET_EXTENSIONS: &[&str] = &["css", "ico", "pdf"];
+
+/// Polls the server for changes, and reloads the page when anything changes, restoring the
+/// position that the page was scrolled to.
+const LIVE_RELOAD: &str = "(function() {
+    var key = 'scroll:' + location.pathname;
+    var saved = sessionStorage.getItem(key);
+    if (saved) {
+        window.addEventListener('load', function() { window.scrollTo(0, +saved); });
+    }
+    setInterval(function() {
+        var request = new XMLHttpRequest();
+        request.open('GET', '/__version');
+        request.onload = function() {
+            if (request.status === 200 && +request.responseText !== VERSION) {
+                sessionStorage.setItem(key, window.scrollY);
+                location.reload();
+            }
+        };
+        request.send();
+    }, 500);
+})();";
+
+/// What the server serves, which is kept up to date as the document is edited.
+#[derive(Default)]
+struct Shared {
+    /// The text of the current document, which may not have been saved.
+    markdown: String,
+    /// Where the current document is saved, if it has been.
+    document: Option<PathBuf>,
+    /// The open folder, and the documents within it.
+    folder:   Option<(PathBuf, Vec<PathBuf>)>,
+    /// Incremented whenever anything that is served changes, so that pages reload.
+    version:  u64,
+}
+
+/// An HTTP server which serves the rendered document, and the documents of the open folder,
+/// to web browsers. Pages reload themselves whenever the document is changed.
+pub struct Server {
+    /// The address that the server is listening on.
+    pub address: SocketAddr,
+    /// Whether other devices on the network may connect, rather than only this computer.
+    pub shared:  bool,
+    state:       Arc<RwLock<Shared>>,
+    running:     Arc<AtomicBool>,
+}
+
+impl Server {
+    /// Begins serving on a background thread. Only connections from this computer are
+    /// accepted, unless the server is shared with the local network.
+    pub fn start(shared: bool) -> io::Result<Server> {
+        let ip = if shared { Ipv4Addr::new(0, 0, 0, 0) } else { Ipv4Addr::new(127, 0, 0, 1) };
+        let listener = TcpListener::bind((ip, PORT)).or_else(|_| TcpListener::bind((ip, 0)))?;
+        let address = listener.local_addr()?;
+
+        // Requests must name this computer as their host, so that other web sites can not
+        // rename themselves to this computer's address and read the documents.
+        let mut hosts = vec!["localhost".to_owned(), "127.0.0.1".to_owned()];
+        if shared {
+            hosts.extend(local_ip().map(|ip| ip.to_string()));
+        }
+        let hosts = Arc::new(hosts);
+
+        let state = Arc::new(RwLock::new(Shared::default()));
+        let running = Arc::new(AtomicBool::new(true));
+        {
+            let (state, running) = (state.clone(), running.clone());
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    if let Ok(stream) = stream {
+                        let (state, hosts) = (state.clone(), hosts.clone());
+                        thread::spawn(move || {
+                            if let Err(why) = respond(stream, &state, &hosts) {
+                                eprintln!("unable to respond to a preview request: {}", why);
+                            }
+                        });
+                    }
+                }
+            });
+        }
+
+        Ok(Server { address, shared, state, running })
+    }
+
+    /// The address that web browsers should visit. Shared servers are addressed by this
+    /// computer's address on the local network.
+    pub fn url(&self) -> String {
+        let ip = if self.shared {
+            local_ip().unwrap_or(self.address.ip())
+        } else {
+            self.address.ip()
+        };
+        format!("http://{}/", SocketAddr::new(ip, self.address.port()))
+    }
+
+    /// Serves the document's latest text, reloading the pages that are being viewed.
+    pub fn update(&self, markdown: &str, document: Option<&Path>, folder: Option<Notes>) {
+        let mut state = self.state.write().unwrap();
+        state.markdown.clear();
+        state.markdown.push_str(markdown);
+        state.document = document.map(Path::to_path_buf);
+
+        let same_folder = match (state.folder.as_ref(), folder) {
+            (Some(&(ref path, ref files)), Some(notes)) => {
+                path == notes.folder && files.as_slice() == notes.files
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        if !same_folder {
+            state.folder = folder.map(|notes| (notes.folder.to_path_buf(), notes.files.to_vec()));
+        }
+
+        state.version += 1;
+    }
+
+    /// Stops accepting connections.
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        // Wake the listener, which is waiting for a connection, so that it notices.
+        let wake = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), self.address.port());
+        let _ = TcpStream::connect(wake);
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) { self.stop(); }
+}
+
+/// Finds this computer's address on the local network. No packets are sent, as connecting a
+/// UDP socket only chooses the interface that it would be sent from.
+fn local_ip() -> Option<IpAddr> {
+    let socket = match UdpSocket::bind("0.0.0.0:0") {
+        Ok(socket) => socket,
+        Err(_) => return None,
+    };
+
+    match socket.connect("192.0.2.1:80").and_then(|_| socket.local_addr()) {
+        Ok(address) => Some(address.ip()),
+        Err(_) => None,
+    }
+}
+
+/// Reads a request from the stream, and writes the response. Requests which do not name one
+/// of the hosts are forbidden.
+fn respond(mut stream: TcpStream, state: &RwLock<Shared>, hosts: &[String]) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let (request_line, host) = match read_head(&mut BufReader::new(&stream))? {
+        Some(head) => head,
+        None => {
+            let status = "431 Request Header Fields Too Large";
+            return write_response(&mut stream, status, "text/plain", b"Request Too Large");
+        }
+    };
+
+    if !is_allowed_host(host.as_ref().map(|host| host.as_str()), hosts) {
+        return write_response(&mut stream, "403 Forbidden", "text/plain", b"Forbidden");
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return write_response(&mut stream, "400 Bad Request", "text/plain", b"Bad Request"),
+    };
+
+    if method != "GET" {
+        let status = "405 Method Not Allowed";
+        return write_response(&mut stream, status, "text/plain", b"Method Not Allowed");
+    }
+
+    let path = percent_decode(target.split(|c| c == '?' || c == '#').next().unwrap_or("/"));
+    let state = state.read().unwrap();
+
+    if path == VERSION_PATH {
+        let version = state.version.to_string();
+        return write_response(&mut stream, "200 OK", "text/plain", version.as_bytes());
+    }
+
+    let current = current_path(&state);
+    if path == "/" {
+        let location = href(&current);
+        let response = format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        );
+        return stream.write_all(response.as_bytes());
+    }
+
+    // Paths which lead outside of the served directory, or to hidden files, are not found.
+    let relative = match relative_path(&path) {
+        Some(relative) => relative,
+        None => return not_found(&mut stream),
+    };
+
+    if relative == current {
+        let html = render(&state, &state.markdown);
+        return write_response(&mut stream, "200 OK", "text/html; charset=utf-8", html.as_bytes());
+    }
+
+    if !is_servable(&state, &relative) {
+        return not_found(&mut stream);
+    }
+
+    let file = match root(&state) {
+        Some(root) => root.join(&relative),
+        None => return not_found(&mut stream),
+    };
+
+    let mut contents = Vec::new();
+    if File::open(&file).and_then(|mut file| file.read_to_end(&mut contents)).is_err() {
+        return not_found(&mut stream);
+    }
+
+    if is_markdown(&file) {
+        let html = render(&state, &String::from_utf8_lossy(&contents));
+        write_response(&mut stream, "200 OK", "text/html; charset=utf-8", html.as_bytes())
+    } else {
+        write_response(&mut stream, "200 OK", content_type(&file), &contents)
+    }
+}
+
+/// Reads the request line and the headers of a request, and returns the request line along
+/// with the value of the Host header. Only the host is needed, but every header must be read
+/// before responding. Returns **None** if a line is longer than `MAX_LINE`, or if there are
+/// more than `MAX_HEADERS` headers.
+fn read_head<R: BufRead>(reader: &mut R) -> io::Result<Option<(String, Option<String>)>> {
+    let mut request_line = String::new();
+    if !read_limited_line(reader, &mut request_line)? {
+        return Ok(None);
+    }
+
+    let mut host = None;
+    let mut header = String::new();
+    for _ in 0..MAX_HEADERS + 1 {
+        header.clear();
+        if !read_limited_line(reader, &mut header)? {
+            return Ok(None);
+        }
+        // The headers end with a blank line, or when the connection is closed.
+        if header.trim_right_matches(|c| c == '\r' || c == '\n').is_empty() {
+            return Ok(Some((request_line, host)));
+        }
+
+        if let Some(colon) = header.find(':') {
+            if header[..colon].trim().eq_ignore_ascii_case("host") {
+                host = Some(header[colon + 1..].trim().to_owned());
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads a line of at most `MAX_LINE` bytes. Returns `false` if the line is longer.
+fn read_limited_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<bool> {
+    let read = reader.by_ref().take(MAX_LINE as u64 + 1).read_line(line)?;
+    Ok(read <= MAX_LINE)
+}
+
+/// The directory which is served. This is the open folder, unless the current document is
+/// outside of it, in which case it is the document's directory.
+fn root(state: &Shared) -> Option<&Path> {
+    let folder = state.folder.as_ref().map(|&(ref folder, _)| folder.as_path());
+    match (folder, state.document.as_ref()) {
+        (Some(folder), Some(document)) if !document.starts_with(folder) => document.parent(),
+        (None, Some(document)) => document.parent(),
+        (folder, _) => folder,
+    }
+}
+
+/// The path of the current document, relative to the directory that is served.
+fn current_path(state: &Shared) -> PathBuf {
+    let document = match state.document {
+        Some(ref document) => document,
+        None => return PathBuf::from("untitled.md"),
+    };
+
+    match root(state).and_then(|root| document.strip_prefix(root).ok()) {
+        Some(relative) => relative.to_path_buf(),
+        None => PathBuf::from(document.file_name().unwrap_or_default()),
+    }
+}
+
+/// Converts the request's path into a path relative to the served directory. Returns
+/// **None** if the path would lead outside of it, or to a hidden file or directory, such as
+/// `.git`.
+fn relative_path(path: &str) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in Path::new(path.trim_left_matches('/')).components() {
+        match component {
+            Component::Normal(name) if !name.to_string_lossy().starts_with('.') => {
+                relative.push(name)
+            }
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+    Some(relative)
+}
+
+/// Whether the file, relative to the served directory, may be served. Besides the current
+/// document, only the open folder's documents and the images and other assets that documents
+/// refer to are served.
+fn is_servable(state: &Shared, relative: &Path) -> bool {
+    if is_markdown(relative) {
+        return match (state.folder.as_ref(), root(state)) {
+            (Some(&(ref folder, ref files)), Some(root)) if root == folder.as_path() => {
+                files.iter().any(|file| file == relative)
+            }
+            _ => false,
+        };
+    }
+
+    let extension = relative.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    is_image(relative) || extension.map_or(false, |ext| ASSET_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Whether the value of the request's `Host` header names one of the hosts, on any port.
+fn is_allowed_host(host: Option<&str>, hosts: &[String]) -> bool {
+    let host = match host {
+        Some(host) => host.to_lowercase(),
+        None => return false,
+    };
+
+    let name = if host.starts_with('[') {
+        host.find(']').map_or(host.as_str(), |end| &host[..end + 1])
+    } else {
+        host.split(':').next().unwrap_or("")
+    };
+    hosts.iter().any(|allowed| allowed == name)
+}
+
+/// Renders the markdown into a page which reloads itself as the document changes. Wiki links
+/// lead to the pages of the notes that they refer to.
+fn render(state: &Shared, markdown: &str) -> String {
+    let notes = match state.folder {
+        Some((ref folder, ref files)) if root(state) == Some(folder.as_path()) => {
+            Some(Notes { folder, files })
+        }
+        _ => None,
+    };
+
+    let body = mark_to_html(markdown, notes, &href, &|_| None);
+    page(&body, &LIVE_RELOAD.replace("VERSION", &state.version.to_string()))
+}
+
+/// Links to the path, relative to the served directory, from any page.
+fn href(relative: &Path) -> String {
+    let components: Vec<String> = relative
+        .components()
+        .map(|component| percent_encode(&component.as_os_str().to_string_lossy()))
+        .collect();
+    ["/", components.join("/").as_str()].concat()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    match extension.as_ref().map(|ext| ext.as_str()) {
+        Some("css") => "text/css",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        Some("ico") => "image/x-icon",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn not_found(stream: &mut TcpStream) -> io::Result<()> {
+    write_response(stream, "404 Not Found", "text/plain", b"Not Found")
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n\
+         Connection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts() -> Vec<String> { vec!["localhost".into(), "127.0.0.1".into()] }
+
+    fn shared(document: Option<&str>, folder: Option<&str>) -> Shared {
+        let files = vec![PathBuf::from("a.md"), PathBuf::from("notes/b.md")];
+        Shared {
+            markdown: String::new(),
+            document: document.map(PathBuf::from),
+            folder:   folder.map(|folder| (PathBuf::from(folder), files)),
+            version:  0,
+        }
+    }
+
+    fn head(request: &str) -> Option<(String, Option<String>)> {
+        read_head(&mut request.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn the_host_is_read_from_the_headers() {
+        let request = "GET / HTTP/1.1\r\nAccept: */*\r\nHOST: localhost:8377\r\n\r\nbody";
+        let expected = ("GET / HTTP/1.1\r\n".to_owned(), Some("localhost:8377".to_owned()));
+        assert_eq!(head(request), Some(expected));
+        // The connection may close before the blank line.
+        assert_eq!(head("GET / HTTP/1.0\n"), Some(("GET / HTTP/1.0\n".to_owned(), None)));
+    }
+
+    #[test]
+    fn large_requests_are_refused() {
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(head(&long_line), None);
+
+        let long_header = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(head(&long_header), None);
+
+        let headers = "X: y\r\n".repeat(MAX_HEADERS);
+        let request = format!("GET / HTTP/1.1\r\n{}\r\n", headers);
+        assert!(head(&request).is_some());
+        let request = format!("GET / HTTP/1.1\r\n{}X: y\r\n\r\n", headers);
+        assert_eq!(head(&request), None);
+    }
+
+    #[test]
+    fn only_this_computer_is_an_allowed_host() {
+        assert!(is_allowed_host(Some("localhost:8377"), &hosts()));
+        assert!(is_allowed_host(Some("127.0.0.1"), &hosts()));
+        assert!(is_allowed_host(Some("LocalHost:80"), &hosts()));
+        assert!(!is_allowed_host(Some("attacker.example:8377"), &hosts()));
+        assert!(!is_allowed_host(Some("localhost.attacker.example"), &hosts()));
+        assert!(!is_allowed_host(Some("[::1]:8377"), &hosts()));
+        assert!(!is_allowed_host(None, &hosts()));
+    }
+
+    #[test]
+    fn paths_stay_within_the_served_directory() {
+        assert_eq!(relative_path("/notes/./b.md"), Some(PathBuf::from("notes/b.md")));
+        assert_eq!(relative_path("/../secret.md"), None);
+        assert_eq!(relative_path("/notes/../../secret.md"), None);
+        assert_eq!(relative_path("/.git/config"), None);
+        assert_eq!(relative_path("/notes/.env"), None);
+    }
+
+    #[test]
+    fn only_documents_and_assets_are_servable() {
+        let state = shared(Some("/folder/a.md"), Some("/folder"));
+        assert!(is_servable(&state, Path::new("notes/b.md")));
+        assert!(is_servable(&state, Path::new("assets/cat.PNG")));
+        assert!(is_servable(&state, Path::new("style.css")));
+        assert!(!is_servable(&state, Path::new("notes/c.md")));
+        assert!(!is_servable(&state, Path::new("secret.txt")));
+        assert!(!is_servable(&state, Path::new("script.js")));
+        assert!(!is_servable(&state, Path::new("Cargo.toml")));
+
+        // The folder's documents are not served beside a document from elsewhere.
+        let state = shared(Some("/elsewhere/a.md"), Some("/folder"));
+        assert!(!is_servable(&state, Path::new("notes/b.md")));
+        assert!(is_servable(&state, Path::new("image.jpg")));
+    }
+}
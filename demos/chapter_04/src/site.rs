@@ -0,0 +1,533 @@
+use horrorshow::Raw;
+use horrorshow::helper::doctype;
+use encoding::detect;
+use outline::{headings, slugify};
+use preview::mark_to_html;
+use project::{is_markdown, markdown_files};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use url::percent_encode;
+use wiki::{escape, note_name, Notes};
+
+/// The name of the directory that a site is built into, when no other is given.
+pub const DEFAULT_OUTPUT: &str = "_site";
+
+/// The stylesheet which every page of the site shares. The site does not depend upon
+/// anything outside of the output directory, so that it may be read offline.
+const STYLESHEET: &str = "\
+body { margin: 0; font-family: sans-serif; line-height: 1.5; color: #222 }
+nav { position: fixed; top: 0; bottom: 0; left: 0; width: 16em; overflow-y: auto;
+      padding: 1em; background: #f6f6f6; border-right: 1px solid #ddd; box-sizing: border-box }
+nav ul { list-style: none; padding-left: 0 }
+nav li.current > a { font-weight: bold }
+main { margin-left: 16em; padding: 1em 2em; max-width: 50em }
+img { max-width: 80% }
+pre { padding: 0.5em; background: #f6f6f6; overflow-x: auto }
+code { font-family: monospace }
+.tags a { margin-right: 0.5em }
+a.wiki.missing { color: #c01c28; text-decoration: underline dotted }
+";
+
+/// A markdown document of the folder, which is rendered into a page of the site.
+pub struct Page {
+    /// The path of the document, relative to the folder.
+    pub source:   PathBuf,
+    /// The path of the page, relative to the output directory.
+    pub output:   PathBuf,
+    pub title:    String,
+    pub tags:     Vec<String>,
+    /// The document's markdown, without its front matter.
+    pub markdown: String,
+}
+
+/// Describes what was written when a site was built.
+pub struct Report {
+    pub pages:   usize,
+    pub tags:    usize,
+    pub assets:  usize,
+    /// The documents which were not rendered, and why.
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+impl Page {
+    /// Reads the document, taking its title and tags from its front matter, such as:
+    ///
+    /// ```text
+    /// ---
+    /// title: Getting Started
+    /// tags: guide, setup
+    /// ---
+    /// ```
+    ///
+    /// Documents without a title are titled by their first heading, or else their file name.
+    /// The document's encoding is detected as it is when the document is opened, and an error
+    /// is returned if it can not be.
+    pub fn read(folder: &Path, source: &Path) -> io::Result<Page> {
+        let mut bytes = Vec::new();
+        File::open(folder.join(source))?.read_to_end(&mut bytes)?;
+        let contents = match detect(&bytes) {
+            Ok(decoded) => decoded.text,
+            Err(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the encoding could not be determined",
+                ))
+            }
+        };
+
+        let (front_matter, markdown) = split_front_matter(&contents);
+        let mut title = None;
+        let mut tags = Vec::new();
+        for line in front_matter.lines() {
+            let (key, value) = match line.find(':') {
+                Some(colon) => (line[..colon].trim(), line[colon + 1..].trim()),
+                None => continue,
+            };
+
+            match key {
+                "title" => title = Some(value.trim_matches('"').to_owned()),
+                "tags" => {
+                    tags = value
+                        .trim_matches(|c| c == '[' || c == ']')
+                        .split(',')
+                        .map(|tag| tag.trim().trim_matches('"').to_owned())
+                        .filter(|tag| !tag.is_empty())
+                        .collect()
+                }
+                _ => (),
+            }
+        }
+
+        let title = title
+            .or_else(|| headings(markdown).into_iter().next().map(|heading| heading.title))
+            .unwrap_or_else(|| note_name(source));
+
+        Ok(Page {
+            source: source.to_path_buf(),
+            output: source.with_extension("html"),
+            title,
+            tags,
+            markdown: markdown.to_owned(),
+        })
+    }
+}
+
+/// Separates the front matter, between lines of `---` at the beginning of the document, from
+/// the markdown which follows it.
+fn split_front_matter(contents: &str) -> (&str, &str) {
+    if !contents.starts_with("---\n") && !contents.starts_with("---\r\n") {
+        return ("", contents);
+    }
+
+    let start = contents.find('\n').map_or(contents.len(), |end| end + 1);
+    let mut offset = start;
+    for line in contents[start..].split('\n') {
+        if line.trim_right() == "---" {
+            let end = (offset + line.len() + 1).min(contents.len());
+            return (&contents[start..offset], &contents[end..]);
+        }
+        offset += line.len() + 1;
+    }
+
+    ("", contents)
+}
+
+/// Renders every markdown document beneath the folder into the output directory, along with
+/// an index page, a page for each tag, and copies of every other file that the documents may
+/// refer to, such as images.
+pub fn build(folder: &Path, output: &Path) -> io::Result<Report> {
+    fs::create_dir_all(output)?;
+    let (folder, output) = (&folder.canonicalize()?, &output.canonicalize()?);
+    if folder.starts_with(output) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the site must be built into a directory apart from its documents",
+        ));
+    }
+
+    // The output directory is skipped if it is within the folder, so that it is not copied
+    // into itself when the site is built again.
+    let files: Vec<PathBuf> = markdown_files(folder)
+        .into_iter()
+        .filter(|file| !is_within(folder, file, output))
+        .collect();
+
+    // Documents which can not be read are skipped, as are documents whose page would replace
+    // the page of another, such as `a.markdown` and `a.md`.
+    let mut pages: Vec<Page> = Vec::with_capacity(files.len());
+    let mut skipped = Vec::new();
+    for file in &files {
+        let page = match Page::read(folder, file) {
+            Ok(page) => page,
+            Err(why) => {
+                skipped.push((file.clone(), why.to_string()));
+                continue;
+            }
+        };
+
+        match pages.iter().find(|other| other.output == page.output) {
+            Some(other) => {
+                let why = format!("its page would replace the page of {}", other.source.display());
+                skipped.push((file.clone(), why));
+            }
+            None => pages.push(page),
+        }
+    }
+
+    // A document named `index` becomes the introduction of the index page.
+    let introduction = pages.iter().position(|page| page.output == Path::new("index.html"));
+    let introduction = introduction.map(|index| pages.remove(index));
+
+    let mut tags: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, page) in pages.iter().enumerate() {
+        for tag in &page.tags {
+            tags.entry(tag.clone()).or_insert_with(Vec::new).push(index);
+        }
+    }
+    let tag_paths = tag_paths(tags.keys(), &pages);
+
+    write(&output.join("style.css"), STYLESHEET)?;
+
+    let notes = Notes { folder, files: &files };
+    for page in &pages {
+        let body = render_page(page, notes);
+        let html = layout(&page.output, &page.title, &pages, &tag_paths, &body);
+        write(&output.join(&page.output), &html)?;
+    }
+
+    let index = Path::new("index.html");
+    let mut body = match introduction {
+        Some(ref introduction) => render_page(introduction, notes),
+        None => format!("<h1>{}</h1>", escape(&folder_name(folder))),
+    };
+    body.push_str("<h2>Pages</h2>");
+    body.push_str(&page_list(index, pages.iter()));
+    if !tags.is_empty() {
+        body.push_str("<h2>Tags</h2><ul>");
+        for tag in tags.keys() {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>",
+                href(index, &tag_paths[tag]),
+                escape(tag)
+            ));
+        }
+        body.push_str("</ul>");
+    }
+    let html = layout(index, &folder_name(folder), &pages, &tag_paths, &body);
+    write(&output.join(index), &html)?;
+
+    for (tag, indices) in &tags {
+        let path = &tag_paths[tag];
+        let title = format!("Tagged “{}”", tag);
+        let mut body = format!("<h1>{}</h1>", escape(&title));
+        body.push_str(&page_list(path, indices.iter().map(|&index| &pages[index])));
+        write(&output.join(path), &layout(path, &title, &pages, &tag_paths, &body))?;
+    }
+
+    let mut assets = Vec::new();
+    collect_assets(folder, Path::new(""), output, &mut assets);
+    for asset in &assets {
+        let destination = output.join(asset);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(folder.join(asset), destination)?;
+    }
+
+    let pages = pages.len() + 1;
+    Ok(Report { pages, tags: tags.len(), assets: assets.len(), skipped })
+}
+
+/// Renders the page's markdown, linking its wiki links and its links to other documents to
+/// the pages of those documents.
+fn render_page(page: &Page, notes: Notes) -> String {
+    let note_href = |note: &Path| href(&page.output, &note.with_extension("html"));
+    mark_to_html(&page.markdown, Some(notes), &note_href, &markdown_link)
+}
+
+/// Links to markdown documents are replaced with links to their pages.
+fn markdown_link(destination: &str) -> Option<String> {
+    if destination.contains("://") || destination.starts_with('#') {
+        return None;
+    }
+
+    let (path, fragment) = match destination.find('#') {
+        Some(hash) => (&destination[..hash], &destination[hash..]),
+        None => (destination, ""),
+    };
+
+    if !is_markdown(Path::new(path)) {
+        return None;
+    }
+
+    let stem = &path[..path.rfind('.').unwrap_or(path.len())];
+    Some([stem, ".html", fragment].concat())
+}
+
+/// Wraps the body of a page within the site's layout, which links every page from a sidebar.
+fn layout(
+    path: &Path,
+    title: &str,
+    pages: &[Page],
+    tag_paths: &BTreeMap<String, PathBuf>,
+    body: &str,
+) -> String {
+    let mut nav = format!("<p><a href=\"{}\">Index</a></p>", href(path, Path::new("index.html")));
+    nav.push_str("<ul>");
+    for page in pages {
+        let class = if page.output == path { " class=\"current\"" } else { "" };
+        nav.push_str(&format!(
+            "<li{}><a href=\"{}\">{}</a></li>",
+            class,
+            href(path, &page.output),
+            escape(&page.title)
+        ));
+    }
+    nav.push_str("</ul>");
+
+    let page = pages.iter().find(|page| page.output == path);
+    let tags = page.map_or(String::new(), |page| {
+        page.tags
+            .iter()
+            .map(|tag| {
+                format!("<a href=\"{}\">#{}</a>", href(path, &tag_paths[tag]), escape(tag))
+            })
+            .collect()
+    });
+
+    format!(
+        "{}",
+        html!(
+            : doctype::HTML;
+            html {
+                head {
+                    meta(charset="utf-8");
+                    meta(name="viewport", content="width=device-width, initial-scale=1");
+                    title : title;
+                    link(rel="stylesheet", href=href(path, Path::new("style.css")));
+                }
+                body {
+                    nav {
+                        : Raw(&nav);
+                    }
+                    main {
+                        : Raw(body);
+                        @ if !tags.is_empty() {
+                            p(class="tags") {
+                                : Raw(&tags);
+                            }
+                        }
+                    }
+                }
+            }
+        )
+    )
+}
+
+/// Lists links to the pages, from the page at the path.
+fn page_list<'a, I: Iterator<Item = &'a Page>>(path: &Path, pages: I) -> String {
+    let items: String = pages
+        .map(|page| {
+            format!("<li><a href=\"{}\">{}</a></li>", href(path, &page.output), escape(&page.title))
+        })
+        .collect();
+    ["<ul>", items.as_str(), "</ul>"].concat()
+}
+
+/// Chooses the path of the page which lists the pages with each tag. The page is named after
+/// the tag's slug, and numbered when another tag has the same slug, or when a document's page
+/// already has that path.
+fn tag_paths<'a, I>(tags: I, pages: &[Page]) -> BTreeMap<String, PathBuf>
+where
+    I: Iterator<Item = &'a String>,
+{
+    let mut taken: HashSet<PathBuf> = pages.iter().map(|page| page.output.clone()).collect();
+    let mut paths = BTreeMap::new();
+    for tag in tags {
+        let slug = slugify(tag);
+        let slug = if slug.is_empty() { "tag".to_owned() } else { slug };
+
+        let mut path = Path::new("tags").join(format!("{}.html", slug));
+        let mut number = 2;
+        while taken.contains(&path) {
+            path = Path::new("tags").join(format!("{}-{}.html", slug, number));
+            number += 1;
+        }
+
+        taken.insert(path.clone());
+        paths.insert(tag.clone(), path);
+    }
+    paths
+}
+
+/// Creates a link from the page at `from` to the page at `to`, which are both relative to the
+/// output directory.
+fn href(from: &Path, to: &Path) -> String {
+    let depth = from.parent().map_or(0, |parent| parent.components().count());
+    let mut link = "../".repeat(depth);
+    let components: Vec<String> = to.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(percent_encode(&name.to_string_lossy())),
+            _ => None,
+        })
+        .collect();
+    link.push_str(&components.join("/"));
+    link
+}
+
+/// Collects the paths of every file beneath the folder that is not a markdown document, such
+/// as images. Hidden files and the output directory are skipped.
+fn collect_assets(folder: &Path, relative: &Path, output: &Path, assets: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(folder.join(relative)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name();
+        let path = relative.join(&name);
+        if name.to_string_lossy().starts_with('.') || is_within(folder, &path, output) {
+            continue;
+        }
+
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => collect_assets(folder, &path, output, assets),
+            Ok(_) if !is_markdown(&path) => assets.push(path),
+            _ => (),
+        }
+    }
+}
+
+/// Whether the path, relative to the folder, is within the output directory.
+fn is_within(folder: &Path, relative: &Path, output: &Path) -> bool {
+    folder.join(relative).starts_with(output)
+}
+
+/// The name of the folder, which titles the index page.
+fn folder_name(folder: &Path) -> String {
+    folder.file_name().map_or("Index".to_owned(), |name| name.to_string_lossy().into_owned())
+}
+
+fn write(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(path)?.write_all(contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn page(output: &str) -> Page {
+        Page {
+            source:   Path::new(output).with_extension("md"),
+            output:   PathBuf::from(output),
+            title:    String::new(),
+            tags:     Vec::new(),
+            markdown: String::new(),
+        }
+    }
+
+    #[test]
+    fn links_are_relative_to_the_page() {
+        let index = Path::new("index.html");
+        assert_eq!(href(index, Path::new("guide/setup.html")), "guide/setup.html");
+        assert_eq!(href(Path::new("guide/setup.html"), index), "../index.html");
+        assert_eq!(href(Path::new("a/b/c.html"), Path::new("tags/x.html")), "../../tags/x.html");
+        assert_eq!(href(index, Path::new("my notes/a b.html")), "my%20notes/a%20b.html");
+    }
+
+    #[test]
+    fn front_matter_is_separated() {
+        assert_eq!(
+            split_front_matter("---\ntitle: A\n---\n# Body\n"),
+            ("title: A\n", "# Body\n")
+        );
+        assert_eq!(split_front_matter("---\r\ntags: x\r\n---\r\nBody"), ("tags: x\r\n", "Body"));
+        assert_eq!(split_front_matter("---\ntitle: A\n---"), ("title: A\n", ""));
+        // Without a closing line, or an opening one, there is no front matter.
+        assert_eq!(split_front_matter("---\ntitle: A\n"), ("", "---\ntitle: A\n"));
+        assert_eq!(split_front_matter("# ---\n---\n"), ("", "# ---\n---\n"));
+    }
+
+    #[test]
+    fn links_to_documents_link_to_pages() {
+        assert_eq!(markdown_link("guide/setup.md"), Some("guide/setup.html".to_owned()));
+        assert_eq!(markdown_link("setup.markdown#usage"), Some("setup.html#usage".to_owned()));
+        assert_eq!(markdown_link("image.png"), None);
+        assert_eq!(markdown_link("#usage"), None);
+        assert_eq!(markdown_link("https://example.com/readme.md"), None);
+    }
+
+    #[test]
+    fn tag_pages_do_not_collide() {
+        let tags: Vec<String> =
+            ["!!", "C++", "Rust", "c", "rust"].iter().map(|&tag| tag.into()).collect();
+        let pages = [page("tags/rust.html"), page("index.html")];
+        let paths = tag_paths(tags.iter(), &pages);
+
+        assert_eq!(paths["C++"], Path::new("tags/c.html"));
+        assert_eq!(paths["c"], Path::new("tags/c-2.html"));
+        assert_eq!(paths["Rust"], Path::new("tags/rust-2.html"));
+        assert_eq!(paths["rust"], Path::new("tags/rust-3.html"));
+        assert_eq!(paths["!!"], Path::new("tags/tag.html"));
+    }
+
+    #[test]
+    fn pages_are_read_with_their_front_matter() {
+        let folder = env::temp_dir().join(format!("site-test-{}", ::std::process::id()));
+        fs::create_dir_all(folder.join("guide")).unwrap();
+        write(
+            &folder.join("guide/setup.md"),
+            "---\ntitle: \"Setting Up\"\ntags: [guide, \"setup\", ]\n---\n# Heading\n",
+        ).unwrap();
+        write(&folder.join("untitled.md"), "# First Heading\n").unwrap();
+        write(&folder.join("empty.md"), "").unwrap();
+
+        let setup = Page::read(&folder, Path::new("guide/setup.md")).unwrap();
+        let untitled = Page::read(&folder, Path::new("untitled.md")).unwrap();
+        let empty = Page::read(&folder, Path::new("empty.md")).unwrap();
+        let _ = fs::remove_dir_all(&folder);
+
+        assert_eq!(setup.title, "Setting Up");
+        assert_eq!(setup.tags, vec!["guide", "setup"]);
+        assert_eq!(setup.output, Path::new("guide/setup.html"));
+        assert_eq!(setup.markdown, "# Heading\n");
+        assert_eq!(untitled.title, "First Heading");
+        assert_eq!(empty.title, "empty");
+    }
+
+    #[test]
+    fn documents_are_decoded_or_skipped() {
+        let folder = env::temp_dir().join(format!("site-decode-{}", ::std::process::id()));
+        let output = env::temp_dir().join(format!("site-output-{}", ::std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        write(&folder.join("a.md"), "# Markdown\n").unwrap();
+        write(&folder.join("a.markdown"), "# Also Markdown\n").unwrap();
+        // UTF-16 with a byte order mark is detected, while arbitrary bytes are not.
+        let utf16: Vec<u8> = [0xFF, 0xFE, b'#', 0, b' ', 0, b'W', 0, b'i', 0, b'd', 0, b'e', 0]
+            .iter()
+            .cloned()
+            .collect();
+        File::create(folder.join("wide.md")).unwrap().write_all(&utf16).unwrap();
+        let binary: Vec<u8> = (0..=255u8).cycle().take(1024).collect();
+        File::create(folder.join("binary.md")).unwrap().write_all(&binary).unwrap();
+
+        let wide = Page::read(&folder, Path::new("wide.md")).map(|page| page.title);
+        let report = build(&folder, &output);
+        let _ = fs::remove_dir_all(&folder);
+        let _ = fs::remove_dir_all(&output);
+
+        assert_eq!(wide.unwrap(), "Wide");
+        let report = report.unwrap();
+        let skipped: Vec<&Path> = report.skipped.iter().map(|s| s.0.as_path()).collect();
+        assert_eq!(skipped, vec![Path::new("a.md"), Path::new("binary.md")]);
+        assert!(report.skipped[0].1.contains("a.markdown"));
+        // The index, `a.markdown`, and `wide.md`.
+        assert_eq!(report.pages, 3);
+    }
+}
@@ -0,0 +1,783 @@
+use config::config_dir;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Directories which hunspell & myspell dictionaries are commonly installed to.
+const DICTIONARY_DIRS: &[&str] =
+    &["/usr/share/hunspell", "/usr/share/myspell", "/usr/share/myspell/dicts"];
+
+/// The maximum number of suggestions to offer for a misspelled word.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Characters to try when no `TRY` line was given by the affix file.
+const DEFAULT_TRY: &str = "esianrtolcdugmphbyfvkwzxjq'";
+
+/// How the flags of a dictionary's words and affixes are written.
+enum FlagType {
+    Single,
+    Long,
+    Numeric,
+}
+
+/// A single position within an affix rule's condition.
+enum CharClass {
+    Any,
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+/// A prefix or suffix rule from a hunspell `.aff` file, which derives words from a stem.
+struct Affix {
+    flag:      String,
+    cross:     bool,
+    strip:     String,
+    add:       String,
+    condition: Vec<CharClass>,
+}
+
+/// A hunspell dictionary, combined with the user's personal dictionary.
+pub struct Dictionary {
+    words:    HashMap<String, Vec<String>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    tries:    Vec<char>,
+    personal: HashSet<String>,
+}
+
+/// The lines of a document which have changed since its spelling was last checked. The lines
+/// are kept in place as lines are inserted or deleted before them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Changes {
+    /// The first and last lines which have changed, if any have.
+    pub lines:      Option<(usize, usize)>,
+    /// Whether every line must be checked, such as when a code fence was added or removed.
+    pub everything: bool,
+}
+
+/// Lists the language of every hunspell dictionary that is installed on the system.
+pub fn available() -> Vec<String> {
+    let mut languages = Vec::new();
+    for dir in DICTIONARY_DIRS {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            if path.extension().map_or(true, |ext| ext != "dic")
+                || !path.with_extension("aff").exists()
+            {
+                continue;
+            }
+
+            if let Some(language) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) {
+                if !languages.contains(&language) {
+                    languages.push(language);
+                }
+            }
+        }
+    }
+
+    languages.sort();
+    languages
+}
+
+/// The language that the user's locale prefers, such as `en_US`.
+pub fn preferred_language() -> Option<String> {
+    match env::var("LANG") {
+        Ok(ref lang) if !lang.is_empty() && lang != "C" && lang != "POSIX" => {
+            lang.split('.').next().map(|lang| lang.to_owned())
+        }
+        _ => None,
+    }
+}
+
+impl Dictionary {
+    /// Loads the installed dictionary for the given language, such as `en_US`.
+    pub fn for_language(language: &str) -> io::Result<Dictionary> {
+        DICTIONARY_DIRS
+            .iter()
+            .map(|dir| Path::new(dir).join([language, ".dic"].concat()))
+            .find(|path| path.exists())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("no dictionary for {}", language))
+            })
+            .and_then(|path| Dictionary::open(&path))
+    }
+
+    /// Loads a hunspell dictionary from the `.dic` file and its accompanying `.aff` file.
+    pub fn open(dic: &Path) -> io::Result<Dictionary> {
+        let aff = read_bytes(&dic.with_extension("aff"))?;
+        let latin1 = String::from_utf8_lossy(&aff).lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some("SET") && fields.next().map_or(false, |e| e.starts_with("ISO"))
+        });
+
+        let aff = decode(aff, latin1);
+        let dic = decode(read_bytes(dic)?, latin1);
+
+        let mut dictionary = Dictionary::parse(&aff, &dic);
+        dictionary.personal = read_personal();
+        Ok(dictionary)
+    }
+
+    /// Parses the contents of a hunspell `.aff` file and its `.dic` file.
+    fn parse(aff: &str, dic: &str) -> Dictionary {
+        let mut flag_type = FlagType::Single;
+        let mut tries = DEFAULT_TRY.chars().collect();
+        let mut crosses = HashMap::new();
+        let (mut prefixes, mut suffixes) = (Vec::new(), Vec::new());
+
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.get(0).cloned() {
+                Some("FLAG") => {
+                    flag_type = match fields.get(1).cloned() {
+                        Some("long") => FlagType::Long,
+                        Some("num") => FlagType::Numeric,
+                        _ => FlagType::Single,
+                    }
+                }
+                Some("TRY") => if let Some(chars) = fields.get(1) {
+                    tries = chars.chars().collect();
+                },
+                Some(kind @ "PFX") | Some(kind @ "SFX") if fields.len() >= 4 => {
+                    // The header of each affix class states whether it may be combined with
+                    // affixes of the opposite kind, followed by the number of rules.
+                    if fields.len() == 4 && fields[3].parse::<usize>().is_ok()
+                        && (fields[2] == "Y" || fields[2] == "N")
+                    {
+                        crosses.insert(fields[1].to_owned(), fields[2] == "Y");
+                        continue;
+                    }
+
+                    let affix = Affix {
+                        flag:      fields[1].to_owned(),
+                        cross:     crosses.get(fields[1]).cloned().unwrap_or(false),
+                        strip:     match fields[2] {
+                            "0" => String::new(),
+                            strip => strip.to_owned(),
+                        },
+                        add:       match fields[3].split('/').next() {
+                            Some("0") | None => String::new(),
+                            Some(add) => add.to_owned(),
+                        },
+                        condition: fields.get(4).map_or(Vec::new(), |c| parse_condition(c)),
+                    };
+
+                    if kind == "PFX" {
+                        prefixes.push(affix);
+                    } else {
+                        suffixes.push(affix);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // The first line of a dictionary is the number of words that it contains.
+        let mut words = HashMap::new();
+        for line in dic.lines().skip(1) {
+            let entry = match line.split_whitespace().next() {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let mut parts = entry.splitn(2, '/');
+            let word = parts.next().unwrap_or("").to_owned();
+            let flags = parts.next().map_or(Vec::new(), |flags| parse_flags(flags, &flag_type));
+            words.entry(word).or_insert_with(Vec::new).extend(flags);
+        }
+
+        Dictionary { words, prefixes, suffixes, tries, personal: HashSet::new() }
+    }
+
+    /// Whether the word is spelled correctly.
+    pub fn check(&self, word: &str) -> bool {
+        if self.check_exact(word) {
+            return true;
+        }
+
+        // Words at the start of a sentence, or written in capitals, may be in lowercase.
+        let lowercase = word.to_lowercase();
+        lowercase != word && self.check_exact(&lowercase)
+    }
+
+    /// Suggests correctly-spelled words which are one edit away from the misspelled word.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let capitalized = chars.get(0).map_or(false, |c| c.is_uppercase());
+        let mut candidates: Vec<String> = Vec::new();
+
+        // Swapping adjacent characters.
+        for i in 1..chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(i - 1, i);
+            candidates.push(swapped.into_iter().collect());
+        }
+
+        // Replacing a character.
+        for i in 0..chars.len() {
+            for &c in &self.tries {
+                let mut replaced = chars.clone();
+                replaced[i] = c;
+                candidates.push(replaced.into_iter().collect());
+            }
+        }
+
+        // Removing a character.
+        for i in 0..chars.len() {
+            let mut removed = chars.clone();
+            removed.remove(i);
+            candidates.push(removed.into_iter().collect());
+        }
+
+        // Inserting a character.
+        for i in 0..chars.len() + 1 {
+            for &c in &self.tries {
+                let mut inserted = chars.clone();
+                inserted.insert(i, c);
+                candidates.push(inserted.into_iter().collect());
+            }
+        }
+
+        // Separating the word into two words.
+        for i in 1..chars.len() {
+            let (first, second): (String, String) =
+                (chars[..i].iter().collect(), chars[i..].iter().collect());
+            if self.check(&first) && self.check(&second) {
+                candidates.push([first.as_str(), " ", second.as_str()].concat());
+            }
+        }
+
+        let mut suggestions: Vec<String> = Vec::new();
+        for candidate in candidates {
+            let valid = candidate.contains(' ') || self.check(&candidate);
+            if valid && candidate != word && !suggestions.contains(&candidate) {
+                suggestions.push(candidate);
+                if suggestions.len() == MAX_SUGGESTIONS {
+                    break;
+                }
+            }
+        }
+
+        if capitalized {
+            suggestions.iter_mut().for_each(|suggestion| *suggestion = capitalize(suggestion));
+        }
+
+        suggestions
+    }
+
+    /// Adds the word to the user's personal dictionary.
+    pub fn add_word(&mut self, word: &str) -> io::Result<()> {
+        self.personal.insert(word.to_owned());
+        let path = match personal_path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{}", word)
+    }
+
+    /// Obtains the byte ranges of every misspelled word in the markdown text. Code, URLs, and
+    /// HTML tags are not checked.
+    pub fn misspelled(&self, markdown: &str) -> Vec<(usize, usize)> {
+        self.misspelled_within(markdown, 0, markdown.len())
+    }
+
+    /// Obtains the byte ranges of the misspelled words between the byte offsets, which must be
+    /// at the start and end of lines. The whole text is still read, as code blocks may begin
+    /// before the start.
+    pub fn misspelled_within(
+        &self,
+        markdown: &str,
+        start: usize,
+        end: usize,
+    ) -> Vec<(usize, usize)> {
+        let prose = prose(markdown);
+        words(&prose[start..end])
+            .into_iter()
+            .map(|(first, last)| (start + first, start + last))
+            .filter(|&(first, last)| !self.check(&prose[first..last]))
+            .collect()
+    }
+
+    fn check_exact(&self, word: &str) -> bool {
+        self.personal.contains(word) || self.words.contains_key(word)
+            || self.check_suffixed(word, None) || self.check_prefixed(word)
+    }
+
+    fn has_flag(&self, stem: &str, flag: &str) -> bool {
+        self.words.get(stem).map_or(false, |flags| flags.iter().any(|f| f == flag))
+    }
+
+    /// Whether the word is a stem with a suffix applied. If a prefix was already removed, the
+    /// stem must permit both of them.
+    fn check_suffixed(&self, word: &str, prefix: Option<&Affix>) -> bool {
+        self.suffixes.iter().any(|suffix| {
+            if (prefix.is_some() && !suffix.cross) || !word.ends_with(suffix.add.as_str())
+                || word.len() == suffix.add.len()
+            {
+                return false;
+            }
+
+            let stem = [&word[..word.len() - suffix.add.len()], suffix.strip.as_str()].concat();
+            suffix.matches_end(&stem) && self.has_flag(&stem, &suffix.flag)
+                && prefix.map_or(true, |prefix| self.has_flag(&stem, &prefix.flag))
+        })
+    }
+
+    /// Whether the word is a stem with a prefix, and optionally a suffix, applied.
+    fn check_prefixed(&self, word: &str) -> bool {
+        self.prefixes.iter().any(|prefix| {
+            if !word.starts_with(prefix.add.as_str()) || word.len() == prefix.add.len() {
+                return false;
+            }
+
+            let stem = [prefix.strip.as_str(), &word[prefix.add.len()..]].concat();
+            prefix.matches_start(&stem)
+                && (self.has_flag(&stem, &prefix.flag)
+                    || (prefix.cross && self.check_suffixed(&stem, Some(prefix))))
+        })
+    }
+}
+
+impl Changes {
+    /// Whether nothing has changed.
+    pub fn is_empty(&self) -> bool { self.lines.is_none() && !self.everything }
+
+    /// Records that text which contains the given number of line breaks was inserted on the
+    /// line.
+    pub fn insert(&mut self, line: usize, breaks: usize) {
+        let shift = |other: usize| if other > line { other + breaks } else { other };
+        self.lines = Some(match self.lines {
+            Some((first, last)) => (first.min(line), shift(last).max(line + breaks)),
+            None => (line, line + breaks),
+        });
+    }
+
+    /// Records that the text from the first line to the last line was deleted, which joins the
+    /// two lines together.
+    pub fn delete(&mut self, first: usize, last: usize) {
+        let shift = |other: usize| if other > last {
+            other - (last - first)
+        } else {
+            other.min(first)
+        };
+        self.lines = Some(match self.lines {
+            Some((start, end)) => (shift(start).min(first), shift(end).max(first)),
+            None => (first, first),
+        });
+    }
+}
+
+impl Affix {
+    /// Whether the end of the stem satisfies the condition of this suffix.
+    fn matches_end(&self, stem: &str) -> bool {
+        let chars: Vec<char> = stem.chars().collect();
+        chars.len() >= self.condition.len()
+            && chars[chars.len() - self.condition.len()..]
+                .iter()
+                .zip(&self.condition)
+                .all(|(&c, class)| class.matches(c))
+    }
+
+    /// Whether the start of the stem satisfies the condition of this prefix.
+    fn matches_start(&self, stem: &str) -> bool {
+        let chars: Vec<char> = stem.chars().collect();
+        chars.len() >= self.condition.len()
+            && chars.iter().zip(&self.condition).all(|(&c, class)| class.matches(c))
+    }
+}
+
+impl CharClass {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            CharClass::Any => true,
+            CharClass::OneOf(ref chars) => chars.contains(&c),
+            CharClass::NoneOf(ref chars) => !chars.contains(&c),
+        }
+    }
+}
+
+/// Parses an affix condition, such as `[^aeiou]y`, into a list of character classes.
+fn parse_condition(condition: &str) -> Vec<CharClass> {
+    let mut classes = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        classes.push(match c {
+            '.' => CharClass::Any,
+            '[' => {
+                let mut set: Vec<char> = chars.by_ref().take_while(|&c| c != ']').collect();
+                if set.first() == Some(&'^') {
+                    set.remove(0);
+                    CharClass::NoneOf(set)
+                } else {
+                    CharClass::OneOf(set)
+                }
+            }
+            c => CharClass::OneOf(vec![c]),
+        });
+    }
+
+    // A condition of `.` matches every stem.
+    if classes.len() == 1 {
+        if let CharClass::Any = classes[0] {
+            classes.clear();
+        }
+    }
+
+    classes
+}
+
+fn parse_flags(flags: &str, flag_type: &FlagType) -> Vec<String> {
+    match *flag_type {
+        FlagType::Single => flags.chars().map(|c| c.to_string()).collect(),
+        FlagType::Long => {
+            let chars: Vec<char> = flags.chars().collect();
+            chars.chunks(2).map(|flag| flag.iter().collect()).collect()
+        }
+        FlagType::Numeric => flags.split(',').map(|flag| flag.trim().to_owned()).collect(),
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn read_bytes(path: &Path) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Dictionaries are either UTF-8 or ISO-8859-1 encoded.
+fn decode(data: Vec<u8>, latin1: bool) -> String {
+    if latin1 {
+        data.into_iter().map(|byte| byte as char).collect()
+    } else {
+        String::from_utf8(data)
+            .unwrap_or_else(|why| String::from_utf8_lossy(&why.into_bytes()).into_owned())
+    }
+}
+
+/// The file which contains the words that the user has added to their dictionary.
+fn personal_path() -> Option<PathBuf> { config_dir().map(|dir| dir.join("dictionary.txt")) }
+
+fn read_personal() -> HashSet<String> {
+    personal_path()
+        .and_then(|path| read_bytes(&path).ok())
+        .map(|data| String::from_utf8_lossy(&data).lines().map(|w| w.trim().to_owned()).collect())
+        .unwrap_or_default()
+}
+
+/// Obtains the byte ranges of each word within the text. Words that contain digits or
+/// underscores, and acronyms written entirely in capitals, are skipped.
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '\'' || c == '’';
+
+    let mut words = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !is_word_char(c) {
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some(&(index, c)) = chars.peek() {
+            if !is_word_char(c) {
+                break;
+            }
+            end = index + c.len_utf8();
+            chars.next();
+        }
+
+        // Apostrophes that surround a word are quotation marks.
+        let word = text[start..end].trim_matches(|c| c == '\'' || c == '’');
+        if word.is_empty() || word.chars().any(|c| c.is_numeric() || c == '_') {
+            continue;
+        }
+
+        if word.chars().count() > 1 && word.chars().all(|c| !c.is_lowercase()) {
+            continue;
+        }
+
+        let offset = start + text[start..end].find(word).unwrap_or(0);
+        words.push((offset, offset + word.len()));
+    }
+
+    words
+}
+
+/// Copies the markdown, replacing code, URLs, and HTML tags with spaces, so that only prose
+/// remains at the same byte offsets as the original text.
+fn prose(markdown: &str) -> String {
+    let mut bytes = markdown.as_bytes().to_vec();
+    let mut fence: Option<&str> = None;
+    let mut previous_blank = true;
+    let mut in_indented_code = false;
+    let mut offset = 0;
+
+    for line in markdown.split('\n') {
+        let (start, end) = (offset, offset + line.len());
+        offset = end + 1;
+        let trimmed = line.trim_left();
+
+        if let Some(marker) = fence {
+            blank(&mut bytes, start, end);
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            blank(&mut bytes, start, end);
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            previous_blank = true;
+            continue;
+        }
+
+        let indented = line.starts_with("    ") || line.starts_with('\t');
+        if indented && (previous_blank || in_indented_code) {
+            in_indented_code = true;
+            previous_blank = false;
+            blank(&mut bytes, start, end);
+            continue;
+        }
+
+        in_indented_code = false;
+        previous_blank = false;
+        blank_inline(&mut bytes, start, line);
+    }
+
+    String::from_utf8(bytes).unwrap_or_else(|_| markdown.to_owned())
+}
+
+/// Blanks code spans, link destinations, URLs, and HTML tags within a line of text.
+fn blank_inline(bytes: &mut [u8], offset: usize, line: &str) {
+    let text = line.as_bytes();
+
+    // The destination of a link reference definition: `[label]: url`
+    if line.trim_left().starts_with('[') {
+        if let Some(colon) = line.find("]:") {
+            blank(bytes, offset + colon + 2, offset + text.len());
+        }
+    }
+
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &line[i..];
+        match text[i] {
+            b'`' => {
+                let run = text[i..].iter().take_while(|&&b| b == b'`').count();
+                let marker = &line[i..i + run];
+                match find_closing_run(&line[i + run..], marker) {
+                    Some(close) => {
+                        let end = i + run + close + run;
+                        blank(bytes, offset + i, offset + end);
+                        i = end;
+                    }
+                    None => i += run,
+                }
+            }
+            b']' if rest.starts_with("](") => {
+                let end = rest.find(')').map_or(text.len(), |close| i + close + 1);
+                blank(bytes, offset + i + 2, offset + end);
+                i = end;
+            }
+            b'<' if text.get(i + 1).map_or(false, |&b| b.is_ascii_alphabetic() || b == b'/') => {
+                let end = rest.find('>').map_or(text.len(), |close| i + close + 1);
+                blank(bytes, offset + i, offset + end);
+                i = end;
+            }
+            _ if (i == 0 || text[i - 1].is_ascii_whitespace() || text[i - 1] == b'(')
+                && ["http://", "https://", "ftp://", "www.", "mailto:"]
+                    .iter()
+                    .any(|scheme| rest.starts_with(scheme)) =>
+            {
+                let end = rest.find(char::is_whitespace).map_or(text.len(), |space| i + space);
+                blank(bytes, offset + i, offset + end);
+                i = end;
+            }
+            // Step over the whole character, so that `i` stays on a character boundary.
+            _ => i += line[i..].chars().next().map_or(1, |c| c.len_utf8()),
+        }
+    }
+}
+
+/// Finds a run of backticks that is exactly as long as the marker which opened a code span.
+fn find_closing_run(text: &str, marker: &str) -> Option<usize> {
+    let mut search = 0;
+    while let Some(found) = text[search..].find(marker) {
+        let start = search + found;
+        let end = start + marker.len();
+        if !text[end..].starts_with('`') {
+            return Some(start);
+        }
+        search = end + text[end..].bytes().take_while(|&b| b == b'`').count();
+    }
+    None
+}
+
+fn blank(bytes: &mut [u8], start: usize, end: usize) {
+    for byte in &mut bytes[start..end] {
+        *byte = b' ';
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwzxjq
+
+PFX U Y 1
+PFX U 0 un .
+
+SFX S Y 2
+SFX S y ies [^aeiou]y
+SFX S 0 s [aeiou]y
+
+SFX N Y 1
+SFX N 0 ness .
+
+SFX D N 1
+SFX D 0 ed [^y]
+";
+
+    const DIC: &str = "5
+happy/UN
+try/S
+play/SD
+kind/UN
+jump/D
+";
+
+    fn dictionary() -> Dictionary { Dictionary::parse(AFF, DIC) }
+
+    #[test]
+    fn stems_are_found() {
+        let dictionary = dictionary();
+        assert!(dictionary.check("happy"));
+        assert!(dictionary.check("jump"));
+        assert!(!dictionary.check("hapy"));
+    }
+
+    #[test]
+    fn suffixes_strip_and_add() {
+        let dictionary = dictionary();
+        assert!(dictionary.check("tries"));
+        assert!(dictionary.check("plays"));
+        assert!(dictionary.check("jumped"));
+        // The conditions of the rules decide which of them apply.
+        assert!(!dictionary.check("trys"));
+        assert!(!dictionary.check("plaies"));
+        assert!(!dictionary.check("played"));
+    }
+
+    #[test]
+    fn affixes_require_their_flags() {
+        let dictionary = dictionary();
+        assert!(dictionary.check("unhappy"));
+        assert!(!dictionary.check("unjump"));
+        assert!(!dictionary.check("kindly"));
+    }
+
+    #[test]
+    fn prefixes_and_suffixes_combine_when_both_cross() {
+        let dictionary = dictionary();
+        assert!(dictionary.check("unkindness"));
+        assert!(!dictionary.check("unhappiness"));
+        assert!(dictionary.check("unhappyness"));
+    }
+
+    #[test]
+    fn capitalized_words_are_found() {
+        let dictionary = dictionary();
+        assert!(dictionary.check("Happy"));
+        assert!(dictionary.check("UNHAPPY"));
+    }
+
+    #[test]
+    fn suggestions_are_one_edit_away() {
+        let dictionary = dictionary();
+        assert!(dictionary.suggest("hapy").contains(&"happy".to_owned()));
+        assert!(dictionary.suggest("tires").contains(&"tries".to_owned()));
+        assert_eq!(dictionary.suggest("Jmup").first().map(|s| s.as_str()), Some("Jump"));
+    }
+
+    #[test]
+    fn flags_are_parsed_by_type() {
+        assert_eq!(parse_flags("AB", &FlagType::Single), vec!["A", "B"]);
+        assert_eq!(parse_flags("AaBb", &FlagType::Long), vec!["Aa", "Bb"]);
+        assert_eq!(parse_flags("1,23", &FlagType::Numeric), vec!["1", "23"]);
+    }
+
+    #[test]
+    fn code_links_and_acronyms_are_skipped() {
+        let dictionary = dictionary();
+        let markdown = "happy `hapy` [jumpp](http://hapy.com) NASA\n\n```\nplayy\n```\nkindd";
+        let misspelled: Vec<&str> =
+            dictionary.misspelled(markdown).into_iter().map(|(s, e)| &markdown[s..e]).collect();
+        assert_eq!(misspelled, vec!["jumpp", "kindd"]);
+    }
+
+    #[test]
+    fn lines_may_be_checked_alone() {
+        let dictionary = dictionary();
+        let markdown = "hapy\n```\njumpp\n```\nkindd\n";
+        let start = markdown.find("jumpp").unwrap();
+        let end = markdown.len();
+        let misspelled: Vec<&str> = dictionary
+            .misspelled_within(markdown, start, end)
+            .into_iter()
+            .map(|(s, e)| &markdown[s..e])
+            .collect();
+        assert_eq!(misspelled, vec!["kindd"]);
+    }
+
+    #[test]
+    fn changed_lines_follow_insertions() {
+        let mut changes = Changes::default();
+        assert!(changes.is_empty());
+        changes.insert(4, 0);
+        assert_eq!(changes.lines, Some((4, 4)));
+        // Lines inserted above the change move it down.
+        changes.insert(1, 2);
+        assert_eq!(changes.lines, Some((1, 6)));
+        changes.insert(9, 1);
+        assert_eq!(changes.lines, Some((1, 10)));
+    }
+
+    #[test]
+    fn changed_lines_follow_deletions() {
+        let mut changes = Changes::default();
+        changes.insert(10, 0);
+        // Lines deleted above the change move it up.
+        changes.delete(2, 5);
+        assert_eq!(changes.lines, Some((2, 7)));
+        // Deleting the changed lines leaves the line that they were joined into.
+        let mut changes = Changes::default();
+        changes.insert(4, 2);
+        changes.delete(3, 8);
+        assert_eq!(changes.lines, Some((3, 3)));
+    }
+}
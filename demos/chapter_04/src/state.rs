@@ -1,21 +1,45 @@
+use encoding::TextFormat;
+use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use tiny_keccak::keccak512;
 
 pub struct ActiveMetadata {
-    path: PathBuf,
-    sum:  [u8; 64],
+    path:   PathBuf,
+    /// The checksum of the file's contents when it was last read or written, which reveals
+    /// whether another program has changed the file since.
+    sum:    [u8; 64],
+    /// The encoding and line endings that the file is written with.
+    format: TextFormat,
 }
 
 impl ActiveMetadata {
-    pub fn new(path: PathBuf, data: &[u8]) -> ActiveMetadata {
-        ActiveMetadata { path, sum: keccak512(data) }
+    /// Describes the file at the path, whose contents are `data`.
+    pub fn new(path: PathBuf, data: &[u8], format: TextFormat) -> ActiveMetadata {
+        ActiveMetadata { path, sum: keccak512(data), format }
     }
 
     pub fn get_path<'a>(&'a self) -> &'a Path { &self.path }
 
     pub fn get_dir(&self) -> Option<PathBuf> { self.path.parent().map(|p| p.to_path_buf()) }
 
-    pub fn is_same_as(&self, data: &[u8]) -> bool { &keccak512(data)[..] == &self.sum[..] }
+    pub fn get_sum(&self) -> [u8; 64] { self.sum }
 
+    /// Records that `data` has been written to the file.
     pub fn set_sum(&mut self, data: &[u8]) { self.sum = keccak512(data); }
+
+    pub fn get_format(&self) -> TextFormat { self.format }
+
+    /// Changes the format that the file will be written with when it is next saved.
+    pub fn set_format(&mut self, format: TextFormat) { self.format = format; }
+}
+
+/// Whether the contents of the file at the path have the checksum. Files which can not be read
+/// do not.
+pub fn file_has_sum(path: &Path, sum: &[u8; 64]) -> bool {
+    let mut data = Vec::new();
+    match File::open(path).and_then(|mut file| file.read_to_end(&mut data)) {
+        Ok(_) => &keccak512(&data)[..] == &sum[..],
+        Err(_) => false,
+    }
 }
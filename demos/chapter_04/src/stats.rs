@@ -0,0 +1,123 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+/// The average number of words that a person reads per minute.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Statistics which describe the length of a markdown document, or a portion of one.
+#[derive(Default)]
+pub struct Statistics {
+    pub words:      usize,
+    pub characters: usize,
+    pub lines:      usize,
+    pub headings:   usize,
+}
+
+impl Statistics {
+    /// Computes statistics for the markdown text. Words and characters are counted from the
+    /// text that will be displayed, rather than the markup. If `include_code` is false, the
+    /// contents and lines of code blocks will not be counted.
+    pub fn new(markdown: &str, include_code: bool) -> Statistics {
+        let mut stats = Statistics::default();
+        let mut in_code_block = false;
+        let mut code_lines: usize = 0;
+        // Whether the previous text ended within a word, which text that immediately follows
+        // continues, such as the `bar` of `foo**bar**`.
+        let mut mid_word = false;
+
+        for event in Parser::new(markdown) {
+            match event {
+                Event::Start(Tag::Header(_)) => stats.headings += 1,
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                Event::Text(ref text) => if include_code || !in_code_block {
+                    let words = text.split_whitespace().count();
+                    let continues = mid_word && !text.starts_with(char::is_whitespace);
+                    stats.words += if continues { words.saturating_sub(1) } else { words };
+                    stats.characters += text.chars().filter(|c| !c.is_control()).count();
+                    if !text.is_empty() {
+                        mid_word = !text.ends_with(char::is_whitespace);
+                    }
+                    continue;
+                } else {
+                    code_lines += text.lines().count();
+                },
+                _ => (),
+            }
+
+            // Inline markup may divide a word, while anything else separates the words on
+            // either side of it.
+            mid_word = mid_word && match event {
+                Event::Start(ref tag) | Event::End(ref tag) => is_inline(tag),
+                Event::InlineHtml(_) => true,
+                _ => false,
+            };
+        }
+
+        // Lines are counted from the source text, minus any excluded code block lines.
+        let lines = markdown.lines().count();
+        stats.lines = lines - code_lines.min(lines);
+        stats
+    }
+
+    /// The estimated number of minutes it would take to read the text.
+    pub fn reading_time(&self) -> usize {
+        (self.words + WORDS_PER_MINUTE - 1) / WORDS_PER_MINUTE
+    }
+}
+
+/// Whether the tag marks up text within a paragraph, rather than containing blocks.
+fn is_inline(tag: &Tag) -> bool {
+    match *tag {
+        Tag::Emphasis | Tag::Strong | Tag::Code | Tag::Link(..) | Tag::Image(..) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "# Title\n\nOne two three.\n\n```\nlet x = 1;\nlet y = 2;\n```\n";
+
+    #[test]
+    fn words_are_counted_from_rendered_text() {
+        let stats = Statistics::new("**Bold** and [a link](http://example.com)", true);
+        assert_eq!(stats.words, 4);
+        assert_eq!(stats.characters, "Bold and a link".len());
+        assert_eq!(stats.lines, 1);
+        assert_eq!(stats.headings, 0);
+    }
+
+    #[test]
+    fn words_divided_by_markup_are_counted_once() {
+        assert_eq!(Statistics::new("foo**bar** baz", true).words, 2);
+        assert_eq!(Statistics::new("a`code`b", true).words, 1);
+        assert_eq!(Statistics::new("un*believ*able [link](x)s", true).words, 2);
+        assert_eq!(Statistics::new("foo **bar** baz", true).words, 3);
+        assert_eq!(Statistics::new("foo\nbar", true).words, 2);
+        assert_eq!(Statistics::new("# foo\n\nbar", true).words, 2);
+        assert_eq!(Statistics::new("- foo\n- bar", true).words, 2);
+    }
+
+    #[test]
+    fn code_blocks_may_be_included() {
+        let stats = Statistics::new(DOCUMENT, true);
+        assert_eq!((stats.words, stats.lines, stats.headings), (12, 8, 1));
+    }
+
+    #[test]
+    fn code_blocks_may_be_excluded() {
+        let stats = Statistics::new(DOCUMENT, false);
+        assert_eq!((stats.words, stats.lines, stats.headings), (4, 6, 1));
+    }
+
+    #[test]
+    fn reading_time_rounds_up() {
+        let mut stats = Statistics::default();
+        assert_eq!(stats.reading_time(), 0);
+        stats.words = 1;
+        assert_eq!(stats.reading_time(), 1);
+        stats.words = WORDS_PER_MINUTE + 1;
+        assert_eq!(stats.reading_time(), 2);
+    }
+}
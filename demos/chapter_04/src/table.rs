@@ -0,0 +1,488 @@
+use pulldown_cmark::Alignment;
+
+/// Editing commands which change the structure of a table.
+#[derive(Clone, Copy)]
+pub enum Command {
+    InsertRowAbove,
+    InsertRowBelow,
+    DeleteRow,
+    InsertColumnLeft,
+    InsertColumnRight,
+    DeleteColumn,
+    Align(Alignment),
+}
+
+/// A table whose cells are collected before it is written, so that its columns can be aligned.
+pub struct Table {
+    pub alignments: Vec<Alignment>,
+    /// The text of each cell, by row. The first row is the table's header.
+    pub rows:       Vec<Vec<String>>,
+}
+
+/// The location of the cursor within a table, as a cell and a byte offset into the cell's text.
+#[derive(Clone, Copy)]
+pub struct Position {
+    pub row:    usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+/// Finds the first and last lines of the table which contains the given line, if the line is
+/// part of a table. A table is a run of lines containing pipes, whose second line is a
+/// delimiter row.
+pub fn bounds(lines: &[&str], line: usize) -> Option<(usize, usize)> {
+    let is_row = |line: &str| line.contains('|');
+    if !lines.get(line).map_or(false, |text| is_row(text)) {
+        return None;
+    }
+
+    let mut first = line;
+    while first > 0 && is_row(lines[first - 1]) {
+        first -= 1;
+    }
+
+    let mut last = line;
+    while last + 1 < lines.len() && is_row(lines[last + 1]) {
+        last += 1;
+    }
+
+    // Lines preceding the header row belong to a paragraph, rather than the table.
+    match (first + 1..last + 1).find(|&number| is_delimiter(lines[number])) {
+        Some(delimiter) if line + 1 >= delimiter => Some((delimiter - 1, last)),
+        _ => None,
+    }
+}
+
+/// Splits a row of a table into the text of each of its cells, along with the byte index at
+/// which each cell begins. The leading and trailing pipes are optional, and escaped pipes do
+/// not divide cells.
+pub fn split_row(line: &str) -> Vec<(usize, &str)> {
+    let bytes = line.as_bytes();
+    let mut start = line.len() - line.trim_left().len();
+    if bytes.get(start) == Some(&b'|') {
+        start += 1;
+    }
+
+    let mut cells = Vec::new();
+    let mut index = start;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'|' => {
+                cells.push((start, &line[start..index]));
+                start = index + 1;
+                index += 1;
+            }
+            _ => index += 1,
+        }
+    }
+
+    let rest = &line[start.min(line.len())..];
+    if cells.is_empty() || !rest.trim().is_empty() {
+        cells.push((start.min(line.len()), rest));
+    }
+
+    cells
+}
+
+/// Whether the line is the delimiter row of a table, such as `| --- | :-: |`.
+fn is_delimiter(line: &str) -> bool {
+    let cells = split_row(line);
+    !cells.is_empty() && cells.iter().all(|&(_, cell)| {
+        let cell = cell.trim();
+        let dashes = cell.trim_left_matches(':').trim_right_matches(':');
+        !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+            && cell.len() - dashes.len() <= 2
+    })
+}
+
+/// The alignment of a column, as described by its cell within the delimiter row.
+fn alignment(delimiter: &str) -> Alignment {
+    let delimiter = delimiter.trim();
+    match (delimiter.starts_with(':'), delimiter.ends_with(':')) {
+        (true, true) => Alignment::Center,
+        (true, false) => Alignment::Left,
+        (false, true) => Alignment::Right,
+        (false, false) => Alignment::None,
+    }
+}
+
+/// The row of the table which is written on the given line of the table. The delimiter row
+/// belongs to the header.
+fn row_of_line(line: usize) -> usize {
+    if line < 2 { 0 } else { line - 1 }
+}
+
+/// The line of the table on which the given row is written.
+fn line_of_row(row: usize) -> usize {
+    if row == 0 { 0 } else { row + 1 }
+}
+
+impl Table {
+    /// Parses the lines of a table, and locates the cursor within it from the cursor's line
+    /// and byte index. Whitespace before the cursor is kept within the cursor's cell, so that
+    /// a space which is being typed is not removed when the table is aligned.
+    pub fn parse(lines: &[&str], cursor_line: usize, cursor_index: usize) -> (Table, Position) {
+        let alignments = lines
+            .get(1)
+            .map_or(Vec::new(), |line| {
+                split_row(line).iter().map(|&(_, cell)| alignment(cell)).collect()
+            });
+
+        let mut position = Position { row: row_of_line(cursor_line), column: 0, offset: 0 };
+        let mut rows = Vec::new();
+        for (number, line) in lines.iter().enumerate() {
+            let on_cursor_line = number == cursor_line;
+            if number == 1 {
+                if on_cursor_line {
+                    let cells = split_row(line);
+                    let column = cells.iter().rposition(|&(start, _)| start <= cursor_index);
+                    position.column = column.unwrap_or(0);
+                }
+                continue;
+            }
+
+            let mut row = Vec::new();
+            for (column, (start, cell)) in split_row(line).into_iter().enumerate() {
+                let content_start = start + cell.len() - cell.trim_left().len();
+                let mut content_end = content_start + cell.trim().len();
+                if on_cursor_line && cursor_index >= start {
+                    position.column = column;
+                    position.offset = if cursor_index <= start + cell.len() {
+                        let cursor = cursor_index.max(content_start);
+                        content_end = content_end.max(cursor);
+                        cursor - content_start
+                    } else {
+                        content_end - content_start
+                    };
+                }
+                row.push(line[content_start..content_end].to_owned());
+            }
+            rows.push(row);
+        }
+
+        (Table { alignments, rows }, position)
+    }
+
+    /// The number of columns within the widest row of the table.
+    pub fn columns(&self) -> usize {
+        let longest_row = self.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        self.alignments.len().max(longest_row).max(1)
+    }
+
+    /// Gives every row, and the alignments, a cell for each column of the table.
+    fn normalize(&mut self) {
+        let columns = self.columns();
+        self.alignments.resize(columns, Alignment::None);
+        for row in &mut self.rows {
+            row.resize(columns, String::new());
+        }
+    }
+
+    /// The position of the cell after the given position. A new row is added to the table
+    /// when moving beyond its last cell.
+    pub fn next_cell(&mut self, position: Position) -> Position {
+        self.normalize();
+        let (row, column) = if position.column + 1 < self.columns() {
+            (position.row, position.column + 1)
+        } else {
+            (position.row + 1, 0)
+        };
+
+        if row >= self.rows.len() {
+            let columns = self.columns();
+            self.rows.push(vec![String::new(); columns]);
+        }
+
+        self.end_of_cell(row, column)
+    }
+
+    /// The position of the cell before the given position, if it is not the first cell.
+    pub fn previous_cell(&mut self, position: Position) -> Position {
+        self.normalize();
+        match (position.row, position.column) {
+            (0, 0) => self.end_of_cell(0, 0),
+            (row, 0) => {
+                let column = self.columns() - 1;
+                self.end_of_cell(row - 1, column)
+            }
+            (row, column) => self.end_of_cell(row, column.min(self.columns()) - 1),
+        }
+    }
+
+    /// The position at the end of the text of the cell.
+    fn end_of_cell(&self, row: usize, column: usize) -> Position {
+        let offset = self.rows.get(row).and_then(|row| row.get(column)).map_or(0, |c| c.len());
+        Position { row, column, offset }
+    }
+
+    /// Applies the command to the table at the given position, and returns where the cursor
+    /// should be placed afterwards.
+    pub fn apply(&mut self, command: Command, position: Position) -> Position {
+        self.normalize();
+        let columns = self.columns();
+        let row = position.row.min(self.rows.len().max(1) - 1);
+        let column = position.column.min(columns - 1);
+
+        match command {
+            Command::InsertRowAbove => {
+                self.rows.insert(row, vec![String::new(); columns]);
+                Position { row, column, offset: 0 }
+            }
+            Command::InsertRowBelow => {
+                self.rows.insert(row + 1, vec![String::new(); columns]);
+                Position { row: row + 1, column, offset: 0 }
+            }
+            Command::DeleteRow => {
+                // A table always has a header, so the last row is cleared rather than removed.
+                if self.rows.len() > 1 {
+                    self.rows.remove(row);
+                } else if let Some(cells) = self.rows.get_mut(0) {
+                    cells.iter_mut().for_each(|cell| cell.clear());
+                }
+                Position { row: row.min(self.rows.len() - 1), column, offset: 0 }
+            }
+            Command::InsertColumnLeft | Command::InsertColumnRight => {
+                let index = match command {
+                    Command::InsertColumnLeft => column,
+                    _ => column + 1,
+                };
+                self.alignments.insert(index, Alignment::None);
+                for cells in &mut self.rows {
+                    cells.insert(index, String::new());
+                }
+                Position { row, column: index, offset: 0 }
+            }
+            Command::DeleteColumn => {
+                if columns > 1 {
+                    self.alignments.remove(column);
+                    for cells in &mut self.rows {
+                        cells.remove(column);
+                    }
+                } else {
+                    self.rows.iter_mut().for_each(|cells| cells[0].clear());
+                }
+                Position { row, column: column.min(self.columns() - 1), offset: 0 }
+            }
+            Command::Align(alignment) => {
+                self.alignments[column] = alignment;
+                Position { row, column, offset: position.offset }
+            }
+        }
+    }
+
+    /// Writes each row of the table with padded cells, so that the columns line up.
+    pub fn lines(&self) -> Vec<String> {
+        let columns = self.columns();
+        let mut widths = vec![3; columns];
+        for row in &self.rows {
+            for (column, cell) in row.iter().enumerate() {
+                widths[column] = widths[column].max(cell.chars().count());
+            }
+        }
+
+        let alignment =
+            |column: usize| self.alignments.get(column).cloned().unwrap_or(Alignment::None);
+
+        let mut lines = Vec::new();
+        for (index, row) in self.rows.iter().enumerate() {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    let cell = row.get(column).map_or("", |cell| cell.as_str());
+                    pad(cell, widths[column], alignment(column))
+                })
+                .collect();
+            lines.push(format!("| {} |", cells.join(" | ")));
+
+            // The delimiter row follows the header row.
+            if index == 0 {
+                let delimiters: Vec<String> = (0..columns)
+                    .map(|column| {
+                        let width = widths[column];
+                        match alignment(column) {
+                            Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                            Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                            Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                            Alignment::None => "-".repeat(width),
+                        }
+                    })
+                    .collect();
+                lines.push(format!("| {} |", delimiters.join(" | ")));
+            }
+        }
+
+        lines
+    }
+}
+
+/// Locates the position within the lines written by `Table::lines`, as the number of a line
+/// and a byte index within that line.
+pub fn locate(lines: &[String], position: Position) -> (usize, usize) {
+    let line = line_of_row(position.row).min(lines.len().max(1) - 1);
+    let cells = match lines.get(line) {
+        Some(text) => split_row(text),
+        None => return (0, 0),
+    };
+
+    let index = match cells.get(position.column).or_else(|| cells.last()) {
+        // The cursor is placed after the leading space of empty cells.
+        Some(&(start, cell)) if cell.trim().is_empty() => start + cell.len().min(1),
+        Some(&(start, cell)) => {
+            let content_start = start + cell.len() - cell.trim_left().len();
+            (content_start + position.offset).min(start + cell.len())
+        }
+        None => 0,
+    };
+
+    (line, index)
+}
+
+/// Pads the cell to the width of its column, according to the alignment of the column.
+fn pad(cell: &str, width: usize, alignment: Alignment) -> String {
+    let padding = width - cell.chars().count().min(width);
+    match alignment {
+        Alignment::Right => format!("{}{}", " ".repeat(padding), cell),
+        Alignment::Center => {
+            let left = padding / 2;
+            format!("{}{}{}", " ".repeat(left), cell, " ".repeat(padding - left))
+        }
+        _ => format!("{}{}", cell, " ".repeat(padding)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &[&str] = &["Name | Size", ":--- | ---:", "a | 1", "bb | 22"];
+
+    #[test]
+    fn bounds_of_tables() {
+        let lines = ["Some text", "a | b", "--- | ---", "1 | 2", "", "c | d"];
+        assert_eq!(bounds(&lines, 0), None);
+        assert_eq!(bounds(&lines, 1), Some((1, 3)));
+        assert_eq!(bounds(&lines, 3), Some((1, 3)));
+        assert_eq!(bounds(&lines, 4), None);
+        // Without a delimiter row, the pipes are only text.
+        assert_eq!(bounds(&lines, 5), None);
+    }
+
+    #[test]
+    fn bounds_exclude_preceding_paragraphs() {
+        let lines = ["x | y", "a | b", "--- | ---", "1 | 2"];
+        assert_eq!(bounds(&lines, 0), None);
+        assert_eq!(bounds(&lines, 3), Some((1, 3)));
+    }
+
+    #[test]
+    fn rows_are_split_into_cells() {
+        assert_eq!(split_row("| a | b |"), vec![(1, " a "), (5, " b ")]);
+        assert_eq!(split_row("a|b"), vec![(0, "a"), (2, "b")]);
+        assert_eq!(split_row(r"a \| b | c"), vec![(0, r"a \| b "), (8, " c")]);
+        assert_eq!(split_row("|"), vec![(1, "")]);
+    }
+
+    #[test]
+    fn delimiter_rows() {
+        assert!(is_delimiter("| --- | :-: |"));
+        assert!(is_delimiter(":--|--:"));
+        assert!(!is_delimiter("| a | --- |"));
+        assert!(!is_delimiter("| - - |"));
+        assert_eq!(alignment(" :-: "), Alignment::Center);
+        assert_eq!(alignment(":--"), Alignment::Left);
+        assert_eq!(alignment("--:"), Alignment::Right);
+        assert_eq!(alignment("---"), Alignment::None);
+    }
+
+    #[test]
+    fn parse_locates_the_cursor() {
+        let (table, position) = Table::parse(TABLE, 3, 6);
+        assert_eq!(table.alignments, vec![Alignment::Left, Alignment::Right]);
+        assert_eq!(table.rows, vec![vec!["Name", "Size"], vec!["a", "1"], vec!["bb", "22"]]);
+        assert_eq!((position.row, position.column, position.offset), (2, 1, 1));
+
+        // A space which is being typed is kept within the cell.
+        let (table, position) = Table::parse(&["a  | b", "--- | ---"], 0, 2);
+        assert_eq!(table.rows[0][0], "a ");
+        assert_eq!((position.row, position.column, position.offset), (0, 0, 2));
+    }
+
+    #[test]
+    fn lines_are_aligned() {
+        let (table, _) = Table::parse(TABLE, 0, 0);
+        assert_eq!(
+            table.lines(),
+            vec!["| Name | Size |", "| :--- | ---: |", "| a    |    1 |", "| bb   |   22 |"]
+        );
+
+        let (table, _) = Table::parse(&["a | b", ":-: | -", "wide | x"], 0, 0);
+        assert_eq!(table.lines(), vec!["|  a   | b   |", "| :--: | --- |", "| wide | x   |"]);
+    }
+
+    #[test]
+    fn moving_between_cells() {
+        let (mut table, position) = Table::parse(TABLE, 2, 0);
+        let next = table.next_cell(position);
+        assert_eq!((next.row, next.column, next.offset), (1, 1, 1));
+        let next = table.next_cell(next);
+        assert_eq!((next.row, next.column, next.offset), (2, 0, 2));
+
+        // Moving beyond the last cell adds a row.
+        let next = table.next_cell(Position { row: 2, column: 1, offset: 0 });
+        assert_eq!((next.row, next.column), (3, 0));
+        assert_eq!(table.rows.len(), 4);
+
+        let previous = table.previous_cell(Position { row: 1, column: 0, offset: 0 });
+        assert_eq!((previous.row, previous.column, previous.offset), (0, 1, 4));
+        let previous = table.previous_cell(Position { row: 0, column: 0, offset: 0 });
+        assert_eq!((previous.row, previous.column, previous.offset), (0, 0, 4));
+    }
+
+    #[test]
+    fn commands_change_the_structure() {
+        let at = Position { row: 1, column: 0, offset: 0 };
+
+        let (mut table, _) = Table::parse(TABLE, 0, 0);
+        let position = table.apply(Command::InsertRowBelow, at);
+        assert_eq!(position.row, 2);
+        assert_eq!(table.rows[2], vec!["", ""]);
+
+        let (mut table, _) = Table::parse(TABLE, 0, 0);
+        table.apply(Command::DeleteRow, at);
+        assert_eq!(table.rows, vec![vec!["Name", "Size"], vec!["bb", "22"]]);
+
+        let (mut table, _) = Table::parse(TABLE, 0, 0);
+        let position = table.apply(Command::InsertColumnRight, at);
+        assert_eq!(position.column, 1);
+        assert_eq!(table.rows[0], vec!["Name", "", "Size"]);
+        assert_eq!(table.alignments[1], Alignment::None);
+
+        let (mut table, _) = Table::parse(TABLE, 0, 0);
+        table.apply(Command::DeleteColumn, at);
+        assert_eq!(table.rows, vec![vec!["Size"], vec!["1"], vec!["22"]]);
+        assert_eq!(table.alignments, vec![Alignment::Right]);
+
+        let (mut table, _) = Table::parse(TABLE, 0, 0);
+        table.apply(Command::Align(Alignment::Center), at);
+        assert_eq!(table.alignments[0], Alignment::Center);
+    }
+
+    #[test]
+    fn the_header_is_never_removed() {
+        let (mut table, _) = Table::parse(&["a | b", "--- | ---"], 0, 0);
+        table.apply(Command::DeleteRow, Position { row: 0, column: 0, offset: 0 });
+        assert_eq!(table.rows, vec![vec!["", ""]]);
+    }
+
+    #[test]
+    fn positions_are_located_in_the_lines() {
+        let (table, _) = Table::parse(TABLE, 0, 0);
+        let lines = table.lines();
+        assert_eq!(locate(&lines, Position { row: 0, column: 0, offset: 2 }), (0, 4));
+        assert_eq!(locate(&lines, Position { row: 2, column: 1, offset: 1 }), (3, 12));
+
+        let (table, _) = Table::parse(&["a | b", "--- | ---", " | "], 0, 0);
+        let lines = table.lines();
+        assert_eq!(locate(&lines, Position { row: 1, column: 1, offset: 0 }), (2, 8));
+    }
+}
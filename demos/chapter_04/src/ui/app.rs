@@ -1,23 +1,61 @@
-use super::{Content, Header, OpenDialog};
+use super::{Busy, Content, DocumentStatus, FileDialog, FocusMode, FolderDialog, Header, Loader,
+            OpenDialog, OpenDialogFactory, SaveDialog, Saver};
+use super::backlinks::{LINE as BACKLINK_LINE, PATH as BACKLINK_PATH};
+use super::completion;
+use super::diagnostics::LINE as PROBLEM_LINE;
+use super::format;
+use super::git::commit_message;
+use super::history::browse;
+use super::images::{document_dir, insert_images, paste_image};
 use super::misc::*;
-use super::save::save;
-use gdk::CONTROL_MASK;
+use super::outline::{ANCHOR, LINE};
+use super::project::PATH as PROJECT_PATH;
+use super::search::{LINE as SEARCH_LINE, PATH as SEARCH_PATH};
+use super::table::{self, Edit};
+use assets::is_image;
+use document::DocumentEvent;
+use encoding::TextFormat;
+use gdk::{CONTROL_MASK, MOD1_MASK, SELECTION_CLIPBOARD, SHIFT_MASK};
 use gdk::enums::key;
+use glib::signal::signal_stop_emission_by_name;
 use gtk;
+use git::{changes, Repo};
 use gtk::*;
-use preview::render;
+use history::History;
+use layout::Layout;
+use lint::lint;
+use outline::headings;
+use preview::{render, render_at, render_excerpt, LARGE_DOCUMENT};
+use project::is_markdown;
+use server::Server;
+use site;
+use sourceview::{Buffer, View};
 use state::ActiveMetadata;
-use std::fs::File;
-use std::io::Read;
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::rc::Rc;
+use std::sync::mpsc::{self, TryRecvError};
 use std::sync::{Arc, RwLock};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use url::file_uri_to_path;
 use webkit2gtk::*;
+use wiki::{backlinks as wiki_backlinks, Notes};
+
+/// How long typing must pause for, in milliseconds, before a large document is rendered,
+/// linted, counted and served.
+const LARGE_DOCUMENT_DELAY: u32 = 300;
+
+/// How long typing must pause for, in milliseconds, before the lines changed since HEAD are
+/// marked again.
+const GIT_DELAY: u32 = 500;
 
 pub struct App {
     pub window:  Window,
     pub header:  Header,
     pub content: Content,
+    pub focus:   FocusMode,
 }
 
 /// A wrapped `App` which provides the capability to execute the program.
@@ -27,6 +65,8 @@ impl ConnectedApp {
     /// Display the window, and execute the gtk main event loop.
     pub fn then_execute(self) {
         self.0.window.show_all();
+        // The layout hides the editor or the preview, so it is applied once they are shown.
+        self.0.content.panes.set_layout(Layout::load());
         gtk::main();
     }
 }
@@ -58,6 +98,15 @@ impl App {
         // Add the content to the window.
         window.add(&content.container);
 
+        // Focus mode hides everything but the editor, and restores the layout afterwards.
+        let focus = FocusMode::new(&window, &header, &content);
+
+        // Enable the keyboard accelerators of the format menu.
+        let accel_group = AccelGroup::new();
+        window.add_accel_group(&accel_group);
+        header.format.add_accelerators(&accel_group);
+        header.layout.add_accelerators(&accel_group);
+
         // Programs what to do when the exit button is used.
         window.connect_delete_event(move |_, _| {
             main_quit();
@@ -65,7 +114,7 @@ impl App {
         });
 
         // Return the application structure.
-        App { window, header, content }
+        App { window, header, content, focus }
     }
 
     /// Creates external state, and maps all of the UI functionality to the UI.
@@ -74,17 +123,60 @@ impl App {
         let current_file = Arc::new(RwLock::new(None));
         // Keep track of whether we are fullscreened or not.
         let fullscreen = Arc::new(AtomicBool::new(false));
+        // The state of the document, which the title and the save buttons display.
+        let status = DocumentStatus::new(&self.window, &self.header, current_file.clone());
+        // Reads and writes documents without blocking the interface.
+        let busy = Busy::new(&self.header);
+        let loader = Loader::new(
+            &self.window,
+            &self.content.source.buff,
+            &self.content.source.view,
+            &self.content.status.loading,
+            current_file.clone(),
+            &busy,
+            &status,
+        );
+        let saver = Saver::new(
+            &self.window,
+            &self.content.source.buff,
+            &self.header.save,
+            current_file.clone(),
+            &busy,
+            &status,
+            SaveDialog::factory(&self.window),
+        );
 
         {
-            let save = &self.header.save;
-            let save_as = &self.header.save_as;
-
             // Connect all of the events that this UI will act upon.
-            self.editor_changed(current_file.clone(), &save);
-            self.open_file(current_file.clone());
-            self.save_event(&save, &save, current_file.clone(), false);
-            self.save_event(&save, &save_as, current_file.clone(), true);
-            self.key_events(current_file, fullscreen);
+            self.editor_changed();
+            self.modified_events(status);
+            self.outline_toggled();
+            self.outline_activated();
+            self.statistics_events();
+            self.spelling_events();
+            self.format_events();
+            self.completion_events(current_file.clone());
+            self.table_events();
+            self.focus_events(fullscreen.clone());
+            self.layout_events();
+            self.history_events(saver.clone());
+            self.lint_events(current_file.clone());
+            self.image_events(saver.clone());
+            self.open_file(loader.clone(), OpenDialog::factory(&self.window));
+            self.project_events(
+                loader.clone(),
+                FolderDialog::factory(&self.window, "Open Folder", "Open"),
+            );
+            self.search_events(loader.clone());
+            self.site_events();
+            self.server_events(current_file.clone());
+            self.backlinks_events(loader.clone(), saver.clone());
+            self.wiki_link_events(loader.clone());
+            self.save_event(&self.header.save, saver.clone(), false);
+            self.save_event(&self.header.save_as, saver.clone(), true);
+            self.git_events(saver.clone());
+            self.encoding_events(saver.clone());
+            self.key_events(saver, fullscreen);
         }
 
         // Wrap the `App` within `ConnectedApp` to enable the developer to execute the program.
@@ -93,28 +185,30 @@ impl App {
 
     /// Handles special functions that should be invoked when certain keys and key combinations
     /// are pressed on the keyboard.
-    fn key_events(
-        &self,
-        current_file: Arc<RwLock<Option<ActiveMetadata>>>,
-        fullscreen: Arc<AtomicBool>,
-    ) {
+    fn key_events(&self, saver: Saver, fullscreen: Arc<AtomicBool>) {
         // Grab required references beforehand.
-        let editor = self.content.source.buff.clone();
-        let headerbar = self.header.container.clone();
-        let save_button = self.header.save.clone();
+        let focus = self.focus.clone();
 
         // Each key press will invoke this function.
         self.window.connect_key_press_event(move |window, gdk| {
             match gdk.get_keyval() {
+                // Toggle focus mode when ctrl+shift+enter is pressed, and leave it with escape.
+                key::Return if gdk.get_state().contains(CONTROL_MASK | SHIFT_MASK) => {
+                    focus.toggle(&fullscreen);
+                    return Inhibit(true);
+                }
+                key::Escape if focus.is_active() => focus.exit(&fullscreen),
                 // Fullscreen the UI when F11 is pressed.
                 key::F11 => if fullscreen.fetch_xor(true, Ordering::SeqCst) {
                     window.unfullscreen();
                 } else {
                     window.fullscreen();
                 },
-                // Save the file when ctrl+s is pressed.
+                // Save the file when ctrl+s is pressed, as the "Save" button would.
                 key if key == 's' as u32 && gdk.get_state().contains(CONTROL_MASK) => {
-                    save(&editor, &headerbar, &save_button, &current_file, false);
+                    if saver.status.get().can_save() {
+                        saver.save(false, |_| ());
+                    }
                 }
                 _ => (),
             }
@@ -123,15 +217,12 @@ impl App {
     }
 
     /// Programs the "Open" button for opening files.
-    fn open_file(&self, current_file: Arc<RwLock<Option<ActiveMetadata>>>) {
-        let editor = self.content.source.buff.clone();
-        let preview = self.content.preview.clone();
-        let headerbar = self.header.container.clone();
+    fn open_file(&self, loader: Loader, dialog: OpenDialogFactory) {
         self.header.open.connect_clicked(move |_| {
             // Create a new open file dialog using the current file's parent
             // directory as the preferred directory, if it's set.
-            let open_dialog = OpenDialog::new({
-                let lock = current_file.read().unwrap();
+            let open_dialog = dialog({
+                let lock = loader.current_file.read().unwrap();
                 if let Some(ref path) = *lock {
                     path.get_dir()
                 } else {
@@ -141,62 +232,1127 @@ impl App {
 
             // Runs the dialog, and opens the file if a file was selected.
             if let Some(new_file) = open_dialog.run() {
-                if let Ok(mut file) = File::open(&new_file) {
-                    // Read the file's contents into an in-memory buffer
-                    let mut contents = String::new();
-                    let _ = file.read_to_string(&mut contents);
-
-                    // Update the title and subtitle
-                    set_title(&headerbar, &new_file);
-                    if let Some(parent) = new_file.parent() {
-                        let subtitle: &str = &parent.to_string_lossy();
-                        headerbar.set_subtitle(subtitle);
+                loader.load(&new_file, None);
+            }
+        });
+    }
+
+    /// Programs the "Open Folder" button, which lists the markdown documents of a folder within
+    /// a sidebar. The sidebar is kept up to date as documents are added and removed.
+    fn project_events(&self, loader: Loader, dialog: OpenDialogFactory) {
+        let folder = self.content.project.folder.clone();
+
+        // The folder is scanned in the background, as large folders take a while to list.
+        {
+            let project = self.content.project.clone();
+            let build = self.header.build.clone();
+            self.header.folder.connect_clicked(move |_| {
+                let path = match dialog(None).run() {
+                    Some(path) => path,
+                    None => return,
+                };
+
+                *project.folder.write().unwrap() = Some((path.clone(), Vec::new()));
+                project.update(&path, &[]);
+                project.container.set_reveal_child(true);
+                build.show();
+                project.watcher.watch(&path);
+            });
+        }
+
+        {
+            let folder = folder.clone();
+            let project = self.content.project.clone();
+            self.content.project.search.connect_search_changed(move |_| {
+                if let Some((ref path, ref files)) = *folder.read().unwrap() {
+                    project.update(path, files);
+                }
+            });
+        }
+
+        // The tree is only rebuilt when documents were added or removed.
+        {
+            let project = self.content.project.clone();
+            self.content.project.watcher.connect_changed(move |path, found| {
+                let mut folder = folder.write().unwrap();
+                if let Some((ref current, ref mut files)) = *folder {
+                    if current == path && files.as_slice() != found {
+                        project.update(path, found);
+                        *files = found.to_vec();
                     }
+                }
+            });
+        }
 
-                    // Set the shared file path as this file.
-                    *current_file.write().unwrap() =
-                        Some(ActiveMetadata::new(new_file, &contents.as_bytes()));
+        self.content.project.view.connect_row_activated(move |tree, path, _| {
+            let model = match tree.get_model() {
+                Some(model) => model,
+                None => return,
+            };
+
+            let file = model
+                .get_iter(path)
+                .and_then(|iter| model.get_value(&iter, PROJECT_PATH as i32).get::<String>());
+
+            // Directories have no path, and are expanded or collapsed instead.
+            match file {
+                Some(ref file) if !file.is_empty() => {
+                    loader.load(Path::new(file), None)
+                }
+                _ => if tree.row_expanded(path) {
+                    tree.collapse_row(path);
+                } else {
+                    tree.expand_row(path, false);
+                },
+            }
+        });
+    }
+
+    /// Programs the panel which searches the documents of the open folder. Searches run as the
+    /// query is changed, and activating a result opens its document at the matching line.
+    fn search_events(&self, loader: Loader) {
+        let panel = self.content.search.clone();
+        let folder = self.content.project.folder.clone();
+        let search = Rc::new(move || {
+            let folder = folder.read().unwrap();
+            panel.search(folder.as_ref().map(|&(ref path, _)| path.as_path()));
+        });
 
-                    // Update the editor & preview
-                    editor.set_text(&contents);
-                    preview.load_html(&render(&contents), None);
+        {
+            let panel = self.content.search.clone();
+            self.header.search.connect_toggled(move |button| {
+                panel.container.set_reveal_child(button.get_active());
+                if button.get_active() {
+                    panel.entry.grab_focus();
                 }
+            });
+        }
+
+        {
+            let search = search.clone();
+            self.content.search.entry.connect_search_changed(move |_| search());
+        }
+
+        for option in &[&self.content.search.regex, &self.content.search.case_sensitive] {
+            let search = search.clone();
+            option.connect_toggled(move |_| search());
+        }
+
+        for globs in &[&self.content.search.include, &self.content.search.exclude] {
+            let search = search.clone();
+            globs.connect_activate(move |_| search());
+        }
+
+        self.content.search.view.connect_row_activated(move |tree, path, _| {
+            let model = match tree.get_model() {
+                Some(model) => model,
+                None => return,
+            };
+
+            let iter = match model.get_iter(path) {
+                Some(iter) => iter,
+                None => return,
+            };
+
+            let file = match model.get_value(&iter, SEARCH_PATH as i32).get::<String>() {
+                Some(file) => PathBuf::from(file),
+                None => return,
+            };
+            let line = model.get_value(&iter, SEARCH_LINE as i32).get::<i32>().unwrap_or(-1);
+
+            // The document is only read again if it is not already open.
+            let line = if line < 0 { 0 } else { line };
+            let is_open = loader
+                .current_file
+                .read()
+                .unwrap()
+                .as_ref()
+                .map_or(false, |current| current.get_path() == file.as_path());
+            if is_open {
+                jump_to_line(&loader.view, &loader.editor, line);
+            } else {
+                loader.load(&file, Some(line));
+            }
+        });
+    }
+
+    /// Programs the "Build Site" button, which renders the documents of the open folder into
+    /// a static site within a chosen directory. The site is built on a background thread.
+    fn site_events(&self) {
+        let folder = self.content.project.folder.clone();
+        let window = self.window.clone();
+        self.header.build.connect_clicked(move |button| {
+            let folder = match *folder.read().unwrap() {
+                Some((ref path, _)) => path.clone(),
+                None => return,
+            };
+
+            let dialog =
+                FolderDialog::new(&window, "Build Site Into", "Build", Some(folder.clone()));
+            let output = match dialog.run() {
+                Some(output) => output,
+                None => return,
+            };
+
+            button.set_sensitive(false);
+            let (sender, receiver) = mpsc::channel();
+            {
+                let output = output.clone();
+                thread::spawn(move || {
+                    let _ = sender.send(site::build(&folder, &output));
+                });
+            }
+
+            let (button, window) = (button.clone(), window.clone());
+            gtk::timeout_add(100, move || {
+                let message = match receiver.try_recv() {
+                    Ok(Ok(report)) => {
+                        let mut message = format!(
+                            "Built {} pages, {} tag pages, and {} assets into {}.",
+                            report.pages,
+                            report.tags,
+                            report.assets,
+                            output.display()
+                        );
+                        if !report.skipped.is_empty() {
+                            message.push_str("\n\nThese documents were skipped:");
+                            for &(ref document, ref why) in &report.skipped {
+                                message.push_str(&format!("\n{}: {}", document.display(), why));
+                            }
+                        }
+                        message
+                    }
+                    Ok(Err(why)) => format!("Unable to build the site: {}", why),
+                    Err(TryRecvError::Empty) => return Continue(true),
+                    Err(TryRecvError::Disconnected) => "Unable to build the site.".to_owned(),
+                };
+
+                button.set_sensitive(true);
+                let dialog = MessageDialog::new(
+                    Some(&window),
+                    DIALOG_MODAL,
+                    MessageType::Info,
+                    ButtonsType::Close,
+                    &message,
+                );
+                dialog.run();
+                dialog.destroy();
+                Continue(false)
+            });
+        });
+    }
+
+    /// Programs the status bar's encoding and line ending menus, which convert the document to
+    /// a different format when it is next saved.
+    fn encoding_events(&self, saver: Saver) {
+        let current_file = saver.current_file.clone();
+        let refresh = {
+            let menus = self.content.status.format.clone();
+            let current_file = current_file.clone();
+            move || menus.set_format(current_file.read().unwrap().as_ref().map(|f| f.get_format()))
+        };
+        let refresh = Rc::new(refresh);
+
+        // The format changes when another file is opened, and when the document is saved as a
+        // new file.
+        {
+            let refresh = refresh.clone();
+            self.content.source.buff.connect_changed(move |_| refresh());
+        }
+        {
+            let refresh = refresh.clone();
+            saver.connect_saved(move || refresh());
+        }
+
+        // Changes the format of the current file, which modifies the document until it is
+        // saved in the new format.
+        let convert = {
+            let editor = self.content.source.buff.clone();
+            move |change: &Fn(&mut TextFormat)| {
+                let changed = match *current_file.write().unwrap() {
+                    Some(ref mut file) => {
+                        let mut format = file.get_format();
+                        change(&mut format);
+                        let changed = format != file.get_format();
+                        file.set_format(format);
+                        changed
+                    }
+                    None => false,
+                };
+
+                // The lock is released first, as the modified flag's handlers read the file.
+                if changed {
+                    editor.set_modified(true);
+                }
+                refresh();
+            }
+        };
+        let convert = Rc::new(convert);
+
+        for &(ref item, encoding) in &self.content.status.format.encodings {
+            let convert = convert.clone();
+            item.connect_activate(move |_| {
+                convert(&|format: &mut TextFormat| {
+                    format.encoding = encoding;
+                    format.bom = format.bom && !encoding.bom().is_empty();
+                });
+            });
+        }
+
+        {
+            let convert = convert.clone();
+            self.content.status.format.bom.connect_toggled(move |item| {
+                let bom = item.get_active();
+                convert(&|format: &mut TextFormat| format.bom = bom);
+            });
+        }
+
+        for &(ref item, ending) in &self.content.status.format.line_endings {
+            let convert = convert.clone();
+            item.connect_activate(move |_| {
+                convert(&|format: &mut TextFormat| format.line_ending = ending);
+            });
+        }
+    }
+
+    /// Programs the "Serve Preview" button, which serves the preview to web browsers while it
+    /// is active. The served pages reload themselves as the document is edited.
+    fn server_events(&self, current_file: Arc<RwLock<Option<ActiveMetadata>>>) {
+        let server: Arc<RwLock<Option<Server>>> = Arc::new(RwLock::new(None));
+
+        // Sends the document's latest text to the server, if it is running.
+        let publish = {
+            let server = server.clone();
+            let folder = self.content.project.folder.clone();
+            move |editor: &Buffer| {
+                let server = server.read().unwrap();
+                let (server, markdown) = match (server.as_ref(), get_buffer(editor)) {
+                    (Some(server), Some(markdown)) => (server, markdown),
+                    _ => return,
+                };
+
+                let current_file = current_file.read().unwrap();
+                let document = current_file.as_ref().map(|file| file.get_path());
+                server.update(&markdown, document, notes(&folder.read().unwrap()));
+            }
+        };
+        let publish = Rc::new(publish);
+
+        // Starts the server, or stops it, according to the state of the status bar's buttons.
+        let restart = {
+            let (server, publish) = (server.clone(), publish.clone());
+            let status = self.content.status.clone();
+            let editor = self.content.source.buff.clone();
+            let window = self.window.clone();
+            move || {
+                *server.write().unwrap() = None;
+                if !status.serve.get_active() {
+                    return status.set_address(None);
+                }
+
+                match Server::start(status.share.get_active()) {
+                    Ok(started) => {
+                        status.set_address(Some(&started.url()));
+                        *server.write().unwrap() = Some(started);
+                        publish(&editor);
+                    }
+                    Err(why) => {
+                        eprintln!("unable to serve the preview: {}", why);
+                        status.serve.set_active(false);
+                        let message = format!(
+                            "Unable to serve the preview, and so serving has been turned off: {}",
+                            why
+                        );
+                        show_error(&window, &message);
+                    }
+                }
+            }
+        };
+        let restart = Rc::new(restart);
+
+        {
+            let restart = restart.clone();
+            self.content.status.serve.connect_toggled(move |_| restart());
+        }
+
+        {
+            let (server, restart) = (server.clone(), restart.clone());
+            self.content.status.share.connect_toggled(move |_| {
+                if server.read().unwrap().is_some() {
+                    restart();
+                }
+            });
+        }
+
+        connect_changed_debounced(&self.content.source.buff, move |editor| publish(editor));
+    }
+
+    /// Programs the backlinks sidebar, which lists the documents of the open folder that link
+    /// to the current document. The list is refreshed whenever a different document is opened,
+    /// or the folder's documents are added, removed or changed.
+    fn backlinks_events(&self, loader: Loader, saver: Saver) {
+        // The backlinks are found on a background thread, as every document of the folder is
+        // read. Incremented whenever they are found again, so that an earlier search which has
+        // not finished is abandoned.
+        let generation = Arc::new(AtomicUsize::new(0));
+        // The document that the backlinks were last found for.
+        let listed: Rc<RefCell<Option<PathBuf>>> = Rc::new(RefCell::new(None));
+        let refresh = {
+            let backlinks = self.content.backlinks.clone();
+            let folder = self.content.project.folder.clone();
+            let current_file = loader.current_file.clone();
+            let listed = listed.clone();
+            move || {
+                if !backlinks.container.get_reveal_child() {
+                    return;
+                }
+
+                let searching = generation.fetch_add(1, Ordering::SeqCst) + 1;
+                let (folder, files) = match *folder.read().unwrap() {
+                    Some((ref folder, ref files)) => (folder.clone(), files.clone()),
+                    None => return backlinks.clear("Open a folder to find backlinks."),
+                };
+
+                let document = match *current_file.read().unwrap() {
+                    Some(ref current) => current.get_path().to_path_buf(),
+                    None => return backlinks.clear("Save the document to find backlinks."),
+                };
+                *listed.borrow_mut() = Some(document.clone());
+
+                let (sender, receiver) = mpsc::channel();
+                {
+                    let folder = folder.clone();
+                    thread::spawn(move || {
+                        let notes = Notes { folder: &folder, files: &files };
+                        let _ = sender.send(wiki_backlinks(notes, &document));
+                    });
+                }
+
+                let (backlinks, current) = (backlinks.clone(), generation.clone());
+                gtk::timeout_add(50, move || {
+                    if current.load(Ordering::SeqCst) != searching {
+                        return Continue(false);
+                    }
+
+                    match receiver.try_recv() {
+                        Ok(found) => backlinks.update(&folder, &found),
+                        Err(TryRecvError::Empty) => return Continue(true),
+                        Err(TryRecvError::Disconnected) => (),
+                    }
+                    Continue(false)
+                });
+            }
+        };
+        let refresh = Rc::new(refresh);
+
+        {
+            let backlinks = self.content.backlinks.clone();
+            let refresh = refresh.clone();
+            self.header.backlinks.connect_toggled(move |button| {
+                backlinks.container.set_reveal_child(button.get_active());
+                refresh();
+            });
+        }
+
+        // The backlinks change when the folder's documents do.
+        {
+            let refresh = refresh.clone();
+            self.content.project.watcher.connect_changed(move |_, _| refresh());
+        }
+
+        // They also change when another document is opened, or the document is saved as
+        // another file.
+        let refresh_document = {
+            let current_file = loader.current_file.clone();
+            move || {
+                let current_file = current_file.read().unwrap();
+                let path = current_file.as_ref().map(|file| file.get_path().to_path_buf());
+                if path.is_some() && *listed.borrow() != path {
+                    refresh();
+                }
+            }
+        };
+        let refresh_document = Rc::new(refresh_document);
+        {
+            let refresh_document = refresh_document.clone();
+            self.content.source.buff.connect_changed(move |_| refresh_document());
+        }
+        saver.connect_saved(move || refresh_document());
+
+        self.content.backlinks.view.connect_row_activated(move |tree, path, _| {
+            let model = match tree.get_model() {
+                Some(model) => model,
+                None => return,
+            };
+
+            let iter = match model.get_iter(path) {
+                Some(iter) => iter,
+                None => return,
+            };
+
+            let file = match model.get_value(&iter, BACKLINK_PATH as i32).get::<String>() {
+                Some(file) => PathBuf::from(file),
+                None => return,
+            };
+            let line = model.get_value(&iter, BACKLINK_LINE as i32).get::<i32>().unwrap_or(0);
+
+            loader.load(&file, Some(line));
+        });
+    }
+
+    /// Opens the documents that wiki links refer to when they are clicked within the preview.
+    fn wiki_link_events(&self, loader: Loader) {
+        self.content.preview.connect_decide_policy(move |_, decision, kind| {
+            if kind != PolicyDecisionType::NavigationAction {
+                return false;
+            }
+
+            let uri = decision
+                .clone()
+                .downcast::<NavigationPolicyDecision>()
+                .ok()
+                .and_then(|navigation| navigation.get_request())
+                .and_then(|request| request.get_uri());
+
+            // Only links to local markdown documents are opened within the editor.
+            match uri.as_ref().and_then(|uri| file_uri_to_path(uri)) {
+                Some(ref path) if is_markdown(path) => {
+                    decision.ignore();
+                    loader.load(path, None);
+                    true
+                }
+                _ => false,
             }
         });
     }
 
     // Utilized for programming the "Save" and "Save As" buttons.
-    fn save_event(
-        &self,
-        save_button: &Button,
-        actual_button: &Button,
-        current_file: Arc<RwLock<Option<ActiveMetadata>>>,
-        save_as: bool,
-    ) {
-        let editor = self.content.source.buff.clone();
-        let headerbar = self.header.container.clone();
-        let save_button = save_button.clone();
-        actual_button.connect_clicked(
-            move |_| save(&editor, &headerbar, &save_button, &current_file, save_as),
-        );
+    fn save_event(&self, button: &Button, saver: Saver, save_as: bool) {
+        button.connect_clicked(move |_| saver.save(save_as, |_| ()));
     }
 
-    /// Updates the WebView when the SourceBuffer is modified.
-    fn editor_changed(
-        &self,
-        current_file: Arc<RwLock<Option<ActiveMetadata>>>,
-        save_button: &Button,
-    ) {
+    /// Updates the WebView when the SourceBuffer is modified. Large documents are only
+    /// rendered once typing pauses, and only the lines around those that are visible within
+    /// the editor are rendered, following the editor as it is scrolled.
+    fn editor_changed(&self) {
+        // Whether the document is large.
+        let large = Arc::new(AtomicBool::new(false));
+
+        // Renders the visible lines once typing, or scrolling, pauses.
+        let schedule = {
+            let preview = self.content.preview.clone();
+            let outline = self.content.outline.clone();
+            let folder = self.content.project.folder.clone();
+            let view = self.content.source.view.clone();
+            let editor = self.content.source.buff.clone();
+            debounced(LARGE_DOCUMENT_DELAY, move || {
+                if let Some(markdown) = get_buffer(&editor) {
+                    let (first, last) = visible_lines(&view);
+                    let folder = folder.read().unwrap();
+                    let html = render_excerpt(&markdown, first, last, notes(&folder));
+                    preview.load_html(&html, None);
+                    outline.update(&headings(&markdown));
+                }
+            })
+        };
+
+        if let Some(adjustment) = self.content.source.container.get_vadjustment() {
+            let (large, schedule) = (large.clone(), schedule.clone());
+            adjustment.connect_value_changed(move |_| if large.load(Ordering::SeqCst) {
+                schedule();
+            });
+        }
+
         let preview = self.content.preview.clone();
-        let save_button = save_button.clone();
+        let outline = self.content.outline.clone();
+        let folder = self.content.project.folder.clone();
         self.content.source.buff.connect_changed(move |editor| {
+            // The size is known without copying the text out of the buffer.
+            let is_large = is_large(editor);
+            large.store(is_large, Ordering::SeqCst);
+            if is_large {
+                schedule();
+            } else if let Some(markdown) = get_buffer(&editor) {
+                let folder = folder.read().unwrap();
+                preview.load_html(&render(&markdown, notes(&folder)), None);
+                outline.update(&headings(&markdown));
+            }
+        });
+    }
+
+    /// Marks the document as modified within the title, and allows it to be saved, whenever
+    /// it differs from the file. Undoing every change since the file was read or written
+    /// returns the document to being unmodified.
+    fn modified_events(&self, status: DocumentStatus) {
+        self.content.source.buff.connect_modified_changed(move |editor| {
+            status.update(DocumentEvent::Modified(editor.get_modified()));
+        });
+    }
+
+    /// Reveals or hides the outline sidebar when the header bar's outline button is toggled.
+    fn outline_toggled(&self) {
+        let sidebar = self.content.outline.container.clone();
+        self.header.outline.connect_toggled(move |button| {
+            sidebar.set_reveal_child(button.get_active());
+        });
+    }
+
+    /// Moves the editor's cursor, and the preview, to the heading that was selected in the
+    /// outline sidebar.
+    fn outline_activated(&self) {
+        let editor = self.content.source.buff.clone();
+        let view = self.content.source.view.clone();
+        let preview = self.content.preview.clone();
+        let folder = self.content.project.folder.clone();
+        self.content.outline.view.connect_row_activated(move |tree, path, _| {
+            let model = match tree.get_model() {
+                Some(model) => model,
+                None => return,
+            };
+
+            let iter = match model.get_iter(path) {
+                Some(iter) => iter,
+                None => return,
+            };
+
+            let line = model.get_value(&iter, LINE as i32).get::<i32>().unwrap_or(0);
+            let anchor = model.get_value(&iter, ANCHOR as i32).get::<String>();
+
+            // Large documents are rendered around the heading once the editor has scrolled to it.
+            jump_to_line(&view, &editor, line);
+            if is_large(&editor) {
+                return;
+            }
+
             if let Some(markdown) = get_buffer(&editor) {
-                preview.load_html(&render(&markdown), None);
-                if let Some(ref current_file) = *current_file.read().unwrap() {
-                    let has_same_sum = current_file.is_same_as(&markdown.as_bytes());
-                    save_button.set_sensitive(!has_same_sum);
+                let anchor = anchor.as_ref().map(|anchor| anchor.as_str());
+                let folder = folder.read().unwrap();
+                preview.load_html(&render_at(&markdown, anchor, notes(&folder)), None);
+            }
+        });
+    }
+
+    /// Keeps the status bar's statistics up to date as the text and selection change.
+    fn statistics_events(&self) {
+        let status = self.content.status.clone();
+        connect_changed_debounced(&self.content.source.buff, move |editor| {
+            status.refresh_document(editor)
+        });
+
+        // The insert and selection bound marks are moved whenever the selection changes.
+        let status = self.content.status.clone();
+        self.content.source.buff.connect_mark_set(move |editor, _, mark| {
+            let name = mark.get_name();
+            if name.as_ref().map_or(false, |n| n == "insert" || n == "selection_bound") {
+                status.refresh_selection(editor);
+            }
+        });
+
+        let status = self.content.status.clone();
+        let editor = self.content.source.buff.clone();
+        self.content.status.include_code.connect_toggled(move |_| status.refresh(&editor));
+
+        self.content.status.refresh(&self.content.source.buff);
+    }
+
+    /// Checks the spelling of the lines which change once typing pauses, and offers suggestions
+    /// for misspelled words within the editor's context menu.
+    fn spelling_events(&self) {
+        let spell = self.content.source.spell.clone();
+        self.content.source.buff.connect_insert_text(move |editor, iter, text| {
+            spell.inserting(editor, iter, text)
+        });
+
+        let spell = self.content.source.spell.clone();
+        self.content.source.buff.connect_delete_range(move |editor, start, end| {
+            spell.deleting(editor, start, end)
+        });
+
+        // Right clicking moves the cursor to the clicked word, so that the context menu can
+        // offer suggestions for it.
+        let editor = self.content.source.buff.clone();
+        self.content.source.view.connect_button_press_event(move |view, event| {
+            if event.get_button() == 3 && !editor.get_has_selection() {
+                let (x, y) = event.get_position();
+                let (x, y) = view.window_to_buffer_coords(TextWindowType::Text, x as i32, y as i32);
+                if let Some(iter) = view.get_iter_at_location(x, y) {
+                    editor.place_cursor(&iter);
+                }
+            }
+            Inhibit(false)
+        });
+
+        let spell = self.content.source.spell.clone();
+        let editor = self.content.source.buff.clone();
+        self.content.source.view.connect_populate_popup(move |_, menu| {
+            let menu = match menu.clone().downcast::<Menu>() {
+                Ok(menu) => menu,
+                Err(_) => return,
+            };
+
+            let cursor = match editor.get_insert() {
+                Some(mark) => editor.get_iter_at_mark(&mark),
+                None => return,
+            };
+
+            let (start, end, word) = match spell.misspelled_at(&cursor) {
+                Some(misspelled) => misspelled,
+                None => return,
+            };
+
+            // Items are prepended, so they are added from the bottom up.
+            menu.prepend(&SeparatorMenuItem::new());
+
+            let add = MenuItem::new_with_label("Add to Dictionary");
+            {
+                let spell = spell.clone();
+                let editor = editor.clone();
+                let word = word.clone();
+                add.connect_activate(move |_| spell.add_word(&editor, &word));
+            }
+            menu.prepend(&add);
+
+            let suggestions = spell.suggest(&word);
+            if suggestions.is_empty() {
+                let none = MenuItem::new_with_label("(No Suggestions)");
+                none.set_sensitive(false);
+                menu.prepend(&none);
+            }
+
+            for suggestion in suggestions.into_iter().rev() {
+                let item = MenuItem::new_with_label(&suggestion);
+                let editor = editor.clone();
+                item.connect_activate(move |_| {
+                    let mut start = editor.get_iter_at_offset(start);
+                    let mut end = editor.get_iter_at_offset(end);
+                    editor.begin_user_action();
+                    editor.delete(&mut start, &mut end);
+                    editor.insert(&mut start, &suggestion);
+                    editor.end_user_action();
+                });
+                menu.prepend(&item);
+            }
+
+            menu.show_all();
+        });
+
+        let spell = self.content.source.spell.clone();
+        let editor = self.content.source.buff.clone();
+        self.content.status.language.connect_changed(move |combo| {
+            match combo.get_active_id() {
+                Some(ref language) if !language.is_empty() => {
+                    spell.set_language(&editor, Some(language))
+                }
+                _ => spell.set_language(&editor, None),
+            }
+        });
+
+        self.content.status.select_default_language();
+    }
+
+    /// Applies formatting to the editor's text when a command in the format menu is activated.
+    fn format_events(&self) {
+        for &(ref item, command) in &self.header.format.items {
+            let editor = self.content.source.buff.clone();
+            let view = self.content.source.view.clone();
+            item.connect_activate(move |_| {
+                format::apply(&editor, command);
+                view.grab_focus();
+            });
+        }
+
+        let editor = self.content.source.buff.clone();
+        let view = self.content.source.view.clone();
+        self.header.format.document.connect_activate(move |_| {
+            format::format_document(&view, &editor);
+            view.grab_focus();
+        });
+    }
+
+    /// Offers completions within the editor's completion window as links, references, and emoji
+    /// shortcodes are typed. Note names are completed from the documents of the open folder.
+    fn completion_events(&self, current_file: Arc<RwLock<Option<ActiveMetadata>>>) {
+        let folder = self.content.project.folder.clone();
+        completion::add_providers(&self.content.source.view, current_file, folder);
+    }
+
+    /// Programs the table editor: Tab and Shift+Tab move between the cells of the table at the
+    /// cursor, its columns are aligned as it is typed into, and the table menu edits its
+    /// structure.
+    fn table_events(&self) {
+        for &(ref item, command) in &self.header.format.table {
+            let editor = self.content.source.buff.clone();
+            let view = self.content.source.view.clone();
+            item.connect_activate(move |_| {
+                table::apply(&editor, command);
+                view.grab_focus();
+            });
+        }
+
+        // Text which is typed into a table, including text composed by an input method, is
+        // inserted by GTK, and the table is realigned once the user action which inserted it
+        // has ended. Undoing the typing first undoes the alignment.
+        let typed = Rc::new(Cell::new(false));
+        {
+            let typed = typed.clone();
+            self.content.source.buff.connect_begin_user_action(move |_| typed.set(false));
+        }
+        {
+            let typed = typed.clone();
+            self.content.source.buff.connect_insert_text(move |_, _, _| typed.set(true));
+        }
+        self.content.source.buff.connect_end_user_action(move |editor| {
+            if typed.replace(false) {
+                table::realign(editor);
+            }
+        });
+
+        // Tab and Shift+Tab move between cells, and deleting realigns the table within the same
+        // undoable action as the deletion.
+        let editor = self.content.source.buff.clone();
+        self.content.source.view.connect_key_press_event(move |_, event| {
+            if event.get_state().intersects(CONTROL_MASK | MOD1_MASK) || editor.get_has_selection()
+            {
+                return Inhibit(false);
+            }
+
+            let handled = match event.get_keyval() {
+                key::ISO_Left_Tab => table::previous_cell(&editor),
+                key::Tab if event.get_state().contains(SHIFT_MASK) => {
+                    table::previous_cell(&editor)
+                }
+                key::Tab => table::next_cell(&editor),
+                key::BackSpace => table::edit(&editor, Edit::Backspace),
+                key::Delete | key::KP_Delete => table::edit(&editor, Edit::Delete),
+                _ => false,
+            };
+            Inhibit(handled)
+        });
+    }
+
+    /// Toggles focus mode from the view menu, and keeps the text column centered, and the
+    /// current paragraph highlighted, while focus mode is active.
+    fn focus_events(&self, fullscreen: Arc<AtomicBool>) {
+        let focus = self.focus.clone();
+        self.header.layout.focus.connect_activate(move |_| focus.toggle(&fullscreen));
+
+        let focus = self.focus.clone();
+        self.content.source.view.connect_size_allocate(move |_, _| focus.center());
+
+        let focus = self.focus.clone();
+        self.content.source.buff.connect_mark_set(move |_, _, mark| {
+            if mark.get_name().map_or(false, |name| name == "insert") {
+                focus.highlight_current();
+            }
+        });
+
+        // Only the paragraphs which the cursor leaves and enters are retagged.
+        let focus = self.focus.clone();
+        self.content.source.buff.connect_changed(move |_| focus.highlight_current());
+
+        let focus = self.focus.clone();
+        self.content.status.typewriter.connect_toggled(move |_| {
+            focus.center();
+            focus.highlight_current();
+        });
+    }
+
+    /// Arranges the editor and the preview when a layout is chosen, and remembers the choice.
+    fn layout_events(&self) {
+        for &(ref item, layout) in &self.header.layout.items {
+            let panes = self.content.panes.clone();
+            item.connect_activate(move |_| {
+                panes.set_layout(layout);
+                if let Err(why) = layout.save() {
+                    eprintln!("unable to save the layout: {}", why);
                 }
+            });
+        }
+
+        // Closing the preview's window returns the preview to the main window.
+        let panes = self.content.panes.clone();
+        self.content.panes.window.connect_delete_event(move |_, _| {
+            panes.set_layout(Layout::SideBySide);
+            if let Err(why) = Layout::SideBySide.save() {
+                eprintln!("unable to save the layout: {}", why);
             }
+            Inhibit(true)
         });
     }
+
+    /// Opens the history of the current document when the history button is clicked. Untitled
+    /// documents must be saved first, as their history is kept by their path.
+    fn history_events(&self, saver: Saver) {
+        let window = self.window.clone();
+        let editor = self.content.source.buff.clone();
+        self.header.history.connect_clicked(move |_| {
+            let (parent, editor) = (window.clone(), editor.clone());
+            let current_file = saver.current_file.clone();
+            document_dir(&window, &saver, move |_| {
+                let path = match *current_file.read().unwrap() {
+                    Some(ref file) => file.get_path().to_path_buf(),
+                    None => return,
+                };
+
+                match History::for_document(&path) {
+                    Some(history) => browse(&parent, &editor, history),
+                    None => eprintln!("unable to locate the configuration directory"),
+                }
+            });
+        });
+    }
+
+    /// Marks the lines that have changed since the repository's HEAD when the document is within
+    /// a git repository, displays the checked out branch, and commits the document.
+    fn git_events(&self, saver: Saver) {
+        let current_file = saver.current_file.clone();
+        let repo: Arc<RwLock<Option<Repo>>> = Arc::new(RwLock::new(None));
+        // Incremented whenever the document is compared, so that earlier comparisons which
+        // finish afterwards are discarded.
+        let generation = Arc::new(AtomicUsize::new(0));
+
+        // Discovers the repository of the current file whenever a different file is opened or
+        // saved, or rereads HEAD if it has moved, and then marks the lines that have changed.
+        // The document is compared with HEAD on a background thread, as large documents take
+        // a while to compare.
+        let refresh = {
+            let editor = self.content.source.buff.clone();
+            let gutter = self.content.git.clone();
+            let commit = self.header.commit.clone();
+            let status = saver.status.clone();
+            let current_file = current_file.clone();
+            let repo = repo.clone();
+            move || {
+                let path = match *current_file.read().unwrap() {
+                    Some(ref file) => file.get_path().to_path_buf(),
+                    None => return,
+                };
+
+                let mut repo = repo.write().unwrap();
+                if !repo.as_ref().map_or(false, |repo| repo.contains(&path)) {
+                    *repo = Repo::open(&path);
+                    commit.set_visible(repo.is_some());
+                } else if let Some(ref mut repo) = *repo {
+                    repo.reload();
+                }
+                status.set_branch(&path, repo.as_ref().and_then(|repo| repo.branch()));
+
+                let (head, markdown) = match (repo.as_ref(), get_buffer(&editor)) {
+                    (Some(repo), Some(markdown)) => (repo.head().map(str::to_owned), markdown),
+                    _ => return gutter.clear(&editor),
+                };
+
+                let current = generation.fetch_add(1, Ordering::SeqCst) + 1;
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let head = head.as_ref().map(|head| head.as_str());
+                    let _ = sender.send(changes(head, &markdown));
+                });
+
+                let (editor, gutter) = (editor.clone(), gutter.clone());
+                let generation = generation.clone();
+                gtk::timeout_add(50, move || match receiver.try_recv() {
+                    Err(TryRecvError::Empty) => Continue(true),
+                    Ok(found) => {
+                        if generation.load(Ordering::SeqCst) == current {
+                            gutter.update(&editor, &found);
+                        }
+                        Continue(false)
+                    }
+                    Err(TryRecvError::Disconnected) => Continue(false),
+                });
+            }
+        };
+        let refresh = Rc::new(refresh);
+
+        // The document is compared with HEAD once typing pauses.
+        {
+            let refresh = refresh.clone();
+            let delayed = debounced(GIT_DELAY, move || refresh());
+            self.content.source.buff.connect_changed(move |_| delayed());
+        }
+
+        {
+            let refresh = refresh.clone();
+            saver.connect_saved(move || refresh());
+        }
+
+        // Other programs may commit, or check out another branch, while the editor is in the
+        // background.
+        {
+            let refresh = refresh.clone();
+            self.window.connect_focus_in_event(move |_, _| {
+                refresh();
+                Inhibit(false)
+            });
+        }
+
+        // The document is saved before it is committed, as the file on disk is committed.
+        let window = self.window.clone();
+        self.header.commit.connect_clicked(move |_| {
+            let (window, repo, refresh) = (window.clone(), repo.clone(), refresh.clone());
+            saver.save(false, move |saved| {
+                if !saved {
+                    return;
+                }
+
+                // The repository is not locked while dialogs are displayed, as the gutter is
+                // refreshed whenever the window regains the focus.
+                let path = match *repo.read().unwrap() {
+                    Some(ref repo) => repo.relative_path().to_path_buf(),
+                    None => return,
+                };
+
+                let message = match commit_message(&window, &path) {
+                    Some(message) => message,
+                    None => return,
+                };
+
+                let committed = match *repo.write().unwrap() {
+                    Some(ref mut repo) => repo.commit(&message),
+                    None => return,
+                };
+
+                if let Err(why) = committed {
+                    eprintln!("unable to commit {}: {}", path.display(), why);
+                    show_error(&window, &format!("Unable to commit {}: {}", path.display(), why));
+                }
+                refresh();
+            });
+        });
+    }
+
+    /// Lints the document whenever it changes, and programs the list of problems.
+    fn lint_events(&self, current_file: Arc<RwLock<Option<ActiveMetadata>>>) {
+        let problems = self.content.problems.clone();
+        let status = self.content.status.clone();
+        connect_changed_debounced(&self.content.source.buff, move |editor| {
+            if let Some(markdown) = get_buffer(&editor) {
+                // Relative paths are resolved against the directory of the current file.
+                let dir = current_file.read().unwrap().as_ref().and_then(|file| file.get_dir());
+                let diagnostics = lint(&markdown, dir.as_ref().map(|dir| dir.as_path()));
+                problems.update(editor, &diagnostics);
+                status.set_problems(diagnostics.len());
+            }
+        });
+
+        let panel = self.content.problems.container.clone();
+        self.content.status.problems.connect_toggled(move |button| {
+            panel.set_reveal_child(button.get_active());
+        });
+
+        let editor = self.content.source.buff.clone();
+        let view = self.content.source.view.clone();
+        self.content.problems.view.connect_row_activated(move |tree, path, _| {
+            let model = match tree.get_model() {
+                Some(model) => model,
+                None => return,
+            };
+
+            if let Some(iter) = model.get_iter(path) {
+                let line = model.get_value(&iter, PROBLEM_LINE as i32).get::<i32>();
+                jump_to_line(&view, &editor, line.unwrap_or(0));
+            }
+        });
+    }
+
+    /// Programs the editor to store pasted and dropped images beside the document, inserting
+    /// links to them into the document.
+    fn image_events(&self, saver: Saver) {
+        {
+            let window = self.window.clone();
+            let editor = self.content.source.buff.clone();
+            let saver = saver.clone();
+            self.content.source.view.connect_paste_clipboard(move |view| {
+                let clipboard = view.get_clipboard(&SELECTION_CLIPBOARD);
+                if !clipboard.wait_is_image_available() {
+                    return;
+                }
+
+                // The text view would otherwise try to paste the image as text.
+                signal_stop_emission_by_name(view, "paste-clipboard");
+
+                let image = match clipboard.wait_for_image() {
+                    Some(image) => image,
+                    None => return,
+                };
+
+                let (parent, editor) = (window.clone(), editor.clone());
+                document_dir(&window, &saver, move |dir| {
+                    paste_image(&parent, &editor, &dir, &image);
+                });
+            });
+        }
+
+        // Accept files that are dragged onto the editor, in addition to text.
+        self.content.source.view.drag_dest_add_uri_targets();
+
+        let window = self.window.clone();
+        let editor = self.content.source.buff.clone();
+        self.content.source.view.connect_drag_data_received(
+            move |view, context, x, y, data, _, time| {
+                let images: Vec<PathBuf> = data
+                    .get_uris()
+                    .iter()
+                    .filter_map(|uri| file_uri_to_path(uri))
+                    .filter(|path| is_image(path))
+                    .collect();
+
+                if images.is_empty() {
+                    return;
+                }
+
+                // The text view would otherwise insert the URIs of the images as text.
+                signal_stop_emission_by_name(view, "drag-data-received");
+                context.drag_finish(true, false, time);
+
+                // The position is remembered, as the document may be saved before the images
+                // are inserted.
+                let (x, y) = view.window_to_buffer_coords(TextWindowType::Widget, x, y);
+                let offset = view
+                    .get_iter_at_location(x, y)
+                    .unwrap_or_else(|| editor.get_end_iter())
+                    .get_offset();
+
+                let (parent, editor) = (window.clone(), editor.clone());
+                document_dir(&window, &saver, move |dir| {
+                    let mut iter = editor.get_iter_at_offset(offset);
+                    insert_images(&parent, &editor, &mut iter, &dir, &images);
+                });
+            },
+        );
+    }
+}
+
+/// The lines of the buffer that are visible within the view.
+fn visible_lines(view: &View) -> (usize, usize) {
+    let rect = view.get_visible_rect();
+    let first = view.get_line_at_y(rect.y).0.get_line();
+    let last = view.get_line_at_y(rect.y + rect.height).0.get_line();
+    (first.max(0) as usize, last.max(0) as usize)
+}
+
+/// The documents of the open folder, which wiki links are resolved against.
+fn notes(folder: &Option<(PathBuf, Vec<PathBuf>)>) -> Option<Notes> {
+    folder.as_ref().map(|&(ref folder, ref files)| Notes { folder, files })
+}
+
+/// Whether the buffer holds a large document, which is counted in characters so that its text
+/// need not be copied out of the buffer.
+fn is_large(buffer: &Buffer) -> bool { buffer.get_char_count() as usize > LARGE_DOCUMENT }
+
+/// Invokes the callback whenever the buffer changes. Large documents are only handled once
+/// typing pauses, as each of their changes would otherwise copy and scan the whole text.
+fn connect_changed_debounced<F: Fn(&Buffer) + 'static>(buffer: &Buffer, callback: F) {
+    let callback = Rc::new(callback);
+    let delayed = {
+        let (buffer, callback) = (buffer.clone(), callback.clone());
+        debounced(LARGE_DOCUMENT_DELAY, move || callback(&buffer))
+    };
+
+    buffer.connect_changed(move |editor| if is_large(editor) {
+        delayed();
+    } else {
+        callback(editor);
+    });
 }
@@ -0,0 +1,88 @@
+use super::misc::escape_markup;
+use gtk::*;
+use std::path::Path;
+use wiki::Backlink;
+
+/// The column within the backlinks' store that holds the markup of each linking line.
+const MARKUP: u32 = 0;
+/// The column within the backlinks' store that holds the full path of each linking document.
+pub const PATH: u32 = 1;
+/// The column within the backlinks' store that holds each link's line number.
+pub const LINE: u32 = 2;
+
+/// A collapsible sidebar which lists the lines of other documents that link to the current
+/// document with a wiki link.
+#[derive(Clone)]
+pub struct Backlinks {
+    pub container: Revealer,
+    pub view:      TreeView,
+    pub store:     ListStore,
+    pub status:    Label,
+}
+
+impl Backlinks {
+    pub fn new() -> Backlinks {
+        let store = ListStore::new(&[Type::String, Type::String, Type::I32]);
+        let view = TreeView::new_with_model(&store);
+        view.set_headers_visible(false);
+        view.set_activate_on_single_click(true);
+
+        let column = TreeViewColumn::new();
+        let cell = CellRendererText::new();
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "markup", MARKUP as i32);
+        view.append_column(&column);
+
+        let scroller = ScrolledWindow::new(None, None);
+        scroller.set_size_request(240, -1);
+        scroller.add(&view);
+
+        let status = Label::new(None);
+        status.set_halign(Align::Start);
+        status.set_margin_start(6);
+
+        let sidebar = Box::new(Orientation::Vertical, 6);
+        sidebar.pack_start(&status, false, false, 0);
+        sidebar.pack_start(&scroller, true, true, 0);
+
+        // The sidebar slides in from the right, and is hidden by default.
+        let container = Revealer::new();
+        container.set_transition_type(RevealerTransitionType::SlideLeft);
+        container.set_reveal_child(false);
+        container.add(&sidebar);
+
+        Backlinks { container, view, store, status }
+    }
+
+    /// Replaces the contents of the sidebar with the given backlinks, whose paths are
+    /// relative to the folder.
+    pub fn update(&self, folder: &Path, backlinks: &[Backlink]) {
+        self.store.clear();
+        for backlink in backlinks {
+            let markup = format!(
+                "<b>{}</b>:{}\n<small>{}</small>",
+                escape_markup(&backlink.path.to_string_lossy()),
+                backlink.line + 1,
+                escape_markup(&backlink.text)
+            );
+            let path = folder.join(&backlink.path).to_string_lossy().into_owned();
+            self.store.insert_with_values(
+                None,
+                &[MARKUP, PATH, LINE],
+                &[&markup, &path, &backlink.line],
+            );
+        }
+
+        self.status.set_text(&match backlinks.len() {
+            0 => "No backlinks".to_owned(),
+            1 => "1 backlink".to_owned(),
+            count => format!("{} backlinks", count),
+        });
+    }
+
+    /// Empties the sidebar, such as when no folder is open.
+    pub fn clear(&self, reason: &str) {
+        self.store.clear();
+        self.status.set_text(reason);
+    }
+}
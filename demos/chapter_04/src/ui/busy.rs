@@ -0,0 +1,49 @@
+use super::Header;
+use gtk::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Indicates that files are being read or written. The header bar's spinner spins, and the
+/// buttons which would read or write another file are insensitive, until every task is done.
+#[derive(Clone)]
+pub struct Busy {
+    spinner: Spinner,
+    opening: Box,
+    saving:  Box,
+    tasks:   Rc<Cell<usize>>,
+}
+
+impl Busy {
+    pub fn new(header: &Header) -> Busy {
+        Busy {
+            spinner: header.spinner.clone(),
+            opening: header.opening.clone(),
+            saving:  header.saving.clone(),
+            tasks:   Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub fn is_busy(&self) -> bool { self.tasks.get() != 0 }
+
+    /// Marks the beginning of a task, which must be ended with `end`.
+    pub fn begin(&self) {
+        if self.tasks.get() == 0 {
+            self.opening.set_sensitive(false);
+            self.saving.set_sensitive(false);
+            self.spinner.show();
+            self.spinner.start();
+        }
+        self.tasks.set(self.tasks.get() + 1);
+    }
+
+    pub fn end(&self) {
+        let tasks = self.tasks.get().saturating_sub(1);
+        self.tasks.set(tasks);
+        if tasks == 0 {
+            self.opening.set_sensitive(true);
+            self.saving.set_sensitive(true);
+            self.spinner.stop();
+            self.spinner.hide();
+        }
+    }
+}
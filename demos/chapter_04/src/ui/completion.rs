@@ -0,0 +1,349 @@
+use super::misc::get_buffer;
+use completion::{proposals, trigger, Proposal, Provider, Trigger};
+use glib::translate::{from_glib_none, ToGlibPtr};
+use gtk;
+use gtk::*;
+use gtk_source_sys::{GtkSourceCompletion, GtkSourceView};
+use gtk_sys::GtkTextIter;
+use sourceview::*;
+use state::ActiveMetadata;
+use std::cell::Cell;
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::{Arc, RwLock};
+
+/// The key under which each provider's `Source` is attached to it.
+const SOURCE: &[u8] = b"cmark-editor-source\0";
+
+/// Providers may be activated as the user types, and by pressing Ctrl+Space.
+const ACTIVATION: c_uint = 1 | 2;
+
+/// Adds a completion provider to the view for each kind of completion: heading anchors, file
+/// paths relative to the document, reference labels, emoji shortcodes, and the names of the
+/// open folder's documents. Each is activated by the text before the cursor, as described by
+/// `completion::trigger`.
+pub fn add_providers(
+    view: &View,
+    current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+    folder: Arc<RwLock<Option<(PathBuf, Vec<PathBuf>)>>>,
+) {
+    let completion = match view.get_completion() {
+        Some(completion) => completion,
+        None => return,
+    };
+
+    let providers = [
+        (Provider::Anchor, "Headings"),
+        (Provider::Path, "Files"),
+        (Provider::Reference, "References"),
+        (Provider::Emoji, "Emoji"),
+        (Provider::Note, "Notes"),
+    ];
+
+    for &(provider, name) in &providers {
+        let source = Source {
+            name: CString::new(name).unwrap_or_default(),
+            provider,
+            view: view.clone(),
+            current_file: current_file.clone(),
+            folder: folder.clone(),
+        };
+
+        let completion: *mut GtkSourceCompletion = completion.to_glib_none().0;
+        unsafe {
+            let object = new_provider(source);
+            let mut error = ptr::null_mut();
+            if gtk_source_completion_add_provider(completion as Pointer, object, &mut error) == 0 {
+                eprintln!("unable to add the {} completion provider", name);
+                g_error_free(error);
+            }
+            // The completion holds its own reference to the provider.
+            g_object_unref(object);
+        }
+    }
+}
+
+/// What a provider completes, and where its proposals are collected from.
+struct Source {
+    name:         CString,
+    provider:     Provider,
+    view:         View,
+    current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+    folder:       Arc<RwLock<Option<(PathBuf, Vec<PathBuf>)>>>,
+}
+
+impl Source {
+    /// The trigger before the cursor, if it is one that this provider completes.
+    fn trigger(&self, cursor: &TextIter) -> Option<Trigger> {
+        let mut line_start = cursor.clone();
+        line_start.set_line_offset(0);
+        let before = line_start.get_text(cursor).unwrap_or_default();
+        trigger(&before).and_then(|trigger| if trigger.provider == self.provider {
+            Some(trigger)
+        } else {
+            None
+        })
+    }
+
+    /// Collects the proposals for the trigger. The whole document is only read when its
+    /// headings or reference definitions are proposed.
+    fn proposals(&self, trigger: &Trigger, cursor: &TextIter) -> Vec<Proposal> {
+        let markdown = match trigger.provider {
+            Provider::Anchor | Provider::Reference => {
+                let buffer = cursor.get_buffer().and_then(|buffer| buffer.downcast().ok());
+                buffer.and_then(|buffer: Buffer| get_buffer(&buffer))
+            }
+            _ => None,
+        };
+
+        let dir = self.current_file.read().unwrap().as_ref().and_then(|file| file.get_dir());
+        let folder = self.folder.read().unwrap();
+        let notes = folder.as_ref().map_or(&[][..], |&(_, ref files)| files.as_slice());
+        proposals(trigger, &markdown.unwrap_or_default(), dir.as_ref().map(|d| d.as_path()), notes)
+    }
+}
+
+// GTK's completion only accepts providers which implement the GtkSourceCompletionProvider
+// interface, and gtk-rs can not yet implement interfaces, so a GObject type which implements
+// it is registered by hand.
+
+type Pointer = *mut c_void;
+type GType = usize;
+
+#[repr(C)]
+struct TypeQuery {
+    type_:         GType,
+    type_name:     *const c_char,
+    class_size:    c_uint,
+    instance_size: c_uint,
+}
+
+#[repr(C)]
+struct InterfaceInfo {
+    init:     Option<unsafe extern "C" fn(Pointer, Pointer)>,
+    finalize: Pointer,
+    data:     Pointer,
+}
+
+/// The virtual functions of GtkSourceCompletionProviderIface. Those which are left alone keep
+/// their default implementations.
+#[repr(C)]
+struct ProviderInterface {
+    parent:                [GType; 2],
+    get_name:              Option<unsafe extern "C" fn(Pointer) -> *mut c_char>,
+    get_icon:              Pointer,
+    get_icon_name:         Pointer,
+    get_gicon:             Pointer,
+    populate:              Option<unsafe extern "C" fn(Pointer, Pointer)>,
+    matches:               Option<unsafe extern "C" fn(Pointer, Pointer) -> c_int>,
+    get_activation:        Option<unsafe extern "C" fn(Pointer) -> c_uint>,
+    get_info_widget:       Pointer,
+    update_info:           Pointer,
+    get_start_iter:
+        Option<unsafe extern "C" fn(Pointer, Pointer, Pointer, *mut GtkTextIter) -> c_int>,
+    activate_proposal:
+        Option<unsafe extern "C" fn(Pointer, Pointer, *mut GtkTextIter) -> c_int>,
+    get_interactive_delay: Pointer,
+    get_priority:          Pointer,
+}
+
+extern "C" {
+    fn g_object_get_type() -> GType;
+    fn g_type_query(type_: GType, query: *mut TypeQuery);
+    fn g_type_register_static_simple(
+        parent: GType,
+        name: *const c_char,
+        class_size: c_uint,
+        class_init: Pointer,
+        instance_size: c_uint,
+        instance_init: Pointer,
+        flags: c_uint,
+    ) -> GType;
+    fn g_type_add_interface_static(type_: GType, interface: GType, info: *const InterfaceInfo);
+    fn g_object_new(type_: GType, first_property: *const c_char, ...) -> Pointer;
+    fn g_object_unref(object: Pointer);
+    fn g_object_set_data_full(
+        object: Pointer,
+        key: *const c_char,
+        data: Pointer,
+        destroy: Option<unsafe extern "C" fn(Pointer)>,
+    );
+    fn g_object_get_data(object: Pointer, key: *const c_char) -> Pointer;
+    fn g_signal_emit_by_name(instance: Pointer, signal: *const c_char, ...);
+    fn g_strdup(text: *const c_char) -> *mut c_char;
+    fn g_free(memory: Pointer);
+    fn g_error_free(error: Pointer);
+    fn g_list_append(list: Pointer, data: Pointer) -> Pointer;
+    fn g_list_free_full(list: Pointer, free: Option<unsafe extern "C" fn(Pointer)>);
+
+    fn gtk_source_completion_provider_get_type() -> GType;
+    fn gtk_source_completion_add_provider(
+        completion: Pointer,
+        provider: Pointer,
+        error: *mut Pointer,
+    ) -> c_int;
+    fn gtk_source_completion_context_get_iter(context: Pointer, iter: *mut GtkTextIter) -> c_int;
+    fn gtk_source_completion_context_add_proposals(
+        context: Pointer,
+        provider: Pointer,
+        proposals: Pointer,
+        finished: c_int,
+    );
+    fn gtk_source_completion_item_new(
+        label: *const c_char,
+        text: *const c_char,
+        icon: Pointer,
+        info: *const c_char,
+    ) -> Pointer;
+    fn gtk_source_completion_proposal_get_text(proposal: Pointer) -> *mut c_char;
+}
+
+/// Registers the provider type the first time that it is needed.
+fn provider_type() -> GType {
+    thread_local!(static PROVIDER_TYPE: Cell<GType> = Cell::new(0));
+    PROVIDER_TYPE.with(|registered| {
+        if registered.get() == 0 {
+            unsafe {
+                let mut query: TypeQuery = mem::zeroed();
+                g_type_query(g_object_get_type(), &mut query);
+                let id = g_type_register_static_simple(
+                    g_object_get_type(),
+                    b"CmarkEditorCompletionProvider\0".as_ptr() as *const c_char,
+                    query.class_size,
+                    ptr::null_mut(),
+                    query.instance_size,
+                    ptr::null_mut(),
+                    0,
+                );
+                let info = InterfaceInfo {
+                    init:     Some(init_interface),
+                    finalize: ptr::null_mut(),
+                    data:     ptr::null_mut(),
+                };
+                g_type_add_interface_static(id, gtk_source_completion_provider_get_type(), &info);
+                registered.set(id);
+            }
+        }
+        registered.get()
+    })
+}
+
+/// Creates a provider which owns the source, and returns a reference to it.
+unsafe fn new_provider(source: Source) -> Pointer {
+    let object = g_object_new(provider_type(), ptr::null());
+    let source = Box::into_raw(Box::new(source)) as Pointer;
+    g_object_set_data_full(object, SOURCE.as_ptr() as *const c_char, source, Some(free_source));
+    object
+}
+
+unsafe extern "C" fn free_source(source: Pointer) { drop(Box::from_raw(source as *mut Source)); }
+
+unsafe fn source<'a>(provider: Pointer) -> &'a Source {
+    &*(g_object_get_data(provider, SOURCE.as_ptr() as *const c_char) as *const Source)
+}
+
+/// The position within the buffer that completion was requested at.
+unsafe fn context_iter(context: Pointer) -> TextIter {
+    let mut iter: GtkTextIter = mem::zeroed();
+    gtk_source_completion_context_get_iter(context, &mut iter);
+    from_glib_none(&iter as *const GtkTextIter)
+}
+
+unsafe extern "C" fn init_interface(interface: Pointer, _: Pointer) {
+    let interface = &mut *(interface as *mut ProviderInterface);
+    interface.get_name = Some(get_name);
+    interface.populate = Some(populate);
+    interface.matches = Some(matches);
+    interface.get_activation = Some(get_activation);
+    interface.get_start_iter = Some(get_start_iter);
+    interface.activate_proposal = Some(activate_proposal);
+}
+
+unsafe extern "C" fn get_name(provider: Pointer) -> *mut c_char {
+    g_strdup(source(provider).name.as_ptr())
+}
+
+unsafe extern "C" fn get_activation(_: Pointer) -> c_uint { ACTIVATION }
+
+unsafe extern "C" fn matches(provider: Pointer, context: Pointer) -> c_int {
+    source(provider).trigger(&context_iter(context)).is_some() as c_int
+}
+
+unsafe extern "C" fn populate(provider: Pointer, context: Pointer) {
+    let source = source(provider);
+    let cursor = context_iter(context);
+    let found = source.trigger(&cursor).map_or(Vec::new(), |t| source.proposals(&t, &cursor));
+
+    let mut list = ptr::null_mut();
+    for proposal in found {
+        let label = CString::new(proposal.label).unwrap_or_default();
+        let text = CString::new(proposal.text).unwrap_or_default();
+        let (icon, info) = (ptr::null_mut(), ptr::null());
+        let item = gtk_source_completion_item_new(label.as_ptr(), text.as_ptr(), icon, info);
+        list = g_list_append(list, item);
+    }
+
+    gtk_source_completion_context_add_proposals(context, provider, list, 1);
+    g_list_free_full(list, Some(g_object_unref));
+}
+
+/// Moves the iter to the start of the text that proposals replace.
+unsafe extern "C" fn get_start_iter(
+    provider: Pointer,
+    context: Pointer,
+    _: Pointer,
+    iter: *mut GtkTextIter,
+) -> c_int {
+    let cursor = context_iter(context);
+    match (cursor.get_buffer(), source(provider).trigger(&cursor)) {
+        (Some(buffer), Some(trigger)) => {
+            let start = buffer.get_iter_at_line_index(cursor.get_line(), trigger.start as i32);
+            let start: *const GtkTextIter = start.to_glib_none().0;
+            *iter = *start;
+            1
+        }
+        _ => 0,
+    }
+}
+
+/// Replaces the text which is being completed with the proposal, as a single undoable action.
+/// Choosing a directory continues to complete the files within it.
+unsafe extern "C" fn activate_proposal(
+    provider: Pointer,
+    proposal: Pointer,
+    iter: *mut GtkTextIter,
+) -> c_int {
+    let source = source(provider);
+    let cursor: TextIter = from_glib_none(iter as *const GtkTextIter);
+    let (buffer, trigger) = match (cursor.get_buffer(), source.trigger(&cursor)) {
+        (Some(buffer), Some(trigger)) => (buffer, trigger),
+        _ => return 0,
+    };
+
+    let text = gtk_source_completion_proposal_get_text(proposal);
+    if text.is_null() {
+        return 0;
+    }
+    let inserted = CStr::from_ptr(text).to_string_lossy().into_owned();
+    g_free(text as Pointer);
+
+    let mut start = buffer.get_iter_at_line_index(cursor.get_line(), trigger.start as i32);
+    let mut end = cursor.clone();
+    buffer.begin_user_action();
+    buffer.delete(&mut start, &mut end);
+    buffer.insert(&mut start, &inserted);
+    buffer.end_user_action();
+
+    if inserted.ends_with('/') {
+        let view = source.view.clone();
+        gtk::idle_add(move || {
+            let view: *mut GtkSourceView = view.to_glib_none().0;
+            g_signal_emit_by_name(view as Pointer, b"show-completion\0".as_ptr() as *const c_char);
+            Continue(false)
+        });
+    }
+    1
+}
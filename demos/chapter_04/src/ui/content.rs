@@ -1,10 +1,23 @@
+use super::{Backlinks, Diagnostics, GitGutter, Outline, Panes, ProjectTree, SearchPanel,
+            SpellChecker, StatusBar};
 use gtk::*;
 use pango::*;
 use sourceview::*;
 use webkit2gtk::*;
 
+/// The column at which the right margin is displayed, and at which paragraphs are wrapped.
+pub const RIGHT_MARGIN: u32 = 100;
+
 pub struct Content {
-    pub container: Paned,
+    pub container: Box,
+    pub panes:     Panes,
+    pub project:   ProjectTree,
+    pub outline:   Outline,
+    pub backlinks: Backlinks,
+    pub problems:  Diagnostics,
+    pub search:    SearchPanel,
+    pub git:       GitGutter,
+    pub status:    StatusBar,
     pub source:    Source,
     pub preview:   WebView,
 }
@@ -13,27 +26,64 @@ pub struct Source {
     pub container: ScrolledWindow,
     pub view:      View,
     pub buff:      Buffer,
+    pub spell:     SpellChecker,
 }
 
 impl Content {
     pub fn new() -> Content {
-        // Create the Paned container for the main content
-        let container = Paned::new(Orientation::Horizontal);
         let source = Source::new();
 
+        // Create the sidebars which list the files of the open folder, and the headings of the
+        // document.
+        let project = ProjectTree::new();
+        let outline = Outline::new();
+        // Create the sidebar which lists the documents that link to this one.
+        let backlinks = Backlinks::new();
+
         // Create a the WebView for the preview pane.
         let context = WebContext::get_default().unwrap();
         let preview = WebView::new_with_context(&context);
 
-        // Pack it in
-        container.pack1(&source.container, true, true);
-        container.pack2(&preview, true, true);
+        // Pack them into the Paned container, which arranges them according to the layout.
+        let panes = Panes::new(&source.container, &preview);
+
+        // Place the sidebars of the folder and outline to the left of the editor and preview,
+        // and the sidebar of backlinks to their right.
+        let body = Box::new(Orientation::Horizontal, 0);
+        body.pack_start(&project.container, false, false, 0);
+        body.pack_start(&outline.container, false, false, 0);
+        body.pack_start(&panes.paned, true, true, 0);
+        body.pack_start(&backlinks.container, false, false, 0);
+
+        // Place the list of problems, the results of searching the folder, and then the status
+        // bar, beneath everything else.
+        let problems = Diagnostics::new(&source.view, &source.buff);
+        let search = SearchPanel::new();
+        let git = GitGutter::new(&source.view);
+        let status = StatusBar::new();
+        let container = Box::new(Orientation::Vertical, 0);
+        container.pack_start(&body, true, true, 0);
+        container.pack_start(&problems.container, false, false, 0);
+        container.pack_start(&search.container, false, false, 0);
+        container.pack_start(&status.container, false, false, 0);
 
         // Ensure that the two panes get half the size of the paned container.
         source.container.set_size_request(100, -1);
         preview.set_size_request(100, -1);
 
-        Content { container, source, preview }
+        Content {
+            container,
+            panes,
+            project,
+            outline,
+            backlinks,
+            problems,
+            search,
+            git,
+            status,
+            source,
+            preview,
+        }
     }
 }
 
@@ -47,7 +97,10 @@ impl Source {
 
         configure_source_view(&view, &buff);
 
-        Source { container, buff, view }
+        // Misspelled words will be underlined once a dictionary has been selected.
+        let spell = SpellChecker::new(&buff);
+
+        Source { container, buff, view, spell }
     }
 }
 
@@ -70,9 +123,8 @@ fn configure_source_view(view: &View, buff: &Buffer) {
     view.set_indent_width(4);
     view.set_smart_backspace(true);
     view.set_right_margin(100);
+    view.set_right_margin_position(RIGHT_MARGIN);
     view.set_left_margin(10);
     view.set_show_right_margin(true);
     view.set_background_pattern(BackgroundPatternType::Grid);
-    // TODO: Next GTK Crate Release
-    // view.set_input_hints(InputHints::SPELLCHECK + InputHints::WORD_COMPLETION);
-}
\ No newline at end of file
+}
@@ -0,0 +1,108 @@
+use gtk::*;
+use lint::{Diagnostic, Severity};
+use pango::Underline;
+use sourceview::*;
+
+/// The column within the diagnostics' store that holds the name of each problem's icon.
+const ICON: u32 = 0;
+/// The column within the diagnostics' store that holds each problem's line number.
+pub const LINE: u32 = 1;
+/// The column within the diagnostics' store that holds each problem's description.
+const MESSAGE: u32 = 2;
+
+/// Displays the problems found by the linter as underlines and gutter marks within the
+/// editor, and as a list within a panel beneath the editor.
+#[derive(Clone)]
+pub struct Diagnostics {
+    pub container: Revealer,
+    pub view:      TreeView,
+    pub store:     ListStore,
+    pub warning:   TextTag,
+    pub error:     TextTag,
+}
+
+impl Diagnostics {
+    pub fn new(source: &View, buff: &Buffer) -> Diagnostics {
+        let store = ListStore::new(&[Type::String, Type::I32, Type::String]);
+        let view = TreeView::new_with_model(&store);
+        view.set_headers_visible(false);
+        view.set_activate_on_single_click(true);
+
+        // Each row displays the icon of its severity, followed by its description.
+        let column = TreeViewColumn::new();
+        let icon = CellRendererPixbuf::new();
+        let text = CellRendererText::new();
+        column.pack_start(&icon, false);
+        column.pack_start(&text, true);
+        column.add_attribute(&icon, "icon-name", ICON as i32);
+        column.add_attribute(&text, "text", MESSAGE as i32);
+        view.append_column(&column);
+
+        let scroller = ScrolledWindow::new(None, None);
+        scroller.set_size_request(-1, 120);
+        scroller.add(&view);
+
+        // The panel slides up from the status bar, and is hidden by default.
+        let container = Revealer::new();
+        container.set_transition_type(RevealerTransitionType::SlideUp);
+        container.set_reveal_child(false);
+        container.add(&scroller);
+
+        // Problems are marked with an icon in the gutter beside their line.
+        source.set_show_line_marks(true);
+        for &(severity, priority) in &[(Severity::Warning, 1), (Severity::Error, 2)] {
+            let attributes = MarkAttributes::new();
+            attributes.set_icon_name(severity.icon_name());
+            source.set_mark_attributes(category(severity), &attributes, priority);
+        }
+
+        // Warnings are underlined with a single line, and errors with a wavy line.
+        let warning = TextTag::new(Some(category(Severity::Warning)));
+        let error = TextTag::new(Some(category(Severity::Error)));
+        let _ = warning.set_property("underline", &Underline::Single.to_value());
+        let _ = error.set_property("underline", &Underline::Error.to_value());
+        if let Some(table) = buff.get_tag_table() {
+            table.add(&warning);
+            table.add(&error);
+        }
+
+        Diagnostics { container, view, store, warning, error }
+    }
+
+    /// Replaces the problems that are displayed with the given diagnostics.
+    pub fn update(&self, buffer: &Buffer, diagnostics: &[Diagnostic]) {
+        let (start, end) = (buffer.get_start_iter(), buffer.get_end_iter());
+        buffer.remove_tag(&self.warning, &start, &end);
+        buffer.remove_tag(&self.error, &start, &end);
+        buffer.remove_source_marks(&start, &end, Some(category(Severity::Warning)));
+        buffer.remove_source_marks(&start, &end, Some(category(Severity::Error)));
+        self.store.clear();
+
+        for diagnostic in diagnostics {
+            let from = buffer.get_iter_at_line_index(diagnostic.line, diagnostic.start as i32);
+            let to = buffer.get_iter_at_line_index(diagnostic.line, diagnostic.end as i32);
+            let tag = match diagnostic.severity {
+                Severity::Warning => &self.warning,
+                Severity::Error => &self.error,
+            };
+
+            buffer.apply_tag(tag, &from, &to);
+            buffer.create_source_mark(None, category(diagnostic.severity), &from);
+
+            let message = format!("Line {}: {}", diagnostic.line + 1, diagnostic.message);
+            self.store.insert_with_values(
+                None,
+                &[ICON, LINE, MESSAGE],
+                &[&diagnostic.severity.icon_name(), &diagnostic.line, &message],
+            );
+        }
+    }
+}
+
+/// The mark category, and tag name, of each severity.
+fn category(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "lint-warning",
+        Severity::Error => "lint-error",
+    }
+}
@@ -1,72 +1,235 @@
 use gtk::*;
-use std::path::PathBuf;
+use project::{markdown_patterns, with_markdown_extension};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
-/// A wrapped FileChooserDialog that automatically destroys itself upon being dropped.
-pub struct OpenDialog(FileChooserDialog);
+/// A dialog which asks the user to choose a file or folder.
+pub trait FileDialog {
+    /// Runs the dialog, returning the chosen path, or **None** if the dialog was canceled.
+    fn run(&self) -> Option<PathBuf>;
+}
+
+/// Creates the dialog which asks where a document is saved, given the folder that it begins
+/// within and the file name that it suggests. Dialogs are created through factories so that
+/// tests may substitute dialogs which choose a path without being displayed.
+pub type SaveDialogFactory = Rc<Fn(Option<PathBuf>, &str) -> Box<FileDialog>>;
 
-/// A wrapped FileChooserDialog that automatically destroys itself upon being dropped.
-pub struct SaveDialog(FileChooserDialog);
+/// Creates the dialog which asks for a file or folder to open, given the folder that it begins
+/// within.
+pub type OpenDialogFactory = Rc<Fn(Option<PathBuf>) -> Box<FileDialog>>;
+
+/// Chooses a file to open.
+pub struct OpenDialog(Chooser);
+
+/// Chooses the file that a document is saved as.
+pub struct SaveDialog {
+    chooser: Chooser,
+    parent:  Window,
+}
+
+/// Chooses a folder.
+pub struct FolderDialog(Chooser);
+
+/// The file chooser that a dialog is displayed with, which automatically destroys itself upon
+/// being dropped. Sandboxed installs, such as Flatpaks, use the desktop portal's native
+/// dialogs, as GTK's own dialogs can not see the host's files from within the sandbox.
+enum Chooser {
+    Dialog(FileChooserDialog),
+    Native(FileChooserNative),
+}
 
 impl OpenDialog {
-    pub fn new(path: Option<PathBuf>) -> OpenDialog {
+    pub fn new(parent: &Window, path: Option<PathBuf>) -> OpenDialog {
         // Create a new file chooser dialog for opening a file.
-        let open_dialog = FileChooserDialog::new(
-            Some("Open"),
-            Some(&Window::new(WindowType::Popup)),
-            FileChooserAction::Open,
-        );
-
-        // Add the cancel and open buttons to that dialog.
-        open_dialog.add_button("Cancel", ResponseType::Cancel.into());
-        open_dialog.add_button("Open", ResponseType::Ok.into());
+        let open_dialog = Chooser::new("Open", parent, FileChooserAction::Open, "Open");
+        add_filters(&open_dialog.file_chooser());
 
         // Set the default path to open this with.
-        path.map(|p| open_dialog.set_current_folder(p));
+        path.map(|p| open_dialog.file_chooser().set_current_folder(p));
 
         OpenDialog(open_dialog)
     }
 
-    pub fn run(&self) -> Option<PathBuf> {
-        if self.0.run() == ResponseType::Ok.into() {
-            self.0.get_filename()
-        } else {
-            None
-        }
+    /// Creates open dialogs which are displayed above the window.
+    pub fn factory(parent: &Window) -> OpenDialogFactory {
+        let parent = parent.clone();
+        Rc::new(move |path| -> Box<FileDialog> { Box::new(OpenDialog::new(&parent, path)) })
     }
 }
 
+impl FileDialog for OpenDialog {
+    fn run(&self) -> Option<PathBuf> { self.0.run() }
+}
+
 impl SaveDialog {
-    pub fn new(path: Option<PathBuf>) -> SaveDialog {
-        // Initializes a new save as dialog, which is embedded within a new popup window.
-        let save_dialog = FileChooserDialog::new(
-            Some("Save As"),
-            Some(&Window::new(WindowType::Popup)),
-            FileChooserAction::Save,
-        );
-
-        // Add the cancel and save buttons to that dialog.
-        save_dialog.add_button("Cancel", ResponseType::Cancel.into());
-        save_dialog.add_button("Save", ResponseType::Ok.into());
+    /// Creates a save as dialog, which suggests saving the document with the given name.
+    pub fn new(parent: &Window, path: Option<PathBuf>, name: &str) -> SaveDialog {
+        // Initializes a new save as dialog, which is transient for the main window.
+        let save_dialog = Chooser::new("Save As", parent, FileChooserAction::Save, "Save");
+        let chooser = save_dialog.file_chooser();
+        add_filters(&chooser);
+        chooser.set_do_overwrite_confirmation(true);
+
+        // Set the default path to open this with.
+        path.map(|p| chooser.set_current_folder(p));
+        chooser.set_current_name(name);
+
+        SaveDialog { chooser: save_dialog, parent: parent.clone() }
+    }
+
+    /// Creates save as dialogs which are displayed above the window.
+    pub fn factory(parent: &Window) -> SaveDialogFactory {
+        let parent = parent.clone();
+        Rc::new(move |path, name: &str| -> Box<FileDialog> {
+            Box::new(SaveDialog::new(&parent, path, name))
+        })
+    }
+}
+
+impl FileDialog for SaveDialog {
+    /// Runs the dialog until a file is chosen, or the dialog is canceled. File names without
+    /// an extension are given the `.md` extension.
+    fn run(&self) -> Option<PathBuf> {
+        loop {
+            let chosen = match self.chooser.run() {
+                Some(chosen) => chosen,
+                None => return None,
+            };
+
+            // The dialog has only confirmed overwriting the file that was chosen, and not the
+            // file that the extension was appended to. GTK's dialogs are still displayed, and
+            // so the confirmation is placed above them.
+            let path = with_markdown_extension(chosen.clone());
+            let parent = match self.chooser {
+                Chooser::Dialog(ref dialog) => dialog.clone().upcast::<Window>(),
+                Chooser::Native(_) => self.parent.clone(),
+            };
+            if path == chosen || !path.exists() || confirm_overwrite(&parent, &path) {
+                return Some(path);
+            }
+        }
+    }
+}
+
+impl FolderDialog {
+    pub fn new(parent: &Window, title: &str, accept: &str, path: Option<PathBuf>) -> FolderDialog {
+        // Create a new file chooser dialog for selecting a folder.
+        let folder_dialog = Chooser::new(title, parent, FileChooserAction::SelectFolder, accept);
 
         // Set the default path to open this with.
-        path.map(|p| save_dialog.set_current_folder(p));
+        path.map(|p| folder_dialog.file_chooser().set_current_folder(p));
+
+        FolderDialog(folder_dialog)
+    }
+
+    /// Creates folder dialogs with the title and accept button, which are displayed above the
+    /// window.
+    pub fn factory(parent: &Window, title: &str, accept: &str) -> OpenDialogFactory {
+        let (parent, title, accept) = (parent.clone(), title.to_owned(), accept.to_owned());
+        Rc::new(move |path| -> Box<FileDialog> {
+            Box::new(FolderDialog::new(&parent, &title, &accept, path))
+        })
+    }
+}
+
+impl FileDialog for FolderDialog {
+    fn run(&self) -> Option<PathBuf> { self.0.run() }
+}
+
+impl Chooser {
+    fn new(title: &str, parent: &Window, action: FileChooserAction, accept: &str) -> Chooser {
+        if use_portal() {
+            let native =
+                FileChooserNative::new(Some(title), Some(parent), action, Some(accept), None);
+            native.set_modal(true);
+            return Chooser::Native(native);
+        }
+
+        let dialog = FileChooserDialog::new(Some(title), Some(parent), action);
+
+        // Add the cancel and accept buttons to that dialog.
+        dialog.add_button("Cancel", ResponseType::Cancel.into());
+        dialog.add_button(accept, ResponseType::Ok.into());
+
+        Chooser::Dialog(dialog)
+    }
 
-        SaveDialog(save_dialog)
+    /// The file chooser interface, which both kinds of dialog implement.
+    fn file_chooser(&self) -> FileChooser {
+        match *self {
+            Chooser::Dialog(ref dialog) => dialog.clone().upcast(),
+            Chooser::Native(ref native) => native.clone().upcast(),
+        }
     }
 
-    pub fn run(&self) -> Option<PathBuf> {
-        if self.0.run() == ResponseType::Ok.into() {
-            self.0.get_filename()
+    fn run(&self) -> Option<PathBuf> {
+        let accepted = match *self {
+            Chooser::Dialog(ref dialog) => dialog.run() == ResponseType::Ok.into(),
+            Chooser::Native(ref native) => native.run() == ResponseType::Accept.into(),
+        };
+
+        if accepted {
+            self.file_chooser().get_filename()
         } else {
             None
         }
     }
 }
 
-impl Drop for OpenDialog {
-    fn drop(&mut self) { self.0.destroy(); }
+impl Drop for Chooser {
+    fn drop(&mut self) {
+        match *self {
+            Chooser::Dialog(ref dialog) => dialog.destroy(),
+            Chooser::Native(ref native) => native.destroy(),
+        }
+    }
+}
+
+/// Whether dialogs are displayed through the desktop portal, which is the case when the
+/// editor has been sandboxed by Flatpak, or when GTK has been asked to use the portal.
+fn use_portal() -> bool {
+    Path::new("/.flatpak-info").exists()
+        || env::var("GTK_USE_PORTAL").map(|value| value == "1").unwrap_or(false)
+}
+
+/// Allows the dialog to show only markdown documents, only text files, or every file. Markdown
+/// documents are shown by default.
+fn add_filters(chooser: &FileChooser) {
+    let markdown = FileFilter::new();
+    markdown.set_name(Some("Markdown Documents"));
+    for pattern in markdown_patterns() {
+        markdown.add_pattern(&pattern);
+    }
+    markdown.add_mime_type("text/markdown");
+
+    let text = FileFilter::new();
+    text.set_name(Some("Text Files"));
+    text.add_mime_type("text/plain");
+
+    let all = FileFilter::new();
+    all.set_name(Some("All Files"));
+    all.add_pattern("*");
+
+    chooser.add_filter(&markdown);
+    chooser.add_filter(&text);
+    chooser.add_filter(&all);
+    chooser.set_filter(&markdown);
 }
 
-impl Drop for SaveDialog {
-    fn drop(&mut self) { self.0.destroy(); }
+/// Asks whether the file, which already exists, should be replaced.
+fn confirm_overwrite(parent: &Window, path: &Path) -> bool {
+    let name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into());
+    let confirm = MessageDialog::new(
+        Some(parent),
+        DIALOG_MODAL,
+        MessageType::Question,
+        ButtonsType::None,
+        &format!("A file named “{}” already exists. Do you want to replace it?", name),
+    );
+    confirm.add_button("Cancel", ResponseType::Cancel.into());
+    confirm.add_button("Replace", ResponseType::Accept.into());
+    let response = confirm.run();
+    confirm.destroy();
+    response == ResponseType::Accept.into()
 }
@@ -0,0 +1,95 @@
+use super::Header;
+use document::{DocumentEvent, DocumentState};
+use gtk::*;
+use state::ActiveMetadata;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+
+/// Tracks the state of the document, which is displayed by the window's title and the header
+/// bar's save buttons whenever it changes. The header bar's subtitle displays the document's
+/// directory, and the branch of the repository which contains it.
+#[derive(Clone)]
+pub struct DocumentStatus {
+    state:        Rc<RefCell<DocumentState>>,
+    window:       Window,
+    headerbar:    HeaderBar,
+    save:         Button,
+    save_as:      Button,
+    current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+    /// The document that the branch was found for, and the branch that is checked out.
+    branch:       Rc<RefCell<Option<(PathBuf, String)>>>,
+}
+
+impl DocumentStatus {
+    pub fn new(
+        window: &Window,
+        header: &Header,
+        current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+    ) -> DocumentStatus {
+        let status = DocumentStatus {
+            state: Rc::new(RefCell::new(DocumentState::default())),
+            window: window.clone(),
+            headerbar: header.container.clone(),
+            save: header.save.clone(),
+            save_as: header.save_as.clone(),
+            current_file,
+            branch: Rc::new(RefCell::new(None)),
+        };
+        status.display();
+        status
+    }
+
+    pub fn get(&self) -> DocumentState { self.state.borrow().clone() }
+
+    /// Moves the document into the state that follows the event.
+    pub fn update(&self, event: DocumentEvent) {
+        let next = self.state.borrow().next(event);
+        *self.state.borrow_mut() = next;
+        self.display();
+    }
+
+    /// Records the branch which is checked out within the repository that contains the
+    /// document at the path, or that the document is not within a repository.
+    pub fn set_branch(&self, path: &Path, branch: Option<String>) {
+        *self.branch.borrow_mut() = branch.map(|branch| (path.to_path_buf(), branch));
+        self.display();
+    }
+
+    fn display(&self) {
+        let state = self.state.borrow();
+        let path = match *self.current_file.read().unwrap() {
+            Some(ref file) if !state.is_untitled() => Some(file.get_path().to_path_buf()),
+            _ => None,
+        };
+        let name = path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map_or("Untitled".to_owned(), |name| name.to_string_lossy().into_owned());
+
+        let title = state.title(&name);
+        self.headerbar.set_title(title.as_str());
+        self.window.set_title(&title);
+
+        // The branch is only displayed once it has been found for this document.
+        let dir = path.as_ref().and_then(|path| path.parent());
+        let subtitle = match (dir, &*self.branch.borrow()) {
+            (Some(dir), &Some((ref document, ref branch))) if Some(document) == path.as_ref() => {
+                format!("{} — {}", dir.display(), branch)
+            }
+            (Some(dir), _) => dir.display().to_string(),
+            (None, _) => String::new(),
+        };
+        self.headerbar.set_subtitle(subtitle.as_str());
+
+        self.save.set_sensitive(state.can_save());
+        self.save_as.set_sensitive(state.can_save_as());
+        match *state {
+            DocumentState::Error { ref message, .. } => {
+                self.save.set_tooltip_text(Some(message.as_str()))
+            }
+            _ => self.save.set_tooltip_text(None),
+        }
+    }
+}
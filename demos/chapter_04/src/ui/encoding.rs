@@ -0,0 +1,147 @@
+use encoding::{decode, Decoded, Encoding, LineEnding, TextFormat};
+use gtk::*;
+use std::path::Path;
+
+/// Status bar menus which display the encoding and line endings of the document, and which
+/// convert the document to another encoding or line ending when it is next saved.
+#[derive(Clone)]
+pub struct FormatMenus {
+    pub encoding:     MenuButton,
+    pub encodings:    Vec<(MenuItem, Encoding)>,
+    pub bom:          CheckMenuItem,
+    pub line_ending:  MenuButton,
+    pub line_endings: Vec<(MenuItem, LineEnding)>,
+}
+
+impl FormatMenus {
+    pub fn new() -> FormatMenus {
+        let menu = Menu::new();
+        let mut encodings = Vec::new();
+        for &encoding in &Encoding::ALL {
+            let item = MenuItem::new_with_label(&format!("Convert to {}", encoding.name()));
+            menu.append(&item);
+            encodings.push((item, encoding));
+        }
+        menu.append(&SeparatorMenuItem::new());
+        let bom = CheckMenuItem::new_with_label("Byte Order Mark");
+        menu.append(&bom);
+        menu.show_all();
+
+        let encoding = MenuButton::new();
+        encoding.set_relief(ReliefStyle::None);
+        encoding.set_tooltip_text(Some("Encoding"));
+        encoding.set_popup(Some(&menu));
+
+        let menu = Menu::new();
+        let mut line_endings = Vec::new();
+        for &ending in &LineEnding::ALL {
+            let item = MenuItem::new_with_label(&format!("Convert to {}", ending.name()));
+            menu.append(&item);
+            line_endings.push((item, ending));
+        }
+        menu.show_all();
+
+        let line_ending = MenuButton::new();
+        line_ending.set_relief(ReliefStyle::None);
+        line_ending.set_tooltip_text(Some("Line Endings"));
+        line_ending.set_popup(Some(&menu));
+
+        let menus = FormatMenus { encoding, encodings, bom, line_ending, line_endings };
+        menus.set_format(None);
+        menus
+    }
+
+    /// Displays the format of the current file. Untitled documents are saved as UTF-8 with
+    /// `\n` line endings, and can not be converted until they have been saved.
+    pub fn set_format(&self, format: Option<TextFormat>) {
+        let saved = format.is_some();
+        let format = format.unwrap_or_default();
+        self.encoding.set_label(&format.encoding_name());
+        self.line_ending.set_label(format.line_ending.name());
+        self.encoding.set_sensitive(saved);
+        self.line_ending.set_sensitive(saved);
+
+        // Only the Unicode encodings may begin with a byte order mark.
+        self.bom.set_active(format.bom);
+        self.bom.set_sensitive(!format.encoding.bom().is_empty());
+    }
+}
+
+/// Asks which encoding the file should be read with, when it could not be determined. The
+/// text of the file is previewed in the selected encoding. Returns **None** if the file should
+/// not be opened.
+pub fn choose_encoding(
+    window: &Window,
+    path: &Path,
+    bytes: &[u8],
+    candidates: &[Encoding],
+) -> Option<Decoded> {
+    // The dialog is kept above the window which is opening the file.
+    let dialog = Dialog::new_with_buttons(
+        Some("Choose an Encoding"),
+        Some(window),
+        DIALOG_MODAL,
+        &[("Cancel", ResponseType::Cancel.into()), ("Open", ResponseType::Accept.into())],
+    );
+    dialog.set_default_size(560, 400);
+
+    let label = Label::new(Some(
+        format!("The encoding of {} could not be determined:", path.display()).as_str(),
+    ));
+    label.set_halign(Align::Start);
+
+    let combo = ComboBoxText::new();
+    for encoding in candidates {
+        combo.append_text(encoding.name());
+    }
+
+    let preview = TextView::new();
+    preview.set_editable(false);
+    preview.set_monospace(true);
+    let scroller = ScrolledWindow::new(None, None);
+    scroller.set_shadow_type(ShadowType::In);
+    scroller.add(&preview);
+
+    // Previews the beginning of the file, decoded with the selected encoding.
+    let sample = &bytes[..bytes.len().min(8192)];
+    let update = {
+        let (candidates, preview) = (candidates.to_vec(), preview.clone());
+        let sample = sample.to_vec();
+        move |combo: &ComboBoxText| {
+            let text = selected(combo, &candidates)
+                .and_then(|encoding| decode(&sample, encoding))
+                .map_or_else(|| "The file is not valid in this encoding.".to_owned(), |d| d.text);
+            if let Some(buffer) = preview.get_buffer() {
+                buffer.set_text(&text);
+            }
+        }
+    };
+    combo.connect_changed(update);
+    combo.set_active(0);
+
+    let area = dialog.get_content_area();
+    area.set_spacing(6);
+    area.set_border_width(12);
+    area.pack_start(&label, false, false, 0);
+    area.pack_start(&combo, false, false, 0);
+    area.pack_start(&scroller, true, true, 0);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let encoding = selected(&combo, candidates);
+    dialog.destroy();
+
+    if response != ResponseType::Accept.into() {
+        return None;
+    }
+
+    encoding.and_then(|encoding| decode(bytes, encoding))
+}
+
+/// The encoding which is selected within the combo box, if any.
+fn selected(combo: &ComboBoxText, candidates: &[Encoding]) -> Option<Encoding> {
+    match combo.get_active() {
+        index if index >= 0 => candidates.get(index as usize).cloned(),
+        _ => None,
+    }
+}
@@ -0,0 +1,266 @@
+use super::{Content, Header};
+use gtk::*;
+use sourceview::*;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use webkit2gtk::*;
+
+/// The width of the text column within focus mode, in characters.
+const COLUMN_WIDTH: usize = 80;
+
+/// The layout which was in use before focus mode was entered, so that it may be restored.
+struct Layout {
+    fullscreen:   bool,
+    editor:       bool,
+    preview:      bool,
+    outline:      bool,
+    backlinks:    bool,
+    problems:     bool,
+    line_numbers: bool,
+    right_margin: bool,
+    margins:      (i32, i32, i32, i32),
+    background:   BackgroundPatternType,
+}
+
+/// A distraction-free writing mode, which hides everything but the editor, centers its text
+/// within a readable column, and dims every paragraph except the one being written.
+#[derive(Clone)]
+pub struct FocusMode {
+    pub window:     Window,
+    pub header:     HeaderBar,
+    pub editor:     ScrolledWindow,
+    pub preview:    WebView,
+    pub outline:    Revealer,
+    pub backlinks:  Revealer,
+    pub problems:   Revealer,
+    pub view:       View,
+    pub buff:       Buffer,
+    pub typewriter: CheckButton,
+    pub dim:        TextTag,
+    /// The marks around the paragraph which is not dimmed, so that only the paragraphs which
+    /// the cursor leaves and enters are retagged. Text typed at either end of the paragraph
+    /// is kept between them.
+    pub lit:        (TextMark, TextMark),
+    pub active:     Arc<AtomicBool>,
+    saved:          Arc<RwLock<Option<Layout>>>,
+}
+
+impl FocusMode {
+    pub fn new(window: &Window, header: &Header, content: &Content) -> FocusMode {
+        // Paragraphs which are not being written are greyed out.
+        let dim = TextTag::new(Some("unfocused"));
+        let _ = dim.set_property("foreground", &"#999999".to_value());
+        if let Some(table) = content.source.buff.get_tag_table() {
+            table.add(&dim);
+        }
+
+        FocusMode {
+            window:     window.clone(),
+            header:     header.container.clone(),
+            editor:     content.source.container.clone(),
+            preview:    content.preview.clone(),
+            outline:    content.outline.container.clone(),
+            backlinks:  content.backlinks.container.clone(),
+            problems:   content.problems.container.clone(),
+            view:       content.source.view.clone(),
+            buff:       content.source.buff.clone(),
+            typewriter: content.status.typewriter.clone(),
+            dim,
+            lit:        (TextMark::new(None, true), TextMark::new(None, false)),
+            active:     Arc::new(AtomicBool::new(false)),
+            saved:      Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn is_active(&self) -> bool { self.active.load(Ordering::SeqCst) }
+
+    /// Enters focus mode if it is inactive, and otherwise leaves it.
+    pub fn toggle(&self, fullscreen: &AtomicBool) {
+        if self.is_active() {
+            self.exit(fullscreen);
+        } else {
+            self.enter(fullscreen);
+        }
+    }
+
+    /// Hides everything but the editor, and remembers the layout so that it can be restored.
+    pub fn enter(&self, fullscreen: &AtomicBool) {
+        if self.active.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        *self.saved.write().unwrap() = Some(Layout {
+            fullscreen:   fullscreen.load(Ordering::SeqCst),
+            editor:       self.editor.get_visible(),
+            preview:      self.preview.get_visible(),
+            outline:      self.outline.get_reveal_child(),
+            backlinks:    self.backlinks.get_reveal_child(),
+            problems:     self.problems.get_reveal_child(),
+            line_numbers: self.view.get_show_line_numbers(),
+            right_margin: self.view.get_show_right_margin(),
+            margins:      (
+                self.view.get_left_margin(),
+                self.view.get_right_margin(),
+                self.view.get_top_margin(),
+                self.view.get_bottom_margin(),
+            ),
+            background:   self.view.get_background_pattern(),
+        });
+
+        self.header.hide();
+        self.editor.show();
+        self.preview.hide();
+        self.outline.set_reveal_child(false);
+        self.backlinks.set_reveal_child(false);
+        self.problems.set_reveal_child(false);
+        self.view.set_show_line_numbers(false);
+        self.view.set_show_right_margin(false);
+        self.view.set_background_pattern(BackgroundPatternType::None);
+        self.typewriter.show();
+
+        if !fullscreen.swap(true, Ordering::SeqCst) {
+            self.window.fullscreen();
+        }
+
+        // The dimming must take precedence over the colors of the syntax highlighting.
+        if let Some(table) = self.buff.get_tag_table() {
+            self.dim.set_priority(table.get_size() - 1);
+        }
+
+        let (start, end) = (self.buff.get_start_iter(), self.buff.get_end_iter());
+        self.buff.apply_tag(&self.dim, &start, &end);
+
+        self.center();
+        self.highlight_current();
+        self.view.grab_focus();
+    }
+
+    /// Restores the layout that was in use before focus mode was entered.
+    pub fn exit(&self, fullscreen: &AtomicBool) {
+        if !self.active.swap(false, Ordering::SeqCst) {
+            return;
+        }
+
+        let layout = match self.saved.write().unwrap().take() {
+            Some(layout) => layout,
+            None => return,
+        };
+
+        self.header.show();
+        self.editor.set_visible(layout.editor);
+        self.preview.set_visible(layout.preview);
+        self.outline.set_reveal_child(layout.outline);
+        self.backlinks.set_reveal_child(layout.backlinks);
+        self.problems.set_reveal_child(layout.problems);
+        self.view.set_show_line_numbers(layout.line_numbers);
+        self.view.set_show_right_margin(layout.right_margin);
+        self.view.set_background_pattern(layout.background);
+        self.typewriter.hide();
+
+        let (left, right, top, bottom) = layout.margins;
+        self.view.set_left_margin(left);
+        self.view.set_right_margin(right);
+        self.view.set_top_margin(top);
+        self.view.set_bottom_margin(bottom);
+
+        if !layout.fullscreen && fullscreen.swap(false, Ordering::SeqCst) {
+            self.window.unfullscreen();
+        }
+
+        let (start, end) = (self.buff.get_start_iter(), self.buff.get_end_iter());
+        self.buff.remove_tag(&self.dim, &start, &end);
+        self.buff.delete_mark(&self.lit.0);
+        self.buff.delete_mark(&self.lit.1);
+    }
+
+    /// Centers a column of readable width within the editor. With typewriter scrolling, the
+    /// editor is also padded above and below, so that the first and last lines can be
+    /// scrolled to the middle of the editor.
+    pub fn center(&self) {
+        if !self.is_active() {
+            return;
+        }
+
+        let sample = "m".repeat(COLUMN_WIDTH);
+        let column = self.view.create_pango_layout(Some(sample.as_str())).get_pixel_size().0;
+        let margin = ((self.view.get_allocated_width() - column) / 2).max(10);
+        self.view.set_left_margin(margin);
+        self.view.set_right_margin(margin);
+
+        let padding = if self.typewriter.get_active() {
+            self.view.get_allocated_height() / 2
+        } else {
+            0
+        };
+        self.view.set_top_margin(padding);
+        self.view.set_bottom_margin(padding);
+    }
+
+    /// Dims every paragraph except the one containing the cursor, and keeps the cursor in
+    /// the middle of the editor when typewriter scrolling is enabled. The rest of the buffer
+    /// is already dimmed, and so only the paragraph that was previously highlighted is dimmed
+    /// again when the cursor leaves it.
+    pub fn highlight_current(&self) {
+        if !self.is_active() {
+            return;
+        }
+
+        let cursor = match self.buff.get_insert() {
+            Some(mark) => mark,
+            None => return,
+        };
+
+        let (first, last) = paragraph(&self.buff, &self.buff.get_iter_at_mark(&cursor));
+        let (ref start, ref end) = self.lit;
+        if start.get_deleted() {
+            self.buff.add_mark(start, &first);
+            self.buff.add_mark(end, &last);
+        } else {
+            let previous = self.buff.get_iter_at_mark(start);
+            let next = self.buff.get_iter_at_mark(end);
+            let moved = previous.get_offset() != first.get_offset()
+                || next.get_offset() != last.get_offset();
+            if moved {
+                self.buff.apply_tag(&self.dim, &previous, &next);
+                self.buff.move_mark(start, &first);
+                self.buff.move_mark(end, &last);
+            }
+        }
+        self.buff.remove_tag(&self.dim, &first, &last);
+
+        if self.typewriter.get_active() {
+            self.view.scroll_to_mark(&cursor, 0.0, true, 0.0, 0.5);
+        }
+    }
+}
+
+/// Finds the start and end of the paragraph containing the iter, where paragraphs are
+/// separated by blank lines.
+fn paragraph(buffer: &Buffer, iter: &TextIter) -> (TextIter, TextIter) {
+    let is_blank = |line: i32| {
+        let start = buffer.get_iter_at_line(line);
+        let mut end = start.clone();
+        if !end.ends_line() {
+            end.forward_to_line_end();
+        }
+        start.get_text(&end).map_or(true, |text| text.trim().is_empty())
+    };
+
+    let line_count = buffer.get_line_count();
+    let mut first = iter.get_line();
+    while first > 0 && !is_blank(first - 1) {
+        first -= 1;
+    }
+
+    let mut last = iter.get_line();
+    while last + 1 < line_count && !is_blank(last + 1) {
+        last += 1;
+    }
+
+    let start = buffer.get_iter_at_line(first);
+    let mut end = buffer.get_iter_at_line(last);
+    if !end.ends_line() {
+        end.forward_to_line_end();
+    }
+    (start, end)
+}
@@ -0,0 +1,267 @@
+use super::misc::get_buffer;
+use format::{self, Command};
+use gdk::{ModifierType, CONTROL_MASK, SHIFT_MASK};
+use gdk::enums::key;
+use gtk::*;
+use pretty::pretty_print;
+use pulldown_cmark::Alignment;
+use sourceview::*;
+use table::Command as TableCommand;
+
+/// Each group of formatting commands, with their labels and keyboard accelerators. Groups are
+/// divided by separators within the format menu.
+const COMMANDS: &[&[(Command, &str, u32, bool)]] = &[
+    &[
+        (Command::Bold, "_Bold", key::b, false),
+        (Command::Italic, "_Italic", key::i, false),
+        (Command::Code, "_Code", key::grave, false),
+        (Command::Link, "_Link", key::k, false),
+    ],
+    &[
+        (Command::Heading(1), "Heading _1", key::_1, false),
+        (Command::Heading(2), "Heading _2", key::_2, false),
+        (Command::Heading(3), "Heading _3", key::_3, false),
+        (Command::Heading(4), "Heading _4", key::_4, false),
+        (Command::Heading(5), "Heading _5", key::_5, false),
+        (Command::Heading(6), "Heading _6", key::_6, false),
+    ],
+    &[
+        (Command::BulletList, "B_ulleted List", key::u, true),
+        (Command::NumberedList, "_Numbered List", key::o, true),
+        (Command::Quote, "_Quote", key::q, true),
+        (Command::Table, "_Table", key::t, true),
+    ],
+];
+
+/// The commands which edit the table at the cursor, with their labels. Groups are divided by
+/// separators within the table submenu.
+const TABLE_COMMANDS: &[&[(TableCommand, &str)]] = &[
+    &[
+        (TableCommand::InsertRowAbove, "Insert Row _Above"),
+        (TableCommand::InsertRowBelow, "Insert Row _Below"),
+        (TableCommand::DeleteRow, "_Delete Row"),
+    ],
+    &[
+        (TableCommand::InsertColumnLeft, "Insert Column _Left"),
+        (TableCommand::InsertColumnRight, "Insert Column _Right"),
+        (TableCommand::DeleteColumn, "Delete _Column"),
+    ],
+    &[
+        (TableCommand::Align(Alignment::None), "_No Alignment"),
+        (TableCommand::Align(Alignment::Left), "Align L_eft"),
+        (TableCommand::Align(Alignment::Center), "Align Cen_ter"),
+        (TableCommand::Align(Alignment::Right), "Align Ri_ght"),
+    ],
+];
+
+/// A header bar menu containing every formatting command.
+#[derive(Clone)]
+pub struct FormatMenu {
+    pub button:   MenuButton,
+    pub items:    Vec<(MenuItem, Command)>,
+    pub table:    Vec<(MenuItem, TableCommand)>,
+    pub document: MenuItem,
+}
+
+impl FormatMenu {
+    pub fn new() -> FormatMenu {
+        let menu = Menu::new();
+        let mut items = Vec::new();
+
+        for (index, group) in COMMANDS.iter().enumerate() {
+            if index != 0 {
+                menu.append(&SeparatorMenuItem::new());
+            }
+
+            for &(command, label, _, _) in group.iter() {
+                let item = MenuItem::new_with_mnemonic(label);
+                menu.append(&item);
+                items.push((item, command));
+            }
+        }
+
+        // Commands which edit an existing table are kept within their own submenu.
+        let table_menu = Menu::new();
+        let mut table = Vec::new();
+        for (index, group) in TABLE_COMMANDS.iter().enumerate() {
+            if index != 0 {
+                table_menu.append(&SeparatorMenuItem::new());
+            }
+
+            for &(command, label) in group.iter() {
+                let item = MenuItem::new_with_mnemonic(label);
+                table_menu.append(&item);
+                table.push((item, command));
+            }
+        }
+
+        let table_item = MenuItem::new_with_mnemonic("Edit T_able");
+        table_item.set_submenu(Some(&table_menu));
+        menu.append(&table_item);
+
+        // Reformatting the whole document is placed apart from the other commands.
+        let document = MenuItem::new_with_mnemonic("_Format Document");
+        menu.append(&SeparatorMenuItem::new());
+        menu.append(&document);
+
+        menu.show_all();
+
+        let button = MenuButton::new();
+        button.set_label("Fo_rmat");
+        button.set_use_underline(true);
+        button.set_popup(Some(&menu));
+
+        FormatMenu { button, items, table, document }
+    }
+
+    /// Binds the keyboard accelerator of each command to the given accelerator group.
+    pub fn add_accelerators(&self, accel_group: &AccelGroup) {
+        let accelerators = COMMANDS.iter().flat_map(|group| group.iter());
+        for (&(ref item, _), &(_, _, key, shift)) in self.items.iter().zip(accelerators) {
+            let modifiers: ModifierType =
+                if shift { CONTROL_MASK | SHIFT_MASK } else { CONTROL_MASK };
+            item.add_accelerator("activate", accel_group, key, modifiers, ACCEL_VISIBLE);
+        }
+
+        self.document.add_accelerator(
+            "activate",
+            accel_group,
+            key::f,
+            CONTROL_MASK | SHIFT_MASK,
+            ACCEL_VISIBLE,
+        );
+    }
+}
+
+/// Applies the formatting command to the selected text, or to the word or line at the cursor
+/// if nothing is selected, as a single undoable action.
+pub fn apply(buffer: &Buffer, command: Command) {
+    let (mut start, mut end) = match buffer.get_selection_bounds() {
+        Some(bounds) => bounds,
+        None => {
+            let cursor = cursor(buffer);
+            (cursor.clone(), cursor)
+        }
+    };
+
+    if command.is_line_command() {
+        // Line commands always operate on whole lines.
+        start.set_line_offset(0);
+        if !end.ends_line() {
+            end.forward_to_line_end();
+        }
+    } else if start.get_offset() == end.get_offset() && start.inside_word()
+        && command != Command::Table
+    {
+        // Inline commands without a selection apply to the word at the cursor.
+        if !start.starts_word() {
+            start.backward_word_start();
+        }
+        if !end.ends_word() {
+            end.forward_word_end();
+        }
+    }
+
+    // Markers that surround the selection, rather than being selected, are removed as well.
+    if let Some(marker) = command.marker() {
+        let length = marker.chars().count() as i32;
+        let mut before = start.clone();
+        let mut after = end.clone();
+        before.backward_chars(length);
+        after.forward_chars(length);
+        if before.get_text(&start).map_or(false, |text| text == marker)
+            && end.get_text(&after).map_or(false, |text| text == marker)
+        {
+            start = before;
+            end = after;
+        }
+    }
+
+    let text = start.get_text(&end).unwrap_or_default();
+    let replacement = match command {
+        Command::Link => format::link(&text),
+        Command::Table => format::table(&text),
+        _ if command.is_line_command() => format::toggle_lines(&text, command),
+        _ => format::toggle_wrap(&text, command.marker().unwrap_or("")),
+    };
+
+    let offset = start.get_offset();
+    buffer.begin_user_action();
+    buffer.delete(&mut start, &mut end);
+    buffer.insert(&mut start, &replacement);
+    buffer.end_user_action();
+
+    // Select the text that was formatted, excluding any markers that were added, so that
+    // applying the same command again will undo it.
+    let (select_from, select_to) = match command.marker() {
+        Some(marker) if replacement.len() > text.len() => {
+            let length = marker.chars().count() as i32;
+            (offset + length, offset + replacement.chars().count() as i32 - length)
+        }
+        _ if command == Command::Link => {
+            // Move the cursor into whichever part of the link has yet to be written.
+            let position = if text.is_empty() || replacement.starts_with("[]") {
+                offset + 1
+            } else {
+                offset + replacement.chars().count() as i32 - 1
+            };
+            (position, position)
+        }
+        _ => (offset, offset + replacement.chars().count() as i32),
+    };
+
+    buffer.select_range(
+        &buffer.get_iter_at_offset(select_from),
+        &buffer.get_iter_at_offset(select_to),
+    );
+}
+
+/// Reformats the whole document as normalized markdown, wrapped at the view's right margin,
+/// as a single undoable action. The cursor is kept beside the same text.
+pub fn format_document(view: &View, buffer: &Buffer) {
+    let text = match get_buffer(buffer) {
+        Some(text) => text,
+        None => return,
+    };
+
+    let formatted = pretty_print(&text, view.get_right_margin_position() as usize);
+    if formatted == text {
+        return;
+    }
+
+    // Whitespace is what the formatter changes the most, so the cursor is located by the
+    // number of visible characters that precede it.
+    let cursor_offset = cursor(buffer).get_offset() as usize;
+    let visible = text.chars().take(cursor_offset).filter(|c| !c.is_whitespace()).count();
+    let mut seen = 0;
+    let new_offset = formatted
+        .chars()
+        .position(|c| {
+            if seen == visible {
+                return true;
+            }
+            if !c.is_whitespace() {
+                seen += 1;
+            }
+            false
+        })
+        .unwrap_or_else(|| formatted.chars().count());
+
+    let (mut start, mut end) = (buffer.get_start_iter(), buffer.get_end_iter());
+    buffer.begin_user_action();
+    buffer.delete(&mut start, &mut end);
+    buffer.insert(&mut start, &formatted);
+    buffer.end_user_action();
+
+    buffer.place_cursor(&buffer.get_iter_at_offset(new_offset as i32));
+    if let Some(cursor) = buffer.get_insert() {
+        view.scroll_to_mark(&cursor, 0.0, true, 0.0, 0.5);
+    }
+}
+
+fn cursor(buffer: &Buffer) -> TextIter {
+    match buffer.get_insert() {
+        Some(mark) => buffer.get_iter_at_mark(&mark),
+        None => buffer.get_end_iter(),
+    }
+}
@@ -0,0 +1,100 @@
+use git::Change;
+use gtk::*;
+use sourceview::*;
+use std::path::Path;
+
+/// Marks the lines within the editor's gutter which have changed since the repository's
+/// HEAD, when the document is within a git repository.
+#[derive(Clone)]
+pub struct GitGutter;
+
+impl GitGutter {
+    pub fn new(source: &View) -> GitGutter {
+        // Changes are marked beneath problems that the linter has found.
+        source.set_show_line_marks(true);
+        for &change in &[Change::Added, Change::Modified, Change::Deleted] {
+            let attributes = MarkAttributes::new();
+            attributes.set_icon_name(icon_name(change));
+            source.set_mark_attributes(category(change), &attributes, 0);
+        }
+
+        GitGutter
+    }
+
+    /// Replaces the marks within the gutter with the given changes.
+    pub fn update(&self, buffer: &Buffer, changes: &[(i32, Change)]) {
+        self.clear(buffer);
+        for &(line, change) in changes {
+            buffer.create_source_mark(None, category(change), &buffer.get_iter_at_line(line));
+        }
+    }
+
+    /// Removes every mark from the gutter, such as when the document is not in a repository.
+    pub fn clear(&self, buffer: &Buffer) {
+        let (start, end) = (buffer.get_start_iter(), buffer.get_end_iter());
+        for &change in &[Change::Added, Change::Modified, Change::Deleted] {
+            buffer.remove_source_marks(&start, &end, Some(category(change)));
+        }
+    }
+}
+
+/// Asks for the message of a commit of the document. Returns **None** if the commit was
+/// canceled, or if the message was left empty.
+pub fn commit_message(window: &Window, relative_path: &Path) -> Option<String> {
+    let dialog = Dialog::new_with_buttons(
+        Some("Commit"),
+        Some(window),
+        DIALOG_MODAL | DIALOG_DESTROY_WITH_PARENT,
+        &[("Cancel", ResponseType::Cancel.into()), ("Commit", ResponseType::Accept.into())],
+    );
+    dialog.set_default_size(480, 240);
+
+    let label = Label::new(Some(
+        format!("Commit the saved changes to {}:", relative_path.display()).as_str(),
+    ));
+    label.set_halign(Align::Start);
+
+    let message = TextView::new();
+    message.set_wrap_mode(WrapMode::Word);
+    let scroller = ScrolledWindow::new(None, None);
+    scroller.set_shadow_type(ShadowType::In);
+    scroller.add(&message);
+
+    let area = dialog.get_content_area();
+    area.set_spacing(6);
+    area.set_border_width(12);
+    area.pack_start(&label, false, false, 0);
+    area.pack_start(&scroller, true, true, 0);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let text = message.get_buffer().and_then(|buffer| {
+        buffer.get_text(&buffer.get_start_iter(), &buffer.get_end_iter(), false)
+    });
+    dialog.destroy();
+
+    match text {
+        Some(ref text) if response == ResponseType::Accept.into() && !text.trim().is_empty() => {
+            Some(text.trim().to_owned())
+        }
+        _ => None,
+    }
+}
+
+/// The mark category of each kind of change.
+fn category(change: Change) -> &'static str {
+    match change {
+        Change::Added => "git-added",
+        Change::Modified => "git-modified",
+        Change::Deleted => "git-deleted",
+    }
+}
+
+/// The icon which marks each kind of change within the gutter.
+fn icon_name(change: Change) -> &'static str {
+    match change {
+        Change::Added => "list-add-symbolic",
+        Change::Modified => "document-edit-symbolic",
+        Change::Deleted => "list-remove-symbolic",
+    }
+}
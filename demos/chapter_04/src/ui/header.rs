@@ -1,10 +1,23 @@
+use super::{FormatMenu, LayoutMenu};
 use gtk::*;
 
 pub struct Header {
     pub container: HeaderBar,
+    pub outline:   ToggleButton,
     pub open:      Button,
+    pub opening:   Box,
+    pub folder:    Button,
+    pub search:    ToggleButton,
+    pub backlinks: ToggleButton,
+    pub build:     Button,
+    pub format:    FormatMenu,
+    pub layout:    LayoutMenu,
+    pub history:   Button,
+    pub commit:    Button,
     pub save:      Button,
     pub save_as:   Button,
+    pub saving:    Box,
+    pub spinner:   Spinner,
 }
 
 impl Header {
@@ -17,14 +30,68 @@ impl Header {
         // Enable the window controls within this headerbar.
         container.set_show_close_button(true);
 
+        let outline = ToggleButton::new_with_mnemonic("O_utline");
         let open = Button::new_with_mnemonic("_Open");
+        let folder = Button::new_with_mnemonic("Open _Folder");
+        // Reveals the panel which searches the documents of the open folder.
+        let search = ToggleButton::new();
+        search.set_image(&Image::new_from_icon_name("edit-find-symbolic", IconSize::Button.into()));
+        search.set_tooltip_text(Some("Search in Folder"));
+        let backlinks = ToggleButton::new_with_mnemonic("_Backlinks");
+        // Only shown once a folder has been opened.
+        let build = Button::new_with_mnemonic("Build S_ite");
+        build.set_no_show_all(true);
+        let format = FormatMenu::new();
+        let layout = LayoutMenu::new();
+        let history = Button::new_with_mnemonic("_History");
+        // Only shown when the document is within a git repository.
+        let commit = Button::new_with_mnemonic("Co_mmit");
+        commit.set_no_show_all(true);
         let save = Button::new_with_mnemonic("_Save");
         let save_as = Button::new_with_mnemonic("Save _As");
-        container.pack_start(&open);
-        container.pack_end(&save_as);
-        container.pack_end(&save);
+
+        // The buttons which read and write files are grouped, so that they may be made
+        // insensitive together while a file is being read or written.
+        let opening = Box::new(Orientation::Horizontal, 0);
+        opening.pack_start(&open, false, false, 0);
+        let saving = Box::new(Orientation::Horizontal, 6);
+        saving.pack_start(&save, false, false, 0);
+        saving.pack_start(&save_as, false, false, 0);
+        // Spins while a file is being read or written.
+        let spinner = Spinner::new();
+        spinner.set_no_show_all(true);
+
+        container.pack_start(&outline);
+        container.pack_start(&opening);
+        container.pack_start(&folder);
+        container.pack_start(&search);
+        container.pack_start(&backlinks);
+        container.pack_start(&build);
+        container.pack_start(&format.button);
+        container.pack_start(&layout.button);
+        container.pack_end(&history);
+        container.pack_end(&commit);
+        container.pack_end(&saving);
+        container.pack_end(&spinner);
 
         // Returns the header and all of it's state
-        Header { container, open, save, save_as }
+        Header {
+            container,
+            outline,
+            open,
+            opening,
+            folder,
+            search,
+            backlinks,
+            build,
+            format,
+            layout,
+            history,
+            commit,
+            save,
+            save_as,
+            saving,
+            spinner,
+        }
     }
 }
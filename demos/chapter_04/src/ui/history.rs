@@ -0,0 +1,330 @@
+use super::misc::get_buffer;
+use diff::{diff, Line};
+use glib_sys::{g_date_time_get_utc_offset, g_date_time_new_from_unix_local, g_date_time_unref};
+use gtk;
+use gtk::*;
+use history::{format_time, History, Snapshot};
+use pango::FontDescription;
+use sourceview::*;
+use std::fmt::Display;
+use std::rc::Rc;
+use std::sync::RwLock;
+
+/// The column within the history's store that holds each snapshot's name.
+const NAME: u32 = 0;
+/// The column within the history's store that holds when each snapshot was taken.
+const TIME: u32 = 1;
+
+/// The tags which mark each kind of change within a comparison, and their colors.
+const CHANGE_COLORS: &[(&str, &str)] =
+    &[("removed", "#f8d7da"), ("added", "#d4edda"), ("filler", "#eeeeee")];
+
+/// A window which lists the snapshots of a document, and compares the selected snapshot with
+/// the document, side by side. Snapshots may be taken, and restored, from the window.
+pub struct HistoryBrowser {
+    pub dialog:    Dialog,
+    pub store:     ListStore,
+    pub list:      TreeView,
+    pub name:      Entry,
+    pub take:      Button,
+    pub restore:   Button,
+    /// Reports the errors which occur while the history is read or written.
+    pub status:    Label,
+    pub snapshot:  TextBuffer,
+    pub current:   TextBuffer,
+    pub history:   History,
+    pub snapshots: RwLock<Vec<Snapshot>>,
+}
+
+impl HistoryBrowser {
+    pub fn new(window: &Window, history: History) -> HistoryBrowser {
+        let dialog = Dialog::new();
+        dialog.set_title("Document History");
+        dialog.set_transient_for(Some(window));
+        dialog.set_default_size(900, 600);
+
+        let store = ListStore::new(&[Type::String, Type::String]);
+        let list = TreeView::new_with_model(&store);
+        for &(title, column) in &[("Snapshot", NAME), ("Taken", TIME)] {
+            let renderer = CellRendererText::new();
+            let view_column = TreeViewColumn::new();
+            view_column.set_title(title);
+            view_column.pack_start(&renderer, true);
+            view_column.add_attribute(&renderer, "text", column as i32);
+            list.append_column(&view_column);
+        }
+
+        let list_scroller = ScrolledWindow::new(None, None);
+        list_scroller.set_size_request(260, -1);
+        list_scroller.add(&list);
+
+        // New snapshots are named within the entry beneath the list.
+        let name = Entry::new();
+        name.set_placeholder_text("Snapshot name");
+        let take = Button::new_with_mnemonic("_Take Snapshot");
+        let restore = Button::new_with_mnemonic("_Restore");
+        restore.set_sensitive(false);
+        let status = Label::new(None);
+        status.set_line_wrap(true);
+        status.set_xalign(0.0);
+        status.set_no_show_all(true);
+
+        let sidebar = Box::new(Orientation::Vertical, 6);
+        sidebar.pack_start(&list_scroller, true, true, 0);
+        sidebar.pack_start(&name, false, false, 0);
+        sidebar.pack_start(&take, false, false, 0);
+        sidebar.pack_start(&restore, false, false, 0);
+        sidebar.pack_start(&status, false, false, 0);
+
+        // The snapshot and the document scroll together, as their lines are aligned.
+        let (snapshot_view, snapshot) = comparison_view();
+        let (current_view, current) = comparison_view();
+        let columns = Box::new(Orientation::Horizontal, 6);
+        columns.set_homogeneous(true);
+        columns.pack_start(&snapshot_view, true, true, 0);
+        columns.pack_start(&current_view, true, true, 0);
+
+        let headings = Box::new(Orientation::Horizontal, 6);
+        headings.set_homogeneous(true);
+        headings.pack_start(&Label::new(Some("Snapshot")), true, true, 0);
+        headings.pack_start(&Label::new(Some("Current Document")), true, true, 0);
+
+        let comparison_scroller = ScrolledWindow::new(None, None);
+        comparison_scroller.add(&columns);
+
+        let comparison = Box::new(Orientation::Vertical, 6);
+        comparison.pack_start(&headings, false, false, 0);
+        comparison.pack_start(&comparison_scroller, true, true, 0);
+
+        let body = Box::new(Orientation::Horizontal, 12);
+        body.set_border_width(12);
+        body.pack_start(&sidebar, false, false, 0);
+        body.pack_start(&comparison, true, true, 0);
+
+        dialog.get_content_area().pack_start(&body, true, true, 0);
+        dialog.add_button("Close", ResponseType::Close.into());
+
+        HistoryBrowser {
+            dialog,
+            store,
+            list,
+            name,
+            take,
+            restore,
+            status,
+            snapshot,
+            current,
+            history,
+            snapshots: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Displays the error beneath the list of snapshots.
+    fn report<E: Display>(&self, message: &str, why: E) {
+        eprintln!("{}: {}", message, why);
+        self.status.set_text(&format!("{}: {}", message, why));
+        self.status.show();
+    }
+
+    /// Lists the snapshots that have been taken of the document, with the times they were
+    /// taken in the local time zone.
+    pub fn refresh(&self) {
+        self.status.hide();
+        let snapshots = self.history.snapshots().unwrap_or_else(|why| {
+            self.report("unable to read the document's history", why);
+            Vec::new()
+        });
+
+        self.store.clear();
+        for snapshot in &snapshots {
+            let time = format_time(snapshot.time, local_offset(snapshot.time));
+            self.store.insert_with_values(None, &[NAME, TIME], &[&snapshot.name, &time]);
+        }
+
+        *self.snapshots.write().unwrap() = snapshots;
+        self.restore.set_sensitive(false);
+        self.snapshot.set_text("");
+        self.current.set_text("");
+    }
+
+    /// The index of the snapshot which is selected within the list.
+    fn selected(&self) -> Option<usize> {
+        let (paths, _) = self.list.get_selection().get_selected_rows();
+        paths.first().and_then(|path| path.get_indices().first().map(|&index| index as usize))
+    }
+
+    /// Takes a snapshot of the editor's text, using the name within the entry.
+    pub fn take_snapshot(&self, editor: &Buffer) {
+        let contents = get_buffer(editor).unwrap_or_default();
+        let name = self.name.get_text().unwrap_or_default();
+        let name = if name.trim().is_empty() { "Untitled Snapshot" } else { name.trim() };
+        match self.history.save(name, &contents) {
+            Ok(_) => {
+                self.name.set_text("");
+                self.refresh();
+            }
+            Err(why) => self.report("unable to take a snapshot of the document", why),
+        }
+    }
+
+    /// Compares the selected snapshot with the editor's text, highlighting the lines that
+    /// were removed from the snapshot, and those that were added to the document.
+    pub fn compare(&self, editor: &Buffer) {
+        let snapshots = self.snapshots.read().unwrap();
+        let snapshot = match self.selected().and_then(|index| snapshots.get(index)) {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+
+        let old = match self.history.contents(snapshot) {
+            Ok(contents) => contents,
+            Err(why) => {
+                self.report(&format!("unable to read the snapshot '{}'", snapshot.name), why);
+                return;
+            }
+        };
+        let new = get_buffer(editor).unwrap_or_default();
+
+        self.snapshot.set_text("");
+        self.current.set_text("");
+        for line in diff(&old, &new) {
+            match line {
+                Line::Same(text) => {
+                    append(&self.snapshot, text, None);
+                    append(&self.current, text, None);
+                }
+                Line::Removed(text) => {
+                    append(&self.snapshot, text, Some("removed"));
+                    append(&self.current, "", Some("filler"));
+                }
+                Line::Added(text) => {
+                    append(&self.snapshot, "", Some("filler"));
+                    append(&self.current, text, Some("added"));
+                }
+            }
+        }
+
+        self.restore.set_sensitive(true);
+    }
+
+    /// Replaces the editor's text with the selected snapshot, as an undoable action, once the
+    /// user has confirmed it. The text is first stored as a snapshot of its own, so that it
+    /// will not be lost.
+    pub fn restore(&self, editor: &Buffer) {
+        let (name, contents) = {
+            let snapshots = self.snapshots.read().unwrap();
+            let snapshot = match self.selected().and_then(|index| snapshots.get(index)) {
+                Some(snapshot) => snapshot,
+                None => return,
+            };
+
+            match self.history.contents(snapshot) {
+                Ok(contents) => (snapshot.name.clone(), contents),
+                Err(why) => {
+                    self.report(&format!("unable to read the snapshot '{}'", snapshot.name), why);
+                    return;
+                }
+            }
+        };
+
+        if !confirm_restore(&self.dialog, &name) {
+            return;
+        }
+
+        let current = get_buffer(editor).unwrap_or_default();
+        if let Err(why) = self.history.save(&format!("Before restoring {}", name), &current) {
+            self.report("unable to take a snapshot of the document", why);
+            return;
+        }
+
+        let (mut start, mut end) = (editor.get_start_iter(), editor.get_end_iter());
+        editor.begin_user_action();
+        editor.delete(&mut start, &mut end);
+        editor.insert(&mut start, &contents);
+        editor.end_user_action();
+
+        self.refresh();
+    }
+}
+
+/// Opens the history of the document, and programs the history's window.
+pub fn browse(window: &Window, editor: &Buffer, history: History) {
+    let browser = Rc::new(HistoryBrowser::new(window, history));
+    browser.refresh();
+
+    let (this, editor) = (browser.clone(), editor.clone());
+    browser.take.connect_clicked(move |_| this.take_snapshot(&editor));
+
+    let (this, editor) = (browser.clone(), editor.clone());
+    browser.name.connect_activate(move |_| this.take_snapshot(&editor));
+
+    let (this, editor) = (browser.clone(), editor.clone());
+    browser.list.get_selection().connect_changed(move |_| this.compare(&editor));
+
+    let (this, editor) = (browser.clone(), editor.clone());
+    browser.restore.connect_clicked(move |_| this.restore(&editor));
+
+    browser.dialog.connect_response(|dialog, _| dialog.destroy());
+    browser.dialog.show_all();
+}
+
+/// Asks whether the document should be replaced with the snapshot.
+fn confirm_restore(dialog: &Dialog, name: &str) -> bool {
+    let confirm = MessageDialog::new(
+        Some(dialog),
+        DIALOG_MODAL,
+        MessageType::Question,
+        ButtonsType::None,
+        &format!(
+            "Replace the document with the snapshot '{}'? The document will be kept as a \
+             snapshot of its own.",
+            name
+        ),
+    );
+    confirm.add_button("Cancel", ResponseType::Cancel.into());
+    confirm.add_button("Restore", ResponseType::Accept.into());
+    let response = confirm.run();
+    confirm.destroy();
+    response == ResponseType::Accept.into()
+}
+
+/// The offset of the local time zone from UTC, in seconds, at the given time.
+fn local_offset(time: u64) -> i64 {
+    unsafe {
+        let date = g_date_time_new_from_unix_local(time as i64);
+        if date.is_null() {
+            return 0;
+        }
+        let offset = g_date_time_get_utc_offset(date) / 1_000_000;
+        g_date_time_unref(date);
+        offset
+    }
+}
+
+/// Creates a read-only view in which one side of a comparison is displayed.
+fn comparison_view() -> (gtk::TextView, TextBuffer) {
+    let buffer = TextBuffer::new(None);
+    if let Some(table) = buffer.get_tag_table() {
+        for &(name, color) in CHANGE_COLORS {
+            let tag = TextTag::new(Some(name));
+            let _ = tag.set_property("paragraph-background", &color.to_value());
+            table.add(&tag);
+        }
+    }
+
+    let view = gtk::TextView::new_with_buffer(&buffer);
+    view.set_editable(false);
+    view.set_cursor_visible(false);
+    WidgetExt::override_font(&view, &FontDescription::from_string("monospace"));
+    (view, buffer)
+}
+
+/// Appends a line to one side of a comparison, tagged with the kind of change it is.
+fn append(buffer: &TextBuffer, line: &str, tag: Option<&str>) {
+    let mut end = buffer.get_end_iter();
+    let offset = end.get_offset();
+    buffer.insert(&mut end, &[line, "\n"].concat());
+    if let Some(tag) = tag {
+        buffer.apply_tag_by_name(tag, &buffer.get_iter_at_offset(offset), &buffer.get_end_iter());
+    }
+}
@@ -0,0 +1,95 @@
+use super::Saver;
+use super::misc::show_error;
+use assets::{image_markdown, import_image, new_image_path};
+use gdk_pixbuf::Pixbuf;
+use gtk::*;
+use sourceview::*;
+use std::path::{Path, PathBuf};
+
+/// Obtains the directory of the current document, and passes it to `then`. Images are stored
+/// beside the document, so the user is asked to save the document first if it is untitled.
+pub fn document_dir<F: Fn(PathBuf) + 'static>(window: &Window, saver: &Saver, then: F) {
+    let current_file = saver.current_file.clone();
+    let current_dir = move || current_file.read().unwrap().as_ref().and_then(|f| f.get_dir());
+    if let Some(dir) = current_dir() {
+        return then(dir);
+    }
+
+    let dialog = MessageDialog::new(
+        Some(window),
+        DIALOG_MODAL,
+        MessageType::Question,
+        ButtonsType::None,
+        "The document must be saved before images can be added to it.",
+    );
+    dialog.add_button("Cancel", ResponseType::Cancel.into());
+    dialog.add_button("Save As", ResponseType::Accept.into());
+    let response = dialog.run();
+    dialog.destroy();
+
+    if response != ResponseType::Accept.into() {
+        return;
+    }
+
+    saver.save(true, move |saved| if let (true, Some(dir)) = (saved, current_dir()) {
+        then(dir);
+    });
+}
+
+/// Saves the pasted image into the assets directory, and inserts a link to it at the cursor.
+/// The user is told if the image could not be saved.
+pub fn paste_image(window: &Window, editor: &Buffer, document_dir: &Path, image: &Pixbuf) {
+    let path = match new_image_path(document_dir, "png") {
+        Ok(path) => path,
+        Err(why) => {
+            eprintln!("unable to create the assets directory: {}", why);
+            show_error(window, &format!("Unable to create the assets directory: {}", why));
+            return;
+        }
+    };
+
+    if let Err(why) = image.savev(&path.to_string_lossy(), "png", &[]) {
+        eprintln!("unable to save the pasted image to {}: {}", path.display(), why);
+        show_error(window, &format!("Unable to save the pasted image: {}", why));
+        return;
+    }
+
+    editor.insert_at_cursor(&image_markdown(document_dir, &path));
+}
+
+/// Copies the dropped images into the assets directory, and inserts links to them at the iter.
+/// The images which could not be copied are listed within a single dialog.
+pub fn insert_images(
+    window: &Window,
+    editor: &Buffer,
+    iter: &mut TextIter,
+    document_dir: &Path,
+    images: &[PathBuf],
+) {
+    let mut failures = Vec::new();
+    let links: Vec<String> = images
+        .iter()
+        .filter_map(|image| match import_image(document_dir, image) {
+            Ok(path) => Some(image_markdown(document_dir, &path)),
+            Err(why) => {
+                eprintln!("unable to copy {} to the assets directory: {}", image.display(), why);
+                failures.push(format!("{}: {}", image.display(), why));
+                None
+            }
+        })
+        .collect();
+
+    if !failures.is_empty() {
+        let message = format!(
+            "Unable to copy these images to the assets directory:\n\n{}",
+            failures.join("\n")
+        );
+        show_error(window, &message);
+    }
+
+    if !links.is_empty() {
+        editor.begin_user_action();
+        editor.insert(iter, &links.join("\n"));
+        editor.end_user_action();
+    }
+}
@@ -0,0 +1,111 @@
+use gdk::{ModifierType, CONTROL_MASK, MOD1_MASK, SHIFT_MASK};
+use gdk::enums::key;
+use gtk::*;
+use layout::Layout;
+use webkit2gtk::*;
+
+/// Each layout, with its label and the key which selects it along with ctrl+alt.
+const LAYOUTS: &[(Layout, &str, u32)] = &[
+    (Layout::EditorOnly, "_Editor Only", key::_1),
+    (Layout::PreviewOnly, "_Preview Only", key::_2),
+    (Layout::SideBySide, "_Side by Side", key::_3),
+    (Layout::Stacked, "S_tacked", key::_4),
+    (Layout::Window, "Preview in _Window", key::_5),
+];
+
+/// The editor and the preview, which are arranged according to the selected layout.
+#[derive(Clone)]
+pub struct Panes {
+    pub paned:   Paned,
+    pub editor:  ScrolledWindow,
+    pub preview: WebView,
+    /// The window which displays the preview when it is popped out of the main window.
+    pub window:  Window,
+}
+
+impl Panes {
+    pub fn new(editor: &ScrolledWindow, preview: &WebView) -> Panes {
+        let paned = Paned::new(Orientation::Horizontal);
+        paned.pack1(editor, true, true);
+        paned.pack2(preview, true, true);
+
+        let window = Window::new(WindowType::Toplevel);
+        window.set_title("Markdown Preview");
+        window.set_default_size(800, 600);
+
+        Panes { paned, editor: editor.clone(), preview: preview.clone(), window }
+    }
+
+    /// Arranges the editor and the preview according to the layout.
+    pub fn set_layout(&self, layout: Layout) {
+        let popped_out = self.window.get_child().is_some();
+        if layout == Layout::Window && !popped_out {
+            self.paned.remove(&self.preview);
+            self.window.add(&self.preview);
+        } else if layout != Layout::Window && popped_out {
+            self.window.remove(&self.preview);
+            self.paned.pack2(&self.preview, true, true);
+        }
+
+        let orientation = match layout {
+            Layout::Stacked => Orientation::Vertical,
+            _ => Orientation::Horizontal,
+        };
+        self.paned.set_orientation(orientation);
+
+        self.editor.set_visible(layout != Layout::PreviewOnly);
+        self.preview.set_visible(layout != Layout::EditorOnly);
+
+        if layout == Layout::Window {
+            self.window.show_all();
+        } else {
+            self.window.hide();
+        }
+    }
+}
+
+/// A header bar menu of the layouts which the editor and the preview may be arranged in.
+#[derive(Clone)]
+pub struct LayoutMenu {
+    pub button: MenuButton,
+    pub items:  Vec<(MenuItem, Layout)>,
+    /// Toggles focus mode.
+    pub focus:  MenuItem,
+}
+
+impl LayoutMenu {
+    pub fn new() -> LayoutMenu {
+        let menu = Menu::new();
+        let mut items = Vec::new();
+        for &(layout, label, _) in LAYOUTS {
+            let item = MenuItem::new_with_mnemonic(label);
+            menu.append(&item);
+            items.push((item, layout));
+        }
+
+        // Focus mode's key is handled by the window, as the menu's accelerators can not be
+        // activated while focus mode hides the header bar. The key is only displayed here.
+        let focus = MenuItem::new_with_mnemonic("_Focus Mode");
+        if let Some(label) = focus.get_child().and_then(|c| c.downcast::<AccelLabel>().ok()) {
+            label.set_accel(key::Return, CONTROL_MASK | SHIFT_MASK);
+        }
+        menu.append(&SeparatorMenuItem::new());
+        menu.append(&focus);
+        menu.show_all();
+
+        let button = MenuButton::new();
+        button.set_label("_View");
+        button.set_use_underline(true);
+        button.set_popup(Some(&menu));
+
+        LayoutMenu { button, items, focus }
+    }
+
+    /// Binds the keyboard accelerator of each layout to the given accelerator group.
+    pub fn add_accelerators(&self, accel_group: &AccelGroup) {
+        let modifiers: ModifierType = CONTROL_MASK | MOD1_MASK;
+        for (&(ref item, _), &(_, _, key)) in self.items.iter().zip(LAYOUTS.iter()) {
+            item.add_accelerator("activate", accel_group, key, modifiers, ACCEL_VISIBLE);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use super::{Busy, DocumentStatus};
+use super::encoding::choose_encoding;
+use super::misc::*;
+use document::DocumentEvent;
+use encoding::{detect, Decoded, Encoding};
+use files::{read_chunked, Progress};
+use gtk;
+use gtk::*;
+use sourceview::*;
+use state::ActiveMetadata;
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+/// How many bytes of the document are placed into the editor at a time, so that the interface
+/// continues to respond while a large document is displayed.
+const INSERT_CHUNK: usize = 64 * 1024;
+
+/// Reads documents into the editor on a background thread, so that large files do not block
+/// the interface. The progress of the read is displayed within the status bar, and the header
+/// bar is busy until the file has been read. No other file may be opened, nor the document
+/// saved, until then. Files which can not be read are reported within a dialog.
+#[derive(Clone)]
+pub struct Loader {
+    pub window:       Window,
+    pub editor:       Buffer,
+    pub view:         View,
+    pub progress:     ProgressBar,
+    pub current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+    pub busy:         Busy,
+    pub status:       DocumentStatus,
+}
+
+impl Loader {
+    pub fn new(
+        window: &Window,
+        editor: &Buffer,
+        view: &View,
+        progress: &ProgressBar,
+        current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+        busy: &Busy,
+        status: &DocumentStatus,
+    ) -> Loader {
+        Loader {
+            window: window.clone(),
+            editor: editor.clone(),
+            view: view.clone(),
+            progress: progress.clone(),
+            current_file,
+            busy: busy.clone(),
+            status: status.clone(),
+        }
+    }
+
+    /// Reads the file into the editor, and makes it the current file. The cursor is moved to
+    /// the line, if one is given, once the file has been read.
+    pub fn load(&self, path: &Path, line: Option<i32>) {
+        // Another file is already being read, or the document is being written, which would
+        // otherwise finish by recording this file as the document that it saved.
+        if self.busy.is_busy() {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        {
+            let path = path.to_path_buf();
+            thread::spawn(move || {
+                read_chunked(&path, |progress| {
+                    // The encoding is detected here, as doing so scans the whole file.
+                    let reading = match progress {
+                        Progress::Read { read, total } => Reading::Progress { read, total },
+                        Progress::Done(result) => Reading::Done(result.map(|bytes| {
+                            let detected = detect(&bytes);
+                            (bytes, detected)
+                        })),
+                    };
+                    sender.send(reading).is_ok()
+                });
+            });
+        }
+
+        let name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into());
+        self.progress.set_fraction(0.0);
+        self.progress.set_text(Some(format!("Opening {}…", name).as_str()));
+        self.progress.show();
+        self.busy.begin();
+
+        let loader = self.clone();
+        let path = path.to_path_buf();
+        gtk::timeout_add(50, move || {
+            loop {
+                match receiver.try_recv() {
+                    Ok(Reading::Progress { read, total }) => {
+                        loader.progress.set_fraction(read as f64 / total.max(1) as f64);
+                    }
+                    Ok(Reading::Done(result)) => {
+                        // The header bar remains busy while the user chooses an encoding.
+                        loader.progress.hide();
+                        match result {
+                            Ok((bytes, detected)) => loader.finish(&path, &bytes, detected, line),
+                            Err(why) => loader.failed(&path, &why),
+                        }
+                        loader.busy.end();
+                        return Continue(false);
+                    }
+                    Err(TryRecvError::Empty) => return Continue(true),
+                    Err(TryRecvError::Disconnected) => {
+                        loader.progress.hide();
+                        loader.failed(&path, &"the file was not read");
+                        loader.busy.end();
+                        return Continue(false);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Tells the user why the file could not be opened. The document is left as it was.
+    fn failed<E: Display>(&self, path: &Path, why: &E) {
+        eprintln!("unable to read {}: {}", path.display(), why);
+        show_error(&self.window, &format!("Unable to open {}: {}", path.display(), why));
+    }
+
+    /// Places the contents of the file, which has been read, into the editor. The text is
+    /// inserted a chunk at a time, during which the editor can not be edited and the header
+    /// bar remains busy.
+    fn finish(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+        detected: Result<Decoded, Vec<Encoding>>,
+        line: Option<i32>,
+    ) {
+        // The user chooses the encoding when it can not be detected.
+        let decoded = match detected {
+            Ok(decoded) => decoded,
+            Err(candidates) => match choose_encoding(&self.window, path, bytes, &candidates) {
+                Some(decoded) => decoded,
+                None => return,
+            },
+        };
+        let contents = decoded.text;
+
+        // Set the shared file path as this file.
+        *self.current_file.write().unwrap() =
+            Some(ActiveMetadata::new(path.to_path_buf(), bytes, decoded.format));
+
+        // Update the editor, which updates the preview in turn. The file's contents can not be
+        // undone, and are unmodified.
+        self.busy.begin();
+        self.view.set_editable(false);
+        self.editor.begin_not_undoable_action();
+        self.editor.set_text("");
+
+        let loader = self.clone();
+        let mut inserted = 0;
+        gtk::idle_add(move || {
+            if inserted < contents.len() {
+                let mut end = (inserted + INSERT_CHUNK).min(contents.len());
+                while !contents.is_char_boundary(end) {
+                    end += 1;
+                }
+                let mut iter = loader.editor.get_end_iter();
+                loader.editor.insert(&mut iter, &contents[inserted..end]);
+                inserted = end;
+                return Continue(true);
+            }
+
+            loader.editor.end_not_undoable_action();
+            loader.view.set_editable(true);
+            loader.busy.end();
+
+            // The cursor has followed the inserted text, and is returned to the start.
+            loader.editor.place_cursor(&loader.editor.get_start_iter());
+            loader.editor.set_modified(false);
+            loader.status.update(DocumentEvent::Opened);
+            if let Some(line) = line {
+                jump_to_line(&loader.view, &loader.editor, line);
+            }
+            Continue(false)
+        });
+    }
+}
+
+/// What the thread which reads the file sends to the interface.
+enum Reading {
+    Progress { read: u64, total: u64 },
+    /// The contents of the file, along with their text if the encoding was detected, or the
+    /// encodings which they may be in otherwise.
+    Done(io::Result<(Vec<u8>, Result<Decoded, Vec<Encoding>>)>),
+}
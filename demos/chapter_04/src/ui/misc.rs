@@ -1,14 +1,8 @@
+use gtk;
 use gtk::*;
 use sourceview::*;
-use std::path::Path;
-
-/// Sets the title of the header bar to the file name of the given path.
-pub fn set_title(headerbar: &HeaderBar, path: &Path) {
-    if let Some(filename) = path.file_name() {
-        let filename: &str = &filename.to_string_lossy();
-        headerbar.set_title(filename);
-    }
-}
+use std::cell::Cell;
+use std::rc::Rc;
 
 /// Obtains the entire inner string of a given text buffer.
 pub fn get_buffer(buffer: &Buffer) -> Option<String> {
@@ -16,3 +10,51 @@ pub fn get_buffer(buffer: &Buffer) -> Option<String> {
     let end = buffer.get_end_iter();
     buffer.get_text(&start, &end, true)
 }
+
+/// Places the cursor at the start of the given line, and scrolls the view to it.
+pub fn jump_to_line(view: &View, buffer: &Buffer, line: i32) {
+    let iter = buffer.get_iter_at_line(line);
+    buffer.place_cursor(&iter);
+    if let Some(cursor) = buffer.get_insert() {
+        view.scroll_to_mark(&cursor, 0.0, true, 0.0, 0.25);
+    }
+    view.grab_focus();
+}
+
+/// Tells the user why something could not be done, within a dialog above the window.
+pub fn show_error(window: &Window, message: &str) {
+    let dialog = MessageDialog::new(
+        Some(window),
+        DIALOG_MODAL,
+        MessageType::Error,
+        ButtonsType::Close,
+        message,
+    );
+    dialog.run();
+    dialog.destroy();
+}
+
+/// Escapes the text so that it is displayed literally within Pango markup.
+pub fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Wraps the callback so that, however often it is requested, it is invoked only once the
+/// delay, in milliseconds, has passed without another request. Work which is too slow to run
+/// on every keystroke is scheduled this way, and so waits until typing pauses.
+pub fn debounced<F: Fn() + 'static>(delay: u32, callback: F) -> Rc<Fn()> {
+    let callback = Rc::new(callback);
+    let requests = Rc::new(Cell::new(0usize));
+    Rc::new(move || {
+        let request = requests.get().wrapping_add(1);
+        requests.set(request);
+        let (callback, requests) = (callback.clone(), requests.clone());
+        gtk::timeout_add(delay, move || {
+            // A later request has restarted the delay.
+            if requests.get() == request {
+                callback();
+            }
+            Continue(false)
+        });
+    })
+}
@@ -1,11 +1,47 @@
 mod app;
+mod backlinks;
+mod busy;
+mod completion;
 mod content;
+mod diagnostics;
 mod dialogs;
+mod document;
+mod encoding;
+mod focus;
+pub mod format;
 pub mod save;
+mod git;
 mod header;
+mod history;
+mod images;
+mod layout;
+mod load;
 pub mod misc;
+pub mod outline;
+pub mod project;
+pub mod search;
+mod spell;
+mod status;
+pub mod table;
 
 pub use self::app::App;
+pub use self::backlinks::Backlinks;
+pub use self::busy::Busy;
 pub use self::content::Content;
-pub use self::dialogs::{OpenDialog, SaveDialog};
+pub use self::diagnostics::Diagnostics;
+pub use self::dialogs::{FileDialog, FolderDialog, OpenDialog, OpenDialogFactory, SaveDialog,
+                        SaveDialogFactory};
+pub use self::document::DocumentStatus;
+pub use self::encoding::FormatMenus;
+pub use self::focus::FocusMode;
+pub use self::format::FormatMenu;
+pub use self::git::GitGutter;
 pub use self::header::Header;
+pub use self::layout::{LayoutMenu, Panes};
+pub use self::load::Loader;
+pub use self::outline::Outline;
+pub use self::project::ProjectTree;
+pub use self::save::Saver;
+pub use self::search::SearchPanel;
+pub use self::spell::SpellChecker;
+pub use self::status::StatusBar;
@@ -0,0 +1,69 @@
+use gtk::*;
+use outline::Heading;
+
+/// The column within the outline's store that holds each heading's title.
+const TITLE: u32 = 0;
+/// The column within the outline's store that holds each heading's line number.
+pub const LINE: u32 = 1;
+/// The column within the outline's store that holds each heading's anchor.
+pub const ANCHOR: u32 = 2;
+
+/// A collapsible sidebar which lists the headings of the document as a tree.
+#[derive(Clone)]
+pub struct Outline {
+    pub container: Revealer,
+    pub view:      TreeView,
+    pub store:     TreeStore,
+}
+
+impl Outline {
+    pub fn new() -> Outline {
+        // Each row stores the title to display, and where that heading can be found.
+        let store = TreeStore::new(&[Type::String, Type::I32, Type::String]);
+        let view = TreeView::new_with_model(&store);
+        view.set_headers_visible(false);
+        view.set_activate_on_single_click(true);
+
+        // Display the title of each heading in a single column.
+        let column = TreeViewColumn::new();
+        let cell = CellRendererText::new();
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", TITLE as i32);
+        view.append_column(&column);
+
+        let scroller = ScrolledWindow::new(None, None);
+        scroller.set_size_request(200, -1);
+        scroller.add(&view);
+
+        // The sidebar slides in from the left, and is hidden by default.
+        let container = Revealer::new();
+        container.set_transition_type(RevealerTransitionType::SlideRight);
+        container.set_reveal_child(false);
+        container.add(&scroller);
+
+        Outline { container, view, store }
+    }
+
+    /// Replaces the contents of the outline with the given headings, nesting each heading
+    /// beneath the nearest preceding heading of a higher level.
+    pub fn update(&self, headings: &[Heading]) {
+        self.store.clear();
+        let mut parents: Vec<(i32, TreeIter)> = Vec::new();
+        for heading in headings {
+            while parents.last().map_or(false, |&(level, _)| level >= heading.level) {
+                parents.pop();
+            }
+
+            let iter = self.store.insert_with_values(
+                parents.last().map(|&(_, ref iter)| iter),
+                None,
+                &[TITLE, LINE, ANCHOR],
+                &[&heading.title, &heading.line, &heading.anchor],
+            );
+
+            parents.push((heading.level, iter));
+        }
+
+        self.view.expand_all();
+    }
+}
@@ -0,0 +1,236 @@
+use super::misc::debounced;
+use gio::{self, Cancellable, FileExt, FileMonitor, FileMonitorExt, FileMonitorFlags};
+use gtk;
+use gtk::*;
+use project::{matches, walk};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::SystemTime;
+
+/// The column within the project's store that holds each file or directory's name.
+const NAME: u32 = 0;
+/// The column within the project's store that holds each file's full path, which is empty
+/// for directories.
+pub const PATH: u32 = 1;
+
+/// How long the folder must go unchanged for, in milliseconds, before it is rescanned.
+const RESCAN_DELAY: u32 = 500;
+
+/// A collapsible sidebar which lists the markdown documents within a folder as a tree, and
+/// which can be filtered by typing.
+#[derive(Clone)]
+pub struct ProjectTree {
+    pub container: Revealer,
+    pub search:    SearchEntry,
+    pub view:      TreeView,
+    pub store:     TreeStore,
+    /// The open folder, and the documents that were last found within it.
+    pub folder:    Arc<RwLock<Option<(PathBuf, Vec<PathBuf>)>>>,
+    pub watcher:   FolderWatcher,
+}
+
+impl ProjectTree {
+    pub fn new() -> ProjectTree {
+        let store = TreeStore::new(&[Type::String, Type::String]);
+        let view = TreeView::new_with_model(&store);
+        view.set_headers_visible(false);
+        view.set_activate_on_single_click(true);
+
+        let column = TreeViewColumn::new();
+        let cell = CellRendererText::new();
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", NAME as i32);
+        view.append_column(&column);
+
+        let search = SearchEntry::new();
+        search.set_placeholder_text("Filter files");
+
+        let scroller = ScrolledWindow::new(None, None);
+        scroller.set_size_request(240, -1);
+        scroller.add(&view);
+
+        let sidebar = Box::new(Orientation::Vertical, 0);
+        sidebar.pack_start(&search, false, false, 0);
+        sidebar.pack_start(&scroller, true, true, 0);
+
+        // The sidebar slides in from the left once a folder has been opened.
+        let container = Revealer::new();
+        container.set_transition_type(RevealerTransitionType::SlideRight);
+        container.set_reveal_child(false);
+        container.add(&sidebar);
+
+        ProjectTree {
+            container,
+            search,
+            view,
+            store,
+            folder: Arc::new(RwLock::new(None)),
+            watcher: FolderWatcher::new(),
+        }
+    }
+
+    /// Replaces the contents of the tree with the files of the folder which match the
+    /// filter, nesting each file beneath its directories.
+    pub fn update(&self, folder: &Path, files: &[PathBuf]) {
+        let filter = self.search.get_text().unwrap_or_default();
+        self.store.clear();
+
+        let mut directories: HashMap<PathBuf, TreeIter> = HashMap::new();
+        for file in files.iter().filter(|file| matches(file, &filter)) {
+            let mut parent: Option<TreeIter> = None;
+            let mut dir = PathBuf::new();
+            if let Some(file_dir) = file.parent() {
+                for component in file_dir.components() {
+                    dir.push(component);
+                    let iter = match directories.get(&dir) {
+                        Some(iter) => iter.clone(),
+                        None => {
+                            let name = component.as_os_str().to_string_lossy().into_owned();
+                            let iter = self.store.insert_with_values(
+                                parent.as_ref(),
+                                None,
+                                &[NAME, PATH],
+                                &[&name, &""],
+                            );
+                            directories.insert(dir.clone(), iter.clone());
+                            iter
+                        }
+                    };
+                    parent = Some(iter);
+                }
+            }
+
+            let name = file.file_name().map_or(String::new(), |name| {
+                name.to_string_lossy().into_owned()
+            });
+            let path = folder.join(file).to_string_lossy().into_owned();
+            self.store.insert_with_values(parent.as_ref(), None, &[NAME, PATH], &[&name, &path]);
+        }
+
+        self.view.expand_all();
+    }
+}
+
+/// Watches the open folder's directories for changes, rescanning the folder on a background
+/// thread once they settle, and notifies its listeners on the main thread whenever documents
+/// are added to, removed from, or changed within the folder.
+#[derive(Clone)]
+pub struct FolderWatcher {
+    /// Incremented whenever another folder is watched, which discards the previous scans.
+    generation: Arc<AtomicUsize>,
+    /// Monitors the watched folder, and each directory beneath it.
+    monitors:   Rc<RefCell<Vec<FileMonitor>>>,
+    /// The documents that the last scan found, and when each was modified.
+    scanned:    Rc<RefCell<Option<Scan>>>,
+    /// Invoked with the folder and its documents, relative to the folder.
+    listeners:  Rc<RefCell<Vec<Rc<Fn(&Path, &[PathBuf])>>>>,
+}
+
+type Scan = (Vec<PathBuf>, Vec<Option<SystemTime>>);
+
+impl FolderWatcher {
+    pub fn new() -> FolderWatcher {
+        FolderWatcher {
+            generation: Arc::new(AtomicUsize::new(0)),
+            monitors:   Rc::new(RefCell::new(Vec::new())),
+            scanned:    Rc::new(RefCell::new(None)),
+            listeners:  Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Invokes the callback whenever the documents of the watched folder have changed, and
+    /// once they have first been found.
+    pub fn connect_changed<F: Fn(&Path, &[PathBuf]) + 'static>(&self, callback: F) {
+        self.listeners.borrow_mut().push(Rc::new(callback));
+    }
+
+    /// Stops watching the previous folder, if there was one, and starts watching this one.
+    pub fn watch(&self, folder: &Path) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.monitors.borrow_mut().clear();
+        *self.scanned.borrow_mut() = None;
+
+        // A change often arrives as several events, such as when a file is saved by being
+        // replaced, and so the folder is rescanned once they stop arriving.
+        let rescan: Rc<RefCell<Option<Rc<Fn()>>>> = Rc::new(RefCell::new(None));
+        let scan = {
+            let (watcher, folder, rescan) = (self.clone(), folder.to_path_buf(), rescan.clone());
+            debounced(RESCAN_DELAY, move || {
+                if let Some(ref rescan) = *rescan.borrow() {
+                    watcher.scan(&folder, generation, rescan.clone());
+                }
+            })
+        };
+        *rescan.borrow_mut() = Some(scan.clone());
+        self.scan(folder, generation, scan);
+    }
+
+    /// Scans the folder on a background thread. Afterwards, its directories are monitored,
+    /// which includes any that have been created since the last scan, and the listeners are
+    /// notified if its documents have changed.
+    fn scan(&self, folder: &Path, generation: usize, rescan: Rc<Fn()>) {
+        let (sender, receiver) = mpsc::channel();
+        {
+            let folder = folder.to_path_buf();
+            thread::spawn(move || {
+                let (directories, files) = walk(&folder);
+                let modified: Vec<Option<SystemTime>> = files
+                    .iter()
+                    .map(|file| fs::metadata(folder.join(file)).and_then(|m| m.modified()).ok())
+                    .collect();
+                let _ = sender.send((directories, (files, modified)));
+            });
+        }
+
+        let watcher = self.clone();
+        let folder = folder.to_path_buf();
+        gtk::timeout_add(50, move || {
+            let (directories, found) = match receiver.try_recv() {
+                Ok(scan) => scan,
+                Err(TryRecvError::Empty) => return Continue(true),
+                Err(TryRecvError::Disconnected) => return Continue(false),
+            };
+            if watcher.generation.load(Ordering::SeqCst) != generation {
+                return Continue(false);
+            }
+
+            watcher.monitor(&folder, &directories, &rescan);
+
+            if watcher.scanned.borrow().as_ref() != Some(&found) {
+                let files = found.0.clone();
+                *watcher.scanned.borrow_mut() = Some(found);
+                let callbacks = watcher.listeners.borrow().clone();
+                for callback in callbacks {
+                    callback(&folder, &files);
+                }
+            }
+            Continue(false)
+        });
+    }
+
+    /// Replaces the monitors with monitors of each of the directories, relative to the folder,
+    /// which request a rescan whenever anything within them changes.
+    fn monitor(&self, folder: &Path, directories: &[PathBuf], rescan: &Rc<Fn()>) {
+        let mut monitors = self.monitors.borrow_mut();
+        monitors.clear();
+        for directory in directories {
+            let path = folder.join(directory);
+            let file = gio::File::new_for_path(&path);
+            match file.monitor_directory(FileMonitorFlags::empty(), None::<&Cancellable>) {
+                Ok(monitor) => {
+                    let rescan = rescan.clone();
+                    monitor.connect_changed(move |_, _, _, _| rescan());
+                    monitors.push(monitor);
+                }
+                Err(why) => eprintln!("unable to watch {}: {}", path.display(), why),
+            }
+        }
+    }
+}
@@ -1,82 +1,330 @@
-use super::SaveDialog;
+use super::{Busy, DocumentStatus, SaveDialogFactory};
 use super::misc::*;
+use document::DocumentEvent;
+use encoding::TextFormat;
+use gtk;
 use gtk::*;
 use sourceview::*;
-use state::ActiveMetadata;
+use project::suggested_name;
+use state::{file_has_sum, ActiveMetadata};
+use std::cell::RefCell;
 use std::fs::OpenOptions;
 use std::io::{self, Write};
-use std::sync::RwLock;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
 
-pub enum SaveAction {
-    New(ActiveMetadata),
-    Saved,
-    Canceled,
+/// Writes the document on a background thread, so that slow disks do not block the interface.
+/// The header bar is busy until the file has been written.
+#[derive(Clone)]
+pub struct Saver {
+    pub window:       Window,
+    pub editor:       Buffer,
+    pub save_button:  Button,
+    pub current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+    pub busy:         Busy,
+    pub status:       DocumentStatus,
+    /// Asks the user where the document is saved as.
+    dialog:           SaveDialogFactory,
+    /// Invoked whenever the document has been saved.
+    listeners:        Rc<RefCell<Vec<Rc<Fn()>>>>,
 }
 
-pub fn save(
-    editor: &Buffer,
-    headerbar: &HeaderBar,
-    save: &Button,
-    current_file: &RwLock<Option<ActiveMetadata>>,
-    save_as: bool,
-) {
-    if let Some(text) = get_buffer(editor) {
-        // If we are programming the "Save As" button then we will not use the
-        // current path. Otherwise, we will save the editor's text to the
-        // current path, if there is a current path.
-        let result = if save_as {
-            write_data(None, text.as_bytes())
-        } else {
-            write_data(current_file.read().unwrap().as_ref(), text.as_bytes())
+impl Saver {
+    pub fn new(
+        window: &Window,
+        editor: &Buffer,
+        save: &Button,
+        current_file: Arc<RwLock<Option<ActiveMetadata>>>,
+        busy: &Busy,
+        status: &DocumentStatus,
+        dialog: SaveDialogFactory,
+    ) -> Saver {
+        Saver {
+            window: window.clone(),
+            editor: editor.clone(),
+            save_button: save.clone(),
+            current_file,
+            busy: busy.clone(),
+            status: status.clone(),
+            dialog,
+            listeners: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Invokes the callback whenever the document has been saved.
+    pub fn connect_saved<F: Fn() + 'static>(&self, callback: F) {
+        self.listeners.borrow_mut().push(Rc::new(callback));
+    }
+
+    /// Saves the editor's text to the current file. If we are saving as, or there is no
+    /// current file, a save dialog will run to obtain the path from the user. The file is
+    /// written in the format that it was read in, or saved as, before. Once the file has been
+    /// written, or if it could not be, `then` is invoked with whether the document was saved.
+    pub fn save<F: Fn(bool) + 'static>(&self, save_as: bool, then: F) {
+        // Another file is already being read or written.
+        if self.busy.is_busy() {
+            return then(false);
+        }
+
+        let text = match get_buffer(&self.editor) {
+            Some(text) => text,
+            None => return then(false),
+        };
+
+        let (path, format) = {
+            let current_file = self.current_file.read().unwrap();
+            let format = current_file.as_ref().map_or(TextFormat::default(), |f| f.get_format());
+            let path = current_file.as_ref().map(|file| file.get_path().to_path_buf());
+            (path, format)
+        };
+
+        let (path, new) = match destination(path, save_as, &text, &self.dialog) {
+            Some(destination) => destination,
+            None => return then(false),
+        };
+
+        // The text is converted to the file's encoding and line endings before it is written.
+        let data = match format.encode(&text) {
+            Ok(data) => data,
+            Err(c) => {
+                let message = format!(
+                    "{} can not represent the character '{}'",
+                    format.encoding.name(),
+                    c
+                );
+                eprintln!("unable to save the document: {}", message);
+                self.status.update(DocumentEvent::SaveFailed(message));
+                return then(false);
+            }
         };
 
-        // Now we are to match the result of the save function's output. We should
-        // only act upon a return that returns an **Ok(Some(ActiveMetadata))**, setting
-        // the title of the header bar, and the current file to the new path that
-        // we have received.
-        match result {
-            Ok(SaveAction::New(file)) => {
-                // Update the title and subtitle
-                set_title(&headerbar, file.get_path());
-                if let Some(parent) = file.get_dir() {
-                    let subtitle: &str = &parent.to_string_lossy();
-                    headerbar.set_subtitle(subtitle);
+        // Files which are being overwritten are first checked for changes by other programs.
+        let sum = match *self.current_file.read().unwrap() {
+            Some(ref file) if !new && file.get_path() == path.as_path() => Some(file.get_sum()),
+            _ => None,
+        };
+
+        self.write(Saving { path, new, format, text, data }, sum, Rc::new(then));
+    }
+
+    /// Writes the document on a background thread. If the file no longer has the checksum, the
+    /// user is asked whether to overwrite it.
+    fn write(&self, saving: Saving, sum: Option<[u8; 64]>, then: Rc<Fn(bool)>) {
+        self.busy.begin();
+        self.status.update(DocumentEvent::SaveBegan);
+        let (sender, receiver) = mpsc::channel();
+        {
+            let (path, data) = (saving.path.clone(), saving.data.clone());
+            thread::spawn(move || {
+                let _ = sender.send(write_data(&path, &data, sum));
+            });
+        }
+
+        let saver = self.clone();
+        let mut saving = Some(saving);
+        gtk::timeout_add(50, move || {
+            let result = match receiver.try_recv() {
+                Err(TryRecvError::Empty) => return Continue(true),
+                Err(TryRecvError::Disconnected) => Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "the file was not written",
+                )),
+                Ok(result) => result,
+            };
+
+            saver.busy.end();
+            let saving = match saving.take() {
+                Some(saving) => saving,
+                None => return Continue(false),
+            };
+
+            match result {
+                Ok(true) => {
+                    saver.saved(saving);
+                    then(true);
+                }
+                Ok(false) => if confirm_overwrite(&saver.window, &saving.path) {
+                    saver.write(saving, None, then.clone());
+                } else {
+                    let modified = saver.editor.get_modified();
+                    saver.status.update(DocumentEvent::SaveCanceled { modified });
+                    then(false);
+                },
+                Err(why) => {
+                    eprintln!("unable to save the document: {}", why);
+                    saver.status.update(DocumentEvent::SaveFailed(why.to_string()));
+                    then(false);
                 }
-                let mut current_file = current_file.write().unwrap();
-                *current_file = Some(file);
-                save.set_sensitive(false);
             }
-            Ok(SaveAction::Saved) => {
-                if let Some(ref mut current_file) = *current_file.write().unwrap() {
-                    current_file.set_sum(&text.as_bytes());
-                    save.set_sensitive(false);
+            Continue(false)
+        });
+    }
+
+    /// Records that the text has been written to the file at the path.
+    fn saved(&self, saving: Saving) {
+        let Saving { path, new, format, text, data } = saving;
+        if new {
+            *self.current_file.write().unwrap() =
+                Some(ActiveMetadata::new(path, &data, format));
+        } else {
+            match *self.current_file.write().unwrap() {
+                // The loader refuses to open another file while this one is being written, and so
+                // this remains the current file.
+                Some(ref mut current_file) if current_file.get_path() == path.as_path() => {
+                    current_file.set_sum(&data);
                 }
+                _ => return,
             }
-            _ => (),
+        }
+
+        // The document may have been edited while it was being written, in which case it
+        // remains modified.
+        if get_buffer(&self.editor).map_or(false, |current| current == text) {
+            self.editor.set_modified(false);
+        }
+
+        let modified = self.editor.get_modified();
+        self.status.update(DocumentEvent::Saved { modified });
+
+        let callbacks = self.listeners.borrow().clone();
+        for callback in callbacks {
+            callback();
+        }
+    }
+}
+
+/// A document which is being saved.
+struct Saving {
+    path:   PathBuf,
+    /// Whether the path was chosen from a save dialog, rather than being the current file.
+    new:    bool,
+    format: TextFormat,
+    /// The text of the document, as it was when it was saved.
+    text:   String,
+    /// The text, encoded in the file's format.
+    data:   Vec<u8>,
+}
+
+/// The path that the document is written to, and whether it was chosen from a save dialog,
+/// or **None** if the dialog was canceled. The dialog is run when saving as, or when the
+/// document has no file.
+fn destination(
+    path: Option<PathBuf>,
+    save_as: bool,
+    text: &str,
+    dialog: &SaveDialogFactory,
+) -> Option<(PathBuf, bool)> {
+    match path {
+        Some(path) if !save_as => Some((path, false)),
+        _ => {
+            // Documents are saved as beside their file, or are named after their first
+            // heading if they are untitled.
+            let (dir, name) = match path {
+                Some(ref path) => (
+                    path.parent().map(Path::to_path_buf),
+                    path.file_name().map_or(String::new(), |n| n.to_string_lossy().into()),
+                ),
+                None => (None, suggested_name(text)),
+            };
+
+            dialog(dir, &name).run().map(|path| (path, true))
+        }
+    }
+}
+
+/// Asks whether a file which another program has changed should be overwritten.
+fn confirm_overwrite(window: &Window, path: &Path) -> bool {
+    let name = path.file_name().map_or(String::new(), |name| name.to_string_lossy().into());
+    let dialog = MessageDialog::new(
+        Some(window),
+        DIALOG_MODAL,
+        MessageType::Warning,
+        ButtonsType::None,
+        &format!("{} has been changed by another program. Overwrite its changes?", name),
+    );
+    dialog.add_button("Cancel", ResponseType::Cancel.into());
+    dialog.add_button("Overwrite", ResponseType::Accept.into());
+    let response = dialog.run();
+    dialog.destroy();
+    response == ResponseType::Accept.into()
+}
+
+/// Writes the data to the file at the path. If a checksum is given, the file is only written
+/// if its contents still have that checksum, and **Ok(false)** is returned otherwise.
+fn write_data(path: &Path, data: &[u8], sum: Option<[u8; 64]>) -> io::Result<bool> {
+    if let Some(ref sum) = sum {
+        if path.exists() && !file_has_sum(path, sum) {
+            return Ok(false);
         }
     }
+
+    let mut file = OpenOptions::new().create(true).write(true).truncate(true).open(path)?;
+    file.write_all(data)?;
+    Ok(true)
 }
 
-/// Saves the given data to the file path supplied. If the path is **None**, a save dialog will
-/// run to obtain the required path from the user. In the event that the dialog has to run to
-/// obtain a file path, this function will return **Ok(Some(path))**, otherwise **Ok(None)**.
-/// An **Err** value indicates an I/O-related error occurred when trying to save the file.
-fn write_data(path: Option<&ActiveMetadata>, data: &[u8]) -> io::Result<SaveAction> {
-    if let Some(path) = path {
-        // Save the given data to the given file, truncating the file beforehand.
-        let mut file =
-            OpenOptions::new().create(true).write(true).truncate(true).open(path.get_path())?;
-        file.write_all(&data)?;
-        return Ok(SaveAction::Saved);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ui::FileDialog;
+
+    /// A dialog which chooses the path without being displayed.
+    struct StubDialog(Option<PathBuf>);
+
+    impl FileDialog for StubDialog {
+        fn run(&self) -> Option<PathBuf> { self.0.clone() }
+    }
+
+    /// The folder and name that each dialog was created with.
+    type Created = Rc<RefCell<Vec<(Option<PathBuf>, String)>>>;
+
+    /// Creates stub dialogs which choose the path, recording what each was created with.
+    fn stub(chosen: Option<&str>) -> (SaveDialogFactory, Created) {
+        let created = Rc::new(RefCell::new(Vec::new()));
+        let chosen = chosen.map(PathBuf::from);
+        let factory: SaveDialogFactory = {
+            let created = created.clone();
+            Rc::new(move |dir, name: &str| -> Box<FileDialog> {
+                created.borrow_mut().push((dir, name.to_owned()));
+                Box::new(StubDialog(chosen.clone()))
+            })
+        };
+        (factory, created)
     }
-    
-    let save_dialog = SaveDialog::new(None);
-    if let Some(new_path) = save_dialog.run() {
-        let mut file =
-            OpenOptions::new().create(true).write(true).truncate(false).open(&new_path)?;
-        file.write_all(data)?;
-        Ok(SaveAction::New(ActiveMetadata::new(new_path, data)))
-    } else {
-        Ok(SaveAction::Canceled)
+
+    #[test]
+    fn saves_to_the_current_file_without_a_dialog() {
+        let (dialog, created) = stub(Some("/elsewhere.md"));
+        let path = Some(PathBuf::from("/notes/today.md"));
+        let destination = destination(path, false, "# Today", &dialog);
+        assert_eq!(destination, Some((PathBuf::from("/notes/today.md"), false)));
+        assert!(created.borrow().is_empty());
+    }
+
+    #[test]
+    fn untitled_documents_are_named_after_their_heading() {
+        let (dialog, created) = stub(Some("/notes/garden.md"));
+        let destination = destination(None, false, "# Garden\n\nIdeas", &dialog);
+        assert_eq!(destination, Some((PathBuf::from("/notes/garden.md"), true)));
+        assert_eq!(*created.borrow(), vec![(None, "Garden.md".to_owned())]);
+    }
+
+    #[test]
+    fn saving_as_begins_beside_the_current_file() {
+        let (dialog, created) = stub(Some("/notes/copy.md"));
+        let path = Some(PathBuf::from("/notes/today.md"));
+        let destination = destination(path, true, "# Today", &dialog);
+        assert_eq!(destination, Some((PathBuf::from("/notes/copy.md"), true)));
+        let expected = vec![(Some(PathBuf::from("/notes")), "today.md".to_owned())];
+        assert_eq!(*created.borrow(), expected);
+    }
+
+    #[test]
+    fn canceling_the_dialog_does_not_save() {
+        let (dialog, _) = stub(None);
+        assert_eq!(destination(None, false, "# Today", &dialog), None);
     }
 }
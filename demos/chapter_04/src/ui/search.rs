@@ -0,0 +1,227 @@
+use super::misc::escape_markup;
+use gtk;
+use gtk::*;
+use project::markdown_files;
+use search::{parse_globs, search_file, FileMatches, Query};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+/// The column within the results' store that holds the markup of each file or match.
+const MARKUP: u32 = 0;
+/// The column within the results' store that holds the full path of each result's file.
+pub const PATH: u32 = 1;
+/// The column within the results' store that holds each match's line number, which is -1
+/// for the rows of files.
+pub const LINE: u32 = 2;
+
+/// A panel which searches every markdown document within the open folder, and lists the
+/// matching lines grouped by document.
+#[derive(Clone)]
+pub struct SearchPanel {
+    pub container:      Revealer,
+    pub entry:          SearchEntry,
+    pub regex:          CheckButton,
+    pub case_sensitive: CheckButton,
+    pub include:        Entry,
+    pub exclude:        Entry,
+    pub status:         Label,
+    pub view:           TreeView,
+    pub store:          TreeStore,
+    /// Identifies the latest search, so that earlier searches stop once it begins.
+    pub generation:     Arc<AtomicUsize>,
+}
+
+impl SearchPanel {
+    pub fn new() -> SearchPanel {
+        let entry = SearchEntry::new();
+        entry.set_placeholder_text("Search in folder");
+        entry.set_hexpand(true);
+        let regex = CheckButton::new_with_mnemonic("Regular E_xpression");
+        let case_sensitive = CheckButton::new_with_mnemonic("Match _Case");
+
+        let include = Entry::new();
+        include.set_placeholder_text("Files to include, such as docs/**");
+        let exclude = Entry::new();
+        exclude.set_placeholder_text("Files to exclude, such as drafts/*");
+
+        let options = Box::new(Orientation::Horizontal, 6);
+        options.pack_start(&entry, true, true, 0);
+        options.pack_start(&regex, false, false, 0);
+        options.pack_start(&case_sensitive, false, false, 0);
+
+        let globs = Box::new(Orientation::Horizontal, 6);
+        globs.set_homogeneous(true);
+        globs.pack_start(&include, true, true, 0);
+        globs.pack_start(&exclude, true, true, 0);
+
+        let store = TreeStore::new(&[Type::String, Type::String, Type::I32]);
+        let view = TreeView::new_with_model(&store);
+        view.set_headers_visible(false);
+        view.set_activate_on_single_click(true);
+
+        let column = TreeViewColumn::new();
+        let cell = CellRendererText::new();
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "markup", MARKUP as i32);
+        view.append_column(&column);
+
+        let scroller = ScrolledWindow::new(None, None);
+        scroller.set_size_request(-1, 200);
+        scroller.add(&view);
+
+        let status = Label::new(None);
+        status.set_halign(Align::Start);
+
+        let panel = Box::new(Orientation::Vertical, 6);
+        panel.set_border_width(6);
+        panel.pack_start(&options, false, false, 0);
+        panel.pack_start(&globs, false, false, 0);
+        panel.pack_start(&scroller, true, true, 0);
+        panel.pack_start(&status, false, false, 0);
+
+        // The panel slides up from the status bar, and is hidden by default.
+        let container = Revealer::new();
+        container.set_transition_type(RevealerTransitionType::SlideUp);
+        container.set_reveal_child(false);
+        container.add(&panel);
+
+        SearchPanel {
+            container,
+            entry,
+            regex,
+            case_sensitive,
+            include,
+            exclude,
+            status,
+            view,
+            store,
+            generation: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// The query described by the panel's widgets.
+    fn query(&self) -> Query {
+        Query {
+            pattern:        self.entry.get_text().unwrap_or_default(),
+            regex:          self.regex.get_active(),
+            case_sensitive: self.case_sensitive.get_active(),
+            include:        parse_globs(&self.include.get_text().unwrap_or_default()),
+            exclude:        parse_globs(&self.exclude.get_text().unwrap_or_default()),
+        }
+    }
+
+    /// Searches the documents within the folder on a background thread, adding each
+    /// document's matches to the panel as soon as they are found.
+    pub fn search(&self, folder: Option<&Path>) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.store.clear();
+
+        let folder = match folder {
+            Some(folder) => folder.to_path_buf(),
+            None => return self.status.set_text("Open a folder to search within it."),
+        };
+
+        let query = self.query();
+        if query.pattern.is_empty() {
+            return self.status.set_text("");
+        }
+
+        let regex = match query.compile() {
+            Ok(regex) => regex,
+            Err(why) => return self.status.set_text(&format!("Invalid expression: {}", why)),
+        };
+
+        self.status.set_text("Searching…");
+        let (sender, receiver) = mpsc::channel::<FileMatches>();
+        {
+            let current = self.generation.clone();
+            let folder = folder.clone();
+            thread::spawn(move || {
+                for file in markdown_files(&folder) {
+                    // Give up once a newer search has begun.
+                    if current.load(Ordering::SeqCst) != generation {
+                        return;
+                    }
+
+                    if !query.includes(&file) {
+                        continue;
+                    }
+
+                    let matches = search_file(&regex, &folder.join(&file));
+                    if matches.is_empty() {
+                        continue;
+                    }
+
+                    // The receiver is gone once the panel has given up on this search.
+                    if sender.send(FileMatches { path: file, matches }).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        // Results are received on the main thread, where they can be added to the panel.
+        let panel = self.clone();
+        let (mut files, mut lines) = (0, 0);
+        gtk::timeout_add(50, move || {
+            if panel.generation.load(Ordering::SeqCst) != generation {
+                return Continue(false);
+            }
+
+            loop {
+                match receiver.try_recv() {
+                    Ok(result) => {
+                        files += 1;
+                        lines += result.matches.len();
+                        panel.append(&folder, &result);
+                    }
+                    Err(TryRecvError::Empty) => return Continue(true),
+                    Err(TryRecvError::Disconnected) => {
+                        panel.status.set_text(&match lines {
+                            0 => "No results".to_owned(),
+                            1 => "1 result in 1 file".to_owned(),
+                            _ if files == 1 => format!("{} results in 1 file", lines),
+                            _ => format!("{} results in {} files", lines, files),
+                        });
+                        return Continue(false);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Lists the matches of a document beneath the document's path.
+    fn append(&self, folder: &Path, result: &FileMatches) {
+        let path = folder.join(&result.path).to_string_lossy().into_owned();
+        let title = format!(
+            "<b>{}</b> ({})",
+            escape_markup(&result.path.to_string_lossy()),
+            result.matches.len()
+        );
+        let parent =
+            self.store.insert_with_values(None, None, &[MARKUP, PATH, LINE], &[&title, &path, &-1]);
+
+        for found in &result.matches {
+            // The matched text is highlighted within its line.
+            let text = &found.text;
+            let markup = format!(
+                "{}: {}<b>{}</b>{}",
+                found.line + 1,
+                escape_markup(text[..found.start].trim_left()),
+                escape_markup(&text[found.start..found.end]),
+                escape_markup(&text[found.end..])
+            );
+            self.store.insert_with_values(
+                Some(&parent),
+                None,
+                &[MARKUP, PATH, LINE],
+                &[&markup, &path, &found.line],
+            );
+        }
+
+        self.view.expand_all();
+    }
+}
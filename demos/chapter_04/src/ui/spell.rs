@@ -0,0 +1,179 @@
+use super::misc::get_buffer;
+use gtk;
+use gtk::*;
+use pango::Underline;
+use sourceview::*;
+use spell::{Changes, Dictionary};
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+
+/// How long typing must pause for, in milliseconds, before the changed lines are checked.
+const CHECK_DELAY: u32 = 300;
+
+/// Underlines misspelled words within a buffer, using the currently-selected dictionary.
+#[derive(Clone)]
+pub struct SpellChecker {
+    pub tag:        TextTag,
+    pub dictionary: Arc<RwLock<Option<Dictionary>>>,
+    /// The lines which have changed since the buffer was last checked.
+    changes:        Rc<RefCell<Changes>>,
+}
+
+impl SpellChecker {
+    pub fn new(buff: &Buffer) -> SpellChecker {
+        // Misspelled words are marked with a red, wavy underline.
+        let tag = TextTag::new(Some("misspelled"));
+        let _ = tag.set_property("underline", &Underline::Error.to_value());
+        if let Some(table) = buff.get_tag_table() {
+            table.add(&tag);
+        }
+
+        SpellChecker {
+            tag,
+            dictionary: Arc::new(RwLock::new(None)),
+            changes: Rc::new(RefCell::new(Changes::default())),
+        }
+    }
+
+    /// Switches to the dictionary of the given language, or disables spell checking if the
+    /// language is **None**, and then checks the buffer again.
+    pub fn set_language(&self, buffer: &Buffer, language: Option<&str>) {
+        let dictionary = language.and_then(|language| match Dictionary::for_language(language) {
+            Ok(dictionary) => Some(dictionary),
+            Err(why) => {
+                eprintln!("unable to load the {} dictionary: {}", language, why);
+                None
+            }
+        });
+
+        *self.dictionary.write().unwrap() = dictionary;
+        self.check(buffer);
+    }
+
+    /// Records that text is about to be inserted at the iter. The lines that it is inserted
+    /// into are checked once typing pauses.
+    pub fn inserting(&self, buffer: &Buffer, iter: &TextIter, text: &str) {
+        let line = iter.get_line() as usize;
+        let breaks = text.matches('\n').count();
+        self.changed(buffer, has_fence(text), |changes| changes.insert(line, breaks));
+    }
+
+    /// Records that the text between the iters is about to be deleted. The line that remains
+    /// is checked once typing pauses.
+    pub fn deleting(&self, buffer: &Buffer, start: &TextIter, end: &TextIter) {
+        let (first, last) = (start.get_line() as usize, end.get_line() as usize);
+        let fence = start.get_text(end).map_or(false, |text| has_fence(&text));
+        self.changed(buffer, fence, |changes| changes.delete(first, last));
+    }
+
+    /// Updates the changed lines, and schedules them to be checked if they are not already.
+    /// Adding or removing a code fence changes which of the following lines are code, and so
+    /// the whole buffer is checked.
+    fn changed<F: FnOnce(&mut Changes)>(&self, buffer: &Buffer, fence: bool, change: F) {
+        let mut changes = self.changes.borrow_mut();
+        let scheduled = !changes.is_empty();
+        change(&mut *changes);
+        changes.everything |= fence;
+
+        if !scheduled {
+            let (spell, buffer) = (self.clone(), buffer.clone());
+            gtk::timeout_add(CHECK_DELAY, move || {
+                let changes = mem::replace(&mut *spell.changes.borrow_mut(), Changes::default());
+                match changes.lines {
+                    Some((first, last)) if !changes.everything => {
+                        spell.check_lines(&buffer, first as i32, last as i32)
+                    }
+                    _ => spell.check(&buffer),
+                }
+                Continue(false)
+            });
+        }
+    }
+
+    /// Underlines every misspelled word within the buffer.
+    pub fn check(&self, buffer: &Buffer) {
+        let last = buffer.get_line_count() - 1;
+        self.check_lines(buffer, 0, last);
+    }
+
+    /// Underlines the misspelled words from the first line to the last line of the buffer.
+    fn check_lines(&self, buffer: &Buffer, first: i32, last: i32) {
+        let start_iter = buffer.get_iter_at_line(first);
+        let mut end_iter = buffer.get_iter_at_line(last);
+        if !end_iter.ends_line() {
+            end_iter.forward_to_line_end();
+        }
+        buffer.remove_tag(&self.tag, &start_iter, &end_iter);
+
+        let dictionary = self.dictionary.read().unwrap();
+        let (dictionary, text) = match (dictionary.as_ref(), get_buffer(buffer)) {
+            (Some(dictionary), Some(text)) => (dictionary, text),
+            _ => return,
+        };
+
+        // The lines may have changed since they were recorded.
+        let start = match line_offset(&text, first as usize) {
+            Some(start) => start,
+            None => return,
+        };
+        let end = line_offset(&text, last as usize + 1).map_or(text.len(), |end| end - 1);
+        let end = end.max(start);
+
+        // The buffer is addressed by characters, rather than bytes.
+        let (mut chars, mut previous) = (start_iter.get_offset() as usize, start);
+        for (start, end) in dictionary.misspelled_within(&text, start, end) {
+            chars += text[previous..start].chars().count();
+            let length = text[start..end].chars().count();
+            let start_iter = buffer.get_iter_at_offset(chars as i32);
+            let end_iter = buffer.get_iter_at_offset((chars + length) as i32);
+            buffer.apply_tag(&self.tag, &start_iter, &end_iter);
+            chars += length;
+            previous = end;
+        }
+    }
+
+    /// Obtains the character offsets, and the text, of the misspelled word at the iter.
+    pub fn misspelled_at(&self, iter: &TextIter) -> Option<(i32, i32, String)> {
+        if !iter.has_tag(&self.tag) {
+            return None;
+        }
+
+        let mut start = iter.clone();
+        if !start.begins_tag(Some(&self.tag)) {
+            start.backward_to_tag_toggle(Some(&self.tag));
+        }
+
+        let mut end = iter.clone();
+        end.forward_to_tag_toggle(Some(&self.tag));
+
+        start.get_text(&end).map(|word| (start.get_offset(), end.get_offset(), word))
+    }
+
+    /// Suggestions for the misspelled word, from the current dictionary.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        self.dictionary.read().unwrap().as_ref().map_or(Vec::new(), |d| d.suggest(word))
+    }
+
+    /// Adds the word to the personal dictionary, and then checks the buffer again.
+    pub fn add_word(&self, buffer: &Buffer, word: &str) {
+        if let Some(ref mut dictionary) = *self.dictionary.write().unwrap() {
+            if let Err(why) = dictionary.add_word(word) {
+                eprintln!("unable to add {} to the personal dictionary: {}", word, why);
+            }
+        }
+        self.check(buffer);
+    }
+}
+
+/// Whether the text contains the marker of a fenced code block.
+fn has_fence(text: &str) -> bool { text.contains("```") || text.contains("~~~") }
+
+/// The byte offset of the start of the line within the text.
+fn line_offset(text: &str, line: usize) -> Option<usize> {
+    if line == 0 {
+        return Some(0);
+    }
+    text.match_indices('\n').nth(line - 1).map(|(index, _)| index + 1)
+}
@@ -0,0 +1,175 @@
+use super::FormatMenus;
+use super::misc::get_buffer;
+use gtk::*;
+use sourceview::*;
+use spell;
+use stats::Statistics;
+
+/// A bar beneath the editor and preview which displays statistics about the document.
+#[derive(Clone)]
+pub struct StatusBar {
+    pub container:    Box,
+    pub document:     Label,
+    pub selection:    Label,
+    pub include_code: CheckButton,
+    pub language:     ComboBoxText,
+    pub problems:     ToggleButton,
+    pub typewriter:   CheckButton,
+    pub serve:        ToggleButton,
+    pub share:        CheckButton,
+    pub address:      LinkButton,
+    pub format:       FormatMenus,
+    pub loading:      ProgressBar,
+}
+
+impl StatusBar {
+    pub fn new() -> StatusBar {
+        let container = Box::new(Orientation::Horizontal, 12);
+        container.set_border_width(3);
+
+        // Statistics for the whole document are on the left, and the selection to the right.
+        let document = Label::new(None);
+        let selection = Label::new(None);
+        let include_code = CheckButton::new_with_label("Count Code Blocks");
+        include_code.set_active(true);
+
+        // Lists every dictionary that may be used for spell checking.
+        let language = ComboBoxText::new();
+        language.set_tooltip_text(Some("Spell Checking Language"));
+        language.append(Some(""), "No Spell Checking");
+        for dictionary in spell::available() {
+            language.append(Some(&dictionary), &dictionary);
+        }
+
+        // Reveals the list of problems that the linter has found.
+        let problems = ToggleButton::new_with_label("No Problems");
+        problems.set_relief(ReliefStyle::None);
+
+        // Keeps the current line centered within focus mode, and is only shown in focus mode.
+        let typewriter = CheckButton::new_with_label("Typewriter Scrolling");
+        typewriter.set_no_show_all(true);
+
+        // Serves the preview to web browsers, and links to the address that it is served at.
+        let serve = ToggleButton::new_with_label("Serve Preview");
+        serve.set_relief(ReliefStyle::None);
+        let share = CheckButton::new_with_label("Allow Other Devices");
+        share.set_tooltip_text(Some("Serve the preview to other devices on the local network"));
+        let address = LinkButton::new("");
+        address.set_no_show_all(true);
+
+        // Displays the encoding and line endings that the document is saved with.
+        let format = FormatMenus::new();
+
+        // Displays how much of a document has been read while it is being opened.
+        let loading = ProgressBar::new();
+        loading.set_show_text(true);
+        loading.set_valign(Align::Center);
+        loading.set_no_show_all(true);
+
+        container.pack_start(&document, false, false, 6);
+        container.pack_start(&selection, false, false, 6);
+        container.pack_start(&loading, false, false, 6);
+        container.pack_end(&language, false, false, 6);
+        container.pack_end(&format.line_ending, false, false, 0);
+        container.pack_end(&format.encoding, false, false, 0);
+        container.pack_end(&include_code, false, false, 6);
+        container.pack_end(&problems, false, false, 6);
+        container.pack_end(&typewriter, false, false, 6);
+        container.pack_end(&address, false, false, 0);
+        container.pack_end(&share, false, false, 0);
+        container.pack_end(&serve, false, false, 0);
+
+        StatusBar {
+            container,
+            document,
+            selection,
+            include_code,
+            language,
+            problems,
+            typewriter,
+            serve,
+            share,
+            address,
+            format,
+            loading,
+        }
+    }
+
+    /// Displays the number of problems that the linter has found.
+    pub fn set_problems(&self, count: usize) {
+        let label = match count {
+            0 => "No Problems".to_owned(),
+            1 => "1 Problem".to_owned(),
+            count => format!("{} Problems", count),
+        };
+
+        self.problems.set_label(&label);
+    }
+
+    /// Links to the address that the preview is served at, or hides the link if the preview
+    /// is not being served.
+    pub fn set_address(&self, url: Option<&str>) {
+        match url {
+            Some(url) => {
+                self.address.set_uri(url);
+                self.address.set_label(url);
+                self.address.show();
+            }
+            None => self.address.hide(),
+        }
+    }
+
+    /// Selects the dictionary which matches the user's locale, if one is installed.
+    pub fn select_default_language(&self) {
+        let selected = match spell::preferred_language() {
+            Some(language) => self.language.set_active_id(Some(language.as_str())),
+            None => false,
+        };
+
+        if !selected {
+            self.language.set_active(0);
+        }
+    }
+
+    /// Recomputes the statistics of the buffer's text, and of its selected text.
+    pub fn refresh(&self, buffer: &Buffer) {
+        self.refresh_document(buffer);
+        self.refresh_selection(buffer);
+    }
+
+    /// Recomputes the statistics of the buffer's whole text.
+    pub fn refresh_document(&self, buffer: &Buffer) {
+        if let Some(markdown) = get_buffer(buffer) {
+            let stats = Statistics::new(&markdown, self.include_code.get_active());
+            self.document.set_text(&describe(&stats));
+        }
+    }
+
+    /// Recomputes the statistics of the buffer's selected text, which is cheap enough to do
+    /// whenever the selection changes.
+    pub fn refresh_selection(&self, buffer: &Buffer) {
+        let selected = buffer
+            .get_selection_bounds()
+            .and_then(|(start, end)| buffer.get_text(&start, &end, true));
+
+        match selected {
+            Some(ref text) if !text.is_empty() => {
+                let stats = Statistics::new(text, self.include_code.get_active());
+                self.selection.set_text(&format!("Selection: {}", describe(&stats)));
+            }
+            _ => self.selection.set_text(""),
+        }
+    }
+}
+
+/// Formats statistics for display within the status bar.
+fn describe(stats: &Statistics) -> String {
+    format!(
+        "{} words, {} characters, {} lines, {} headings, {} min read",
+        stats.words,
+        stats.characters,
+        stats.lines,
+        stats.headings,
+        stats.reading_time()
+    )
+}
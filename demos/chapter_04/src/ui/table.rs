@@ -0,0 +1,182 @@
+use gtk::*;
+use sourceview::*;
+use table::{self, Command, Position, Table};
+
+/// A deletion which is typed into a table. Text is inserted by GTK itself, so that input
+/// methods may compose it, and the table is realigned afterwards.
+#[derive(Clone, Copy)]
+pub enum Edit {
+    Backspace,
+    Delete,
+}
+
+/// The table which contains the cursor, along with the lines of the buffer that it spans.
+struct Context {
+    first:    i32,
+    last:     i32,
+    table:    Table,
+    position: Position,
+}
+
+impl Context {
+    /// Parses the table that contains the cursor, if the cursor is within a table. Only the
+    /// lines around the cursor which may belong to the table are read, as every line of a
+    /// table contains a pipe, so that large documents are not copied upon every keystroke.
+    fn at_cursor(buffer: &Buffer) -> Option<Context> {
+        let cursor = match buffer.get_insert() {
+            Some(mark) => buffer.get_iter_at_mark(&mark),
+            None => return None,
+        };
+
+        let is_row = |line: i32| line_text(buffer, line, line).map_or(false, |t| t.contains('|'));
+        let line = cursor.get_line();
+        if !is_row(line) {
+            return None;
+        }
+
+        let mut first = line;
+        while first > 0 && is_row(first - 1) {
+            first -= 1;
+        }
+        let mut last = line;
+        while last + 1 < buffer.get_line_count() && is_row(last + 1) {
+            last += 1;
+        }
+
+        let text = match line_text(buffer, first, last) {
+            Some(text) => text,
+            None => return None,
+        };
+
+        let lines: Vec<&str> = text.split('\n').collect();
+        let line = (line - first) as usize;
+        let (start, end) = match table::bounds(&lines, line) {
+            Some(bounds) => bounds,
+            None => return None,
+        };
+
+        let index = cursor.get_line_index() as usize;
+        let (table, position) = Table::parse(&lines[start..end + 1], line - start, index);
+        Some(Context {
+            first: first + start as i32,
+            last: first + end as i32,
+            table,
+            position,
+        })
+    }
+
+    /// The iters at the start and end of the table's lines.
+    fn range(&self, buffer: &Buffer) -> (TextIter, TextIter) {
+        line_range(buffer, self.first, self.last)
+    }
+
+    /// Replaces the table within the buffer with the aligned table, as a single undoable
+    /// action, and then places the cursor at the given position.
+    fn write(&self, buffer: &Buffer, position: Position) {
+        let lines = self.table.lines();
+        let text = lines.join("\n");
+
+        let (mut start, mut end) = self.range(buffer);
+        if start.get_text(&end).map_or(true, |current| current != text) {
+            buffer.begin_user_action();
+            buffer.delete(&mut start, &mut end);
+            buffer.insert(&mut start, &text);
+            buffer.end_user_action();
+        }
+
+        let (line, index) = table::locate(&lines, position);
+        let cursor = buffer.get_iter_at_line_index(self.first + line as i32, index as i32);
+        buffer.place_cursor(&cursor);
+    }
+}
+
+/// Moves the cursor to the next cell of the table at the cursor, adding a row when moving
+/// beyond the last cell. Returns `false` if the cursor is not within a table.
+pub fn next_cell(buffer: &Buffer) -> bool {
+    match Context::at_cursor(buffer) {
+        Some(mut context) => {
+            let position = context.table.next_cell(context.position);
+            context.write(buffer, position);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Moves the cursor to the previous cell of the table at the cursor. Returns `false` if the
+/// cursor is not within a table.
+pub fn previous_cell(buffer: &Buffer) -> bool {
+    match Context::at_cursor(buffer) {
+        Some(mut context) => {
+            let position = context.table.previous_cell(context.position);
+            context.write(buffer, position);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Applies the table command to the table at the cursor, if the cursor is within a table.
+pub fn apply(buffer: &Buffer, command: Command) {
+    if let Some(mut context) = Context::at_cursor(buffer) {
+        let position = context.table.apply(command, context.position);
+        context.write(buffer, position);
+    }
+}
+
+/// Applies the deletion to the table at the cursor, and then aligns the table's columns, as a
+/// single undoable action. Returns `false` if the cursor is not within a table.
+pub fn edit(buffer: &Buffer, edit: Edit) -> bool {
+    if Context::at_cursor(buffer).is_none() {
+        return false;
+    }
+
+    let mut cursor = match buffer.get_insert() {
+        Some(mark) => buffer.get_iter_at_mark(&mark),
+        None => return false,
+    };
+
+    buffer.begin_user_action();
+    match edit {
+        Edit::Backspace => {
+            buffer.backspace(&mut cursor, true, true);
+        }
+        Edit::Delete => {
+            let mut end = cursor.clone();
+            if end.forward_cursor_position() {
+                buffer.delete(&mut cursor, &mut end);
+            }
+        }
+    }
+    realign(buffer);
+    buffer.end_user_action();
+    true
+}
+
+/// Aligns the columns of the table at the cursor, if they are not already aligned. The cursor
+/// is left where it is when nothing needs to change.
+pub fn realign(buffer: &Buffer) {
+    if let Some(context) = Context::at_cursor(buffer) {
+        let (start, end) = context.range(buffer);
+        let aligned = context.table.lines().join("\n");
+        if start.get_text(&end).map_or(false, |current| current != aligned) {
+            context.write(buffer, context.position);
+        }
+    }
+}
+
+/// The iters at the start of the first line and the end of the last line.
+fn line_range(buffer: &Buffer, first: i32, last: i32) -> (TextIter, TextIter) {
+    let start = buffer.get_iter_at_line(first);
+    let mut end = buffer.get_iter_at_line(last);
+    if !end.ends_line() {
+        end.forward_to_line_end();
+    }
+    (start, end)
+}
+
+/// The text of the lines from the first to the last, without the last line's ending.
+fn line_text(buffer: &Buffer, first: i32, last: i32) -> Option<String> {
+    let (start, end) = line_range(buffer, first, last);
+    start.get_text(&end)
+}
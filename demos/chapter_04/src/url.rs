@@ -0,0 +1,102 @@
+use std::path::{Component, Path, PathBuf};
+use std::str;
+
+/// Decodes escaped characters in a URL or link destination, such as `%20`.
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Escapes the characters of a relative path which can not appear in a link destination.
+pub fn percent_encode(path: &str) -> String {
+    path.chars()
+        .map(|c| match c {
+            ' ' => "%20".to_owned(),
+            '(' => "%28".to_owned(),
+            ')' => "%29".to_owned(),
+            '<' => "%3C".to_owned(),
+            '>' => "%3E".to_owned(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Converts a `file://` URI into a local path.
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.trim().trim_left_matches("file://");
+    if path.len() == uri.trim().len() {
+        return None;
+    }
+
+    // A host name may precede the path, as in `file://localhost/home`.
+    path.find('/').map(|slash| PathBuf::from(percent_decode(&path[slash..])))
+}
+
+/// Creates a link destination to the path, relative to the directory. Paths outside of the
+/// directory are linked absolutely.
+pub fn relative_link(dir: &Path, path: &Path) -> String {
+    let relative = match path.strip_prefix(dir) {
+        Ok(relative) => relative,
+        Err(_) => path,
+    };
+
+    let components: Vec<String> = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(percent_encode(&name.to_string_lossy())),
+            Component::RootDir => Some(String::new()),
+            _ => None,
+        })
+        .collect();
+
+    components.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_characters_are_decoded() {
+        assert_eq!(percent_decode("My%20Notes/caf%C3%A9.md"), "My Notes/café.md");
+        assert_eq!(percent_decode("%28a%29"), "(a)");
+        // Incomplete or invalid escapes are kept as they are.
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%2"), "%2");
+        assert_eq!(percent_decode("%zz%41"), "%zzA");
+    }
+
+    #[test]
+    fn file_uris_are_converted_to_paths() {
+        let path = |uri| file_uri_to_path(uri);
+        assert_eq!(path("file:///home/me/a%20b.png"), Some(PathBuf::from("/home/me/a b.png")));
+        assert_eq!(path("file://localhost/tmp/a.png\r\n"), Some(PathBuf::from("/tmp/a.png")));
+        assert_eq!(path("https://example.com/a.png"), None);
+        assert_eq!(path("/tmp/a.png"), None);
+    }
+
+    #[test]
+    fn links_are_relative_to_the_directory() {
+        let dir = Path::new("/notes");
+        let link = |path| relative_link(dir, Path::new(path));
+        assert_eq!(link("/notes/assets/a b.png"), "assets/a%20b.png");
+        assert_eq!(link("/notes/(draft)/<x>.md"), "%28draft%29/%3Cx%3E.md");
+        // Paths outside of the directory are absolute.
+        assert_eq!(link("/pictures/a.png"), "/pictures/a.png");
+    }
+}
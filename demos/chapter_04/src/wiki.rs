@@ -0,0 +1,316 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// The documents of an open folder, which wiki links are resolved against.
+#[derive(Clone, Copy)]
+pub struct Notes<'a> {
+    pub folder: &'a Path,
+    /// The paths of the folder's documents, relative to the folder.
+    pub files:  &'a [PathBuf],
+}
+
+/// A `[[Note Name]]` or `[[Note Name|Label]]` link to another document.
+pub struct WikiLink {
+    /// The byte range of the link within the text, including its brackets.
+    pub start:  usize,
+    pub end:    usize,
+    /// The name of the linked note.
+    pub target: String,
+    /// The text which is displayed for the link.
+    pub label:  String,
+}
+
+/// A line of another document which links to the current document.
+pub struct Backlink {
+    /// The path of the linking document, relative to the folder.
+    pub path: PathBuf,
+    pub line: i32,
+    pub text: String,
+}
+
+impl<'a> Notes<'a> {
+    /// Finds the document that the note name refers to. Names are matched against the
+    /// documents' paths without their extensions, and then against their file names alone,
+    /// ignoring case.
+    pub fn resolve(&self, target: &str) -> Option<&'a PathBuf> {
+        let target = target.trim().to_lowercase();
+        let files = self.files;
+        files
+            .iter()
+            .find(|file| without_extension(file) == target)
+            .or_else(|| files.iter().find(|file| note_name(file).to_lowercase() == target))
+    }
+}
+
+/// The name that other documents use to link to the document, which is its file name without
+/// the extension.
+pub fn note_name(relative: &Path) -> String {
+    relative.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned())
+}
+
+fn without_extension(relative: &Path) -> String {
+    relative.with_extension("").to_string_lossy().to_lowercase()
+}
+
+/// Collects the wiki links within the markdown text. Links within code are ignored.
+pub fn links(markdown: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for line in markdown.split('\n') {
+        let line_start = offset;
+        offset += line.len() + 1;
+
+        let trimmed = line.trim_left();
+        let marker = if trimmed.starts_with("```") {
+            Some("```")
+        } else if trimmed.starts_with("~~~") {
+            Some("~~~")
+        } else {
+            None
+        };
+
+        match (fence, marker) {
+            (None, Some(marker)) => {
+                fence = Some(marker);
+                continue;
+            }
+            (Some(open), Some(marker)) if open == marker => {
+                fence = None;
+                continue;
+            }
+            (Some(_), _) => continue,
+            (None, None) => (),
+        }
+
+        // Indented lines are code blocks, unless they continue a list.
+        if line.starts_with("    ") && !trimmed.starts_with(|c| "-*+".contains(c)) {
+            continue;
+        }
+
+        links_within(line, line_start, &mut links);
+    }
+
+    links
+}
+
+/// Collects the links within a line, skipping over inline code spans.
+fn links_within(line: &str, line_start: usize, links: &mut Vec<WikiLink>) {
+    let mut index = 0;
+    while index < line.len() {
+        let rest = &line[index..];
+        if rest.starts_with('`') {
+            let ticks = rest.len() - rest.trim_left_matches('`').len();
+            let closing = &rest[..ticks];
+            index += ticks;
+            match line[index..].find(closing) {
+                Some(end) => index += end + ticks,
+                None => return,
+            }
+        } else if rest.starts_with("[[") {
+            let inner = &rest[2..];
+            let end = match inner.find("]]") {
+                Some(end) if !inner[..end].contains('[') && !inner[..end].trim().is_empty() => end,
+                _ => {
+                    index += 1;
+                    continue;
+                }
+            };
+
+            let text = &inner[..end];
+            let (target, label) = match text.find('|') {
+                Some(bar) => (text[..bar].trim(), text[bar + 1..].trim()),
+                None => (text.trim(), text.trim()),
+            };
+
+            links.push(WikiLink {
+                start:  line_start + index,
+                end:    line_start + index + end + 4,
+                target: target.to_owned(),
+                label:  label.to_owned(),
+            });
+            index += end + 4;
+        } else {
+            index += rest.chars().next().map_or(1, |c| c.len_utf8());
+        }
+    }
+}
+
+/// Replaces each wiki link with an HTML link to the document that it refers to, whose
+/// destination is created by `href` from the document's path relative to the folder. Links to
+/// documents that do not exist are marked as missing.
+pub fn replace_links(markdown: &str, notes: Option<Notes>, href: &Fn(&Path) -> String) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut last = 0;
+    for link in links(markdown) {
+        output.push_str(&markdown[last..link.start]);
+        match notes.and_then(|notes| notes.resolve(&link.target)) {
+            Some(file) => output.push_str(&format!(
+                "<a class=\"wiki\" href=\"{}\">{}</a>",
+                escape(&href(file)),
+                escape(&link.label)
+            )),
+            None => output.push_str(&format!(
+                "<a class=\"wiki missing\" title=\"No note named {}\">{}</a>",
+                escape(&link.target),
+                escape(&link.label)
+            )),
+        }
+        last = link.end;
+    }
+
+    output.push_str(&markdown[last..]);
+    output
+}
+
+/// Finds every line of the folder's other documents which links to the document.
+pub fn backlinks(notes: Notes, document: &Path) -> Vec<Backlink> {
+    let relative = match document.strip_prefix(notes.folder) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => return Vec::new(),
+    };
+
+    let mut backlinks = Vec::new();
+    for file in notes.files.iter().filter(|&file| *file != relative) {
+        let mut markdown = String::new();
+        let read = File::open(notes.folder.join(file))
+            .and_then(|mut document| document.read_to_string(&mut markdown));
+        if read.is_err() {
+            continue;
+        }
+
+        let mut lines = Vec::new();
+        for link in links(&markdown) {
+            if notes.resolve(&link.target) != Some(&relative) {
+                continue;
+            }
+
+            let line = markdown[..link.start].matches('\n').count();
+            if !lines.contains(&line) {
+                lines.push(line);
+            }
+        }
+
+        for line in lines {
+            let text = markdown.lines().nth(line).unwrap_or("").trim().to_owned();
+            backlinks.push(Backlink { path: file.clone(), line: line as i32, text });
+        }
+    }
+
+    backlinks
+}
+
+/// Escapes the text so that it may be placed within HTML.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    fn targets(markdown: &str) -> Vec<(String, String)> {
+        links(markdown).into_iter().map(|link| (link.target, link.label)).collect()
+    }
+
+    fn files() -> Vec<PathBuf> {
+        let names = ["Index.md", "ideas/Garden.md", "Garden.md"];
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn links_have_targets_and_labels() {
+        let markdown = "See [[Garden]] and [[ideas/Garden | the other one]].";
+        assert_eq!(
+            targets(markdown),
+            vec![
+                ("Garden".to_owned(), "Garden".to_owned()),
+                ("ideas/Garden".to_owned(), "the other one".to_owned()),
+            ]
+        );
+
+        let link = &links(markdown)[0];
+        assert_eq!(&markdown[link.start..link.end], "[[Garden]]");
+    }
+
+    #[test]
+    fn malformed_links_are_ignored() {
+        assert!(targets("[[]] [[ ]] [[a [b]] [[unclosed").is_empty());
+        assert_eq!(targets("[[[Note]]"), vec![("Note".to_owned(), "Note".to_owned())]);
+    }
+
+    #[test]
+    fn links_within_code_are_ignored() {
+        let markdown = "```\n[[Fenced]]\n```\n`[[Span]]` ``[[Double`]]``\n    [[Indented]]\n\
+                        ~~~\n[[Tilde]]\n~~~\n    - [[Listed]]\n[[Linked]]";
+        let names: Vec<String> = targets(markdown).into_iter().map(|(target, _)| target).collect();
+        assert_eq!(names, vec!["Listed", "Linked"]);
+    }
+
+    #[test]
+    fn names_are_resolved() {
+        let files = files();
+        let notes = Notes { folder: Path::new("/notes"), files: &files };
+        assert_eq!(notes.resolve("index"), Some(&files[0]));
+        // Paths are preferred over file names.
+        assert_eq!(notes.resolve(" Ideas/Garden "), Some(&files[1]));
+        assert_eq!(notes.resolve("garden"), Some(&files[2]));
+        assert_eq!(notes.resolve("missing"), None);
+        assert_eq!(note_name(&files[1]), "Garden");
+    }
+
+    #[test]
+    fn links_are_replaced_with_html() {
+        let files = files();
+        let notes = Notes { folder: Path::new("/notes"), files: &files };
+        let href = |file: &Path| format!("{}?a&b", file.display());
+        let html = replace_links("[[Index|<Home>]] [[Nowhere]]", Some(notes), &href);
+        assert_eq!(
+            html,
+            "<a class=\"wiki\" href=\"Index.md?a&amp;b\">&lt;Home&gt;</a> \
+             <a class=\"wiki missing\" title=\"No note named Nowhere\">Nowhere</a>"
+        );
+    }
+
+    #[test]
+    fn backlinks_are_found() {
+        let folder = env::temp_dir().join(format!("wiki-test-{}", ::std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let write = |name: &str, text: &str| {
+            fs::File::create(folder.join(name)).unwrap().write_all(text.as_bytes()).unwrap();
+        };
+        write("Garden.md", "Back to [[Index]].\n");
+        write("Index.md", "[[Index]] links to itself.\n");
+        write("Other.md", "Nothing\n\n  See [[index]] and [[Index|home]].\n`[[Index]]`\n");
+
+        let files = vec![
+            PathBuf::from("Garden.md"),
+            PathBuf::from("Index.md"),
+            PathBuf::from("Missing.md"),
+            PathBuf::from("Other.md"),
+        ];
+        let notes = Notes { folder: &folder, files: &files };
+        let found = backlinks(notes, &folder.join("Index.md"));
+        let outside = backlinks(notes, Path::new("/elsewhere/Index.md"));
+        let _ = fs::remove_dir_all(&folder);
+
+        let found: Vec<_> =
+            found.iter().map(|b| (b.path.clone(), b.line, b.text.clone())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (PathBuf::from("Garden.md"), 0, "Back to [[Index]].".to_owned()),
+                (PathBuf::from("Other.md"), 2, "See [[index]] and [[Index|home]].".to_owned()),
+            ]
+        );
+        assert!(outside.is_empty());
+    }
+}